@@ -0,0 +1,130 @@
+//! Benchmarks for the filters that run over the whole of a paste.
+//!
+//! These modules belong to the binary rather than the library, so they are compiled into the
+//! benchmark directly.
+//! Their unit tests are compiled out with no harness to run them, which leaves unused imports.
+//! What the library exports is benchmarked in requests.rs instead.
+
+use std::io::Read;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/capture.rs"]
+mod capture;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/contents.rs"]
+mod contents;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/scrub.rs"]
+mod scrub;
+
+use crate::contents::Utf8Reader;
+use crate::scrub::Preset;
+
+const ALL_PRESETS: &[Preset] = &[
+    Preset::Jwt,
+    Preset::Email,
+    Preset::Uuid,
+    Preset::Mac,
+    Preset::Ipv4,
+];
+
+/// About a megabyte of log output with a token worth scrubbing on most lines.
+fn log() -> String {
+    (0..10_000)
+        .map(|i| match i % 4 {
+            0 => format!(
+                "2019-05-01T12:00:{:02} INFO request from 10.0.{}.{} completed in {}ms\n",
+                i % 60,
+                i % 256,
+                i % 200,
+                i
+            ),
+            1 => format!(
+                "2019-05-01T12:00:{:02} WARN user{}@example.com has no session \
+                 3f2b8c1e-4a5d-4e6f-9a8b-{:012x}\n",
+                i % 60,
+                i % 50,
+                i
+            ),
+            2 => format!(
+                "2019-05-01T12:00:{:02} DEBUG link up on 00:1a:2b:3c:4d:{:02x} after {} retries\n",
+                i % 60,
+                i % 256,
+                i % 7
+            ),
+            _ => format!(
+                "2019-05-01T12:00:{:02} INFO nothing to see here, just line {} of the log\n",
+                i % 60,
+                i
+            ),
+        })
+        .collect()
+}
+
+/// The log as a coloured terminal would have shown it, with progress redraws.
+fn terminal_log() -> String {
+    log()
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            format!(
+                "\x1b[32m{}\x1b[0m\r\x1b[2K[{:>3}%]\r\x1b[1m{}\x1b[0m\n",
+                &line[..10],
+                i % 100,
+                line
+            )
+        })
+        .collect()
+}
+
+fn scrub(c: &mut Criterion) {
+    let log = log();
+    let mut group = c.benchmark_group("scrub");
+    group.throughput(Throughput::Bytes(log.len() as u64));
+
+    group.bench_function("all presets", |b| {
+        b.iter(|| scrub::scrub(&log, ALL_PRESETS))
+    });
+    group.bench_function("ipv4", |b| b.iter(|| scrub::scrub(&log, &[Preset::Ipv4])));
+
+    group.finish();
+}
+
+fn terminal(c: &mut Criterion) {
+    let output = terminal_log();
+    let mut group = c.benchmark_group("terminal");
+    group.throughput(Throughput::Bytes(output.len() as u64));
+
+    group.bench_function("strip_ansi", |b| b.iter(|| capture::strip_ansi(&output)));
+    group.bench_function("clean_terminal_output", |b| {
+        b.iter(|| capture::clean_terminal_output(&output))
+    });
+
+    group.finish();
+}
+
+fn utf8(c: &mut Criterion) {
+    let log = log();
+    let mut group = c.benchmark_group("utf8");
+    group.throughput(Throughput::Bytes(log.len() as u64));
+
+    group.bench_function("Utf8Reader", |b| {
+        let mut output = Vec::with_capacity(log.len());
+
+        b.iter(|| {
+            output.clear();
+            Utf8Reader::new(log.as_bytes())
+                .read_to_end(&mut output)
+                .unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, scrub, terminal, utf8);
+criterion_main!(benches);
@@ -0,0 +1,63 @@
+//! Benchmarks for what runs once for each paste: reading its options and building the request.
+//!
+//! Unlike the filters, these are all exported by the library.
+
+use std::hint::black_box;
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use patisserie::client::{PasteRequest, PASTERY_URL};
+use patisserie::duration::{self, ONE_WEEK};
+use patisserie::languages;
+use reqwest::Url;
+
+fn durations(c: &mut Criterion) {
+    let mut group = c.benchmark_group("duration");
+
+    group.bench_function("parse", |b| b.iter(|| duration::parse(black_box("2w"))));
+    group.bench_function("parse invalid", |b| {
+        b.iter(|| duration::parse(black_box("2 weeks")))
+    });
+    group.bench_function("format", |b| {
+        b.iter(|| duration::format(black_box(ONE_WEEK)))
+    });
+
+    group.finish();
+}
+
+fn languages(c: &mut Criterion) {
+    let mut group = c.benchmark_group("languages");
+
+    group.bench_function("parse", |b| b.iter(|| languages::parse(black_box("rust"))));
+    // A typo is compared against every language that Pastery knows.
+    group.bench_function("closest", |b| {
+        b.iter(|| languages::closest(black_box("pyton")))
+    });
+    group.bench_function("from_extension", |b| {
+        b.iter(|| languages::from_extension(black_box(Path::new("src/main.rs"))))
+    });
+    group.bench_function("from_shebang", |b| {
+        b.iter(|| languages::from_shebang(black_box("#!/usr/bin/env python3\nprint()\n")))
+    });
+
+    group.finish();
+}
+
+fn requests(c: &mut Criterion) {
+    let api_url = Url::parse(PASTERY_URL).unwrap();
+    let request = PasteRequest::new()
+        .title("logs/build & test.log")
+        .language("rust")
+        .duration(ONE_WEEK)
+        .max_views(10);
+    let mut group = c.benchmark_group("request");
+
+    group.bench_function("url", |b| {
+        b.iter(|| black_box(&request).url(&api_url, black_box("my-api-key")))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, durations, languages, requests);
+criterion_main!(benches);
@@ -6,15 +6,15 @@ use std::{
 };
 
 use failure::Error;
-use phf_codegen::Set;
+use phf_codegen::{Map, Set};
 
 fn main() -> Result<(), Error> {
     let path =
         Path::new(&env::var("OUT_DIR").expect("no OUT_DIR during build?")).join("lang.codegen.rs");
     let mut file = BufWriter::new(File::create(&path)?);
 
-    write!(&mut file, "#[allow(clippy::all)]\n")?;
-    write!(&mut file, "static LANGUAGES: phf::Set<&'static str> = ")?;
+    writeln!(&mut file, "#[allow(clippy::all)]")?;
+    write!(&mut file, "pub static LANGUAGES: phf::Set<&'static str> = ")?;
 
     let mut set = Set::new();
 
@@ -24,12 +24,126 @@ fn main() -> Result<(), Error> {
 
     set.build(&mut file)?;
 
-    write!(&mut file, ";\n")?;
+    writeln!(&mut file, ";")?;
+
+    writeln!(&mut file, "#[allow(clippy::all)]")?;
+    write!(
+        &mut file,
+        "pub static EXTENSIONS: phf::Map<&'static str, &'static str> = "
+    )?;
+
+    let mut map = Map::new();
+
+    for (extension, lang) in EXTENSIONS {
+        assert!(
+            LANGUAGES.contains(lang),
+            "{} is mapped to the unknown language {}",
+            extension,
+            lang
+        );
+        map.entry(*extension, &format!("{:?}", lang));
+    }
+
+    map.build(&mut file)?;
+
+    writeln!(&mut file, ";")?;
 
     Ok(())
 }
 
-const LANGUAGES: &'static [&'static str] = &[
+/// The language of the files with each extension, in lowercase, for the languages that are
+/// known by their extension.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("ada", "ada"),
+    ("adb", "ada"),
+    ("ads", "ada"),
+    ("awk", "awk"),
+    ("bash", "bash"),
+    ("bat", "bat"),
+    ("c", "c"),
+    ("cc", "cpp"),
+    ("cfg", "ini"),
+    ("clj", "clojure"),
+    ("cljs", "clojurescript"),
+    ("cmake", "cmake"),
+    ("coffee", "coffee-script"),
+    ("cpp", "cpp"),
+    ("cs", "csharp"),
+    ("css", "css"),
+    ("cu", "cuda"),
+    ("cxx", "cpp"),
+    ("d", "d"),
+    ("dart", "dart"),
+    ("diff", "diff"),
+    ("el", "common-lisp"),
+    ("erl", "erlang"),
+    ("ex", "elixir"),
+    ("exs", "elixir"),
+    ("f90", "fortran"),
+    ("fs", "fsharp"),
+    ("go", "go"),
+    ("groovy", "groovy"),
+    ("h", "c"),
+    ("hpp", "cpp"),
+    ("hs", "haskell"),
+    ("htm", "html"),
+    ("html", "html"),
+    ("ini", "ini"),
+    ("java", "java"),
+    ("jl", "julia"),
+    ("js", "js"),
+    ("json", "json"),
+    ("kt", "kotlin"),
+    ("kts", "kotlin"),
+    ("lisp", "common-lisp"),
+    ("lua", "lua"),
+    ("m", "objective-c"),
+    ("md", "markdown"),
+    ("mjs", "js"),
+    ("mk", "make"),
+    ("ml", "ocaml"),
+    ("mli", "ocaml"),
+    ("nginx", "nginx"),
+    ("nim", "nimrod"),
+    ("nix", "nixos"),
+    ("patch", "diff"),
+    ("php", "php"),
+    ("pl", "perl"),
+    ("pm", "perl"),
+    ("properties", "properties"),
+    ("proto", "protobuf"),
+    ("ps1", "powershell"),
+    ("py", "python"),
+    ("r", "splus"),
+    ("rb", "rb"),
+    ("rkt", "racket"),
+    ("rs", "rust"),
+    ("rst", "rst"),
+    ("sass", "sass"),
+    ("scala", "scala"),
+    ("scm", "scheme"),
+    ("scss", "scss"),
+    ("sh", "bash"),
+    ("sql", "sql"),
+    ("swift", "swift"),
+    ("tcl", "tcl"),
+    ("tex", "tex"),
+    ("toml", "ini"),
+    ("ts", "ts"),
+    ("txt", "text"),
+    ("v", "verilog"),
+    ("vala", "vala"),
+    ("vb", "vb.net"),
+    ("vhd", "vhdl"),
+    ("vim", "vim"),
+    ("xml", "xml"),
+    ("xsl", "xslt"),
+    ("yaml", "yaml"),
+    ("yml", "yaml"),
+    ("zsh", "bash"),
+];
+
+const LANGUAGES: &[&str] = &[
     "abap",
     "ada",
     "agda",
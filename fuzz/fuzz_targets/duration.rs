@@ -0,0 +1,19 @@
+//! Parse arbitrary durations, and check that every duration that parses is formatted as one that
+//! parses back to the same value.
+//!
+//! Run with `cargo +nightly fuzz run duration` from this directory.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[allow(dead_code)]
+#[path = "../../src/duration.rs"]
+mod duration;
+
+fuzz_target!(|s: &str| {
+    if let Ok(parsed) = duration::parse(s) {
+        assert!(parsed <= duration::ONE_HUNDRED_YEARS);
+        assert_eq!(duration::parse(&duration::format(parsed)).unwrap(), parsed);
+    }
+});
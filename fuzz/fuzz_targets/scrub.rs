@@ -0,0 +1,26 @@
+//! Scrub arbitrary text with every preset, and check that scrubbing it again changes nothing.
+//!
+//! Run with `cargo +nightly fuzz run scrub` from this directory.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[allow(dead_code)]
+#[path = "../../src/scrub.rs"]
+mod scrub;
+
+use crate::scrub::Preset;
+
+const ALL_PRESETS: &[Preset] = &[
+    Preset::Jwt,
+    Preset::Email,
+    Preset::Uuid,
+    Preset::Mac,
+    Preset::Ipv4,
+];
+
+fuzz_target!(|text: &str| {
+    let scrubbed = scrub::scrub(text, ALL_PRESETS);
+    assert_eq!(scrub::scrub(&scrubbed, ALL_PRESETS), scrubbed);
+});
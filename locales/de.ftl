@@ -0,0 +1,90 @@
+# Die Meldungen von patisserie, auf Deutsch.
+
+## Präfixe
+
+error = Fehler: { $message }
+error-in = Fehler: { $path }: { $message }
+warning = Warnung: { $message }
+hint = Tipp: { $hint }
+
+## Fragen
+
+prompt-choices = [j/N]
+answer-yes = j, ja
+
+## Hochladen
+
+stdin-reading = Lese von der Standardeingabe, zum Beenden { $eof } drücken.
+title-shortened = der Titel ist länger als die { $max } Zeichen, die { $backend } erlaubt, und wurde gekürzt zu { $title }
+upload-retried = der Server war nicht erreichbar ({ $error }); der Paste wird erneut gesendet
+not-shortened = die URL konnte nicht gekürzt werden: { $error }
+archive-packed = { $files ->
+        [one] { $files } Datei
+       *[other] { $files } Dateien
+    } gepackt in { $name }: { $size } Bytes, SHA-256 { $digest }
+preflight-continue = Fortfahren?
+languages-refreshed = Pastery kennt { $count } Sprachen, { $new ->
+        [0] die dieser Build alle kennt
+        [one] davon { $new } neu seit diesem Build
+       *[other] davon { $new } neu seit diesem Build
+    }.
+preflight-cancelled = Es wurde nichts hochgeladen
+delete-confirm = { $count ->
+        [one] Dieses Paste
+       *[other] Diese { $count } Pastes
+    } endgültig löschen?
+delete-cancelled = Es wurde nichts gelöscht
+paste-deleted = { $id } wurde gelöscht
+sensitive-skipped = { $path } wurde übersprungen: es passt auf das Muster für vertrauliche Dateien `{ $pattern }` (mit --force trotzdem aufnehmen)
+sensitive-not-uploaded = { $path } wurde nicht hochgeladen: es passt auf das Muster für vertrauliche Dateien `{ $pattern }` (mit --force trotzdem hochladen)
+binary-skipped = { $path } wurde übersprungen: es ist eine Binärdatei
+unsupported-title = Pastes bei { $backend } können keinen Titel haben, daher wird --title ignoriert
+unsupported-duration = Pastes bei { $backend } laufen nicht ab, daher wird --duration ignoriert
+unsupported-max-views = Pastes bei { $backend } lassen sich nicht auf eine Anzahl von Aufrufen begrenzen, daher wird --max-views ignoriert
+fallback-paste-rs = es gibt keinen Pastery-API-Schlüssel, daher wird der Paste auf paste.rs hochgeladen; gib --api-key oder --backend an, um zu wählen
+paste-too-large = der Paste ist { $size } Bytes groß, mehr als die { $max } Bytes, die { $backend } annimmt
+
+## Fehler von Pastery
+
+upload-network = { $service } ist nicht erreichbar: { $message }
+upload-rejected = { $service } hat den Paste abgelehnt: { $message }
+upload-unexpected = { $service } hat unerwartet geantwortet ({ $status })
+upload-redirected = { $service } hat das Hochladen ({ $status }) umgeleitet nach { $to }
+upload-redirected-nowhere = { $service } hat das Hochladen ({ $status }) ohne gültiges Ziel umgeleitet
+hint-network = prüfe die Verbindung und die Proxy-Einstellungen
+hint-unauthorized = prüfe den mit --api-key oder PASTERY_API_KEY angegebenen Schlüssel; deinen findest du unter https://www.pastery.net/account/
+hint-unauthorized-gist = prüfe das mit --github-token oder GITHUB_TOKEN angegebene Token; es braucht den Scope gist
+hint-unauthorized-gitlab = prüfe das mit --gitlab-token oder GITLAB_TOKEN angegebene Token; es braucht den Scope api
+hint-unauthorized-hastebin = prüfe, ob --hastebin-url oder HASTEBIN_URL ein haste-server ist, der Uploads ohne Schlüssel annimmt
+hint-unauthorized-anonymous = { $service } braucht keine Zugangsdaten; prüfe, ob ein Proxy danach fragt
+hint-too-large = lade einen kürzeren Ausschnitt der Eingabe hoch
+hint-invalid-language = lass --lang weg, damit Pastery die Sprache erkennt
+hint-redirect-nowhere = prüfe die URL des Dienstes
+hint-redirect-https = der Dienst akzeptiert nur HTTPS; verwende eine https://-URL
+hint-redirect-login = das sieht nach einer Anmeldeseite aus; prüfe, dass die URL die der API und nicht der Weboberfläche ist und dass sie ohne Anmeldung erreichbar ist
+hint-redirect-followable = verwende die neue URL; Umleitungen werden nur für Pastes im Speicher gefolgt, etwa bei maskierter Eingabe, und nur bis --max-redirects
+hint-redirect-drops-paste = verwende die neue URL; diese Umleitung würde die Anfrage ohne den Paste senden
+batch-failed = { $failed } von { $total } Uploads sind fehlgeschlagen
+batch-failed-skipped = { $failed } von { $total } Uploads sind fehlgeschlagen und { $skipped } wurden übersprungen
+
+## Beobachten
+
+clipboard-watching = Beobachte die Zwischenablage; zum Beenden Strg-C drücken.
+clipboard-confirm = Die Zwischenablage hochladen ({ $lines ->
+        [one] { $lines } Zeile
+       *[other] { $lines } Zeilen
+    }, { $bytes } Bytes)?
+directory-watching = Beobachte { $dir } auf neue Dateien; zum Beenden Strg-C drücken.
+listening = Lausche auf { $address }
+not-accepted = eine Verbindung konnte nicht angenommen werden: { $error }
+
+## Abstürze
+
+crashed = patisserie ist abgestürzt: { $message }
+crashed-again = patisserie ist erneut abgestürzt: { $message }
+crash-written = Ein Absturzbericht wurde nach { $path } geschrieben.
+crash-not-written = Der Absturzbericht konnte nicht nach { $path } geschrieben werden ({ $error }), daher hier:
+crash-report-at = Bitte melde den Absturz unter { $url } und hänge den Bericht an.
+crash-upload = Den Absturzbericht zu Pastery hochladen, um ihn im Fehlerbericht zu verlinken?
+crash-uploaded = Der Absturzbericht wurde hochgeladen nach { $url }
+crash-not-uploaded = Der Absturzbericht konnte nicht hochgeladen werden: { $error }
@@ -0,0 +1,93 @@
+# The messages that patisserie prints, in English. Every other language falls back to these for
+# the messages it does not translate.
+
+## Prefixes
+
+error = error: { $message }
+error-in = error: { $path }: { $message }
+warning = warning: { $message }
+hint = hint: { $hint }
+
+## Questions
+
+# What the user is asked to type, after a question.
+prompt-choices = [y/N]
+# The answers, besides "y" and "yes", that mean yes, separated by commas.
+answer-yes = y, yes
+
+## Uploading
+
+stdin-reading = Reading from standard input, press { $eof } to finish.
+title-shortened = the title is longer than the { $max } characters { $backend } allows and was shortened to { $title }
+upload-retried = could not reach the server ({ $error }); sending the paste again
+not-shortened = the URL could not be shortened: { $error }
+archive-packed = Packed { $files ->
+        [one] { $files } file
+       *[other] { $files } files
+    } into { $name }: { $size } bytes, SHA-256 { $digest }
+preflight-continue = Continue?
+languages-refreshed = Pastery knows { $count } languages, { $new ->
+        [0] all of which this build knows
+        [one] { $new } of which is new since this build
+       *[other] { $new } of which are new since this build
+    }.
+preflight-cancelled = Nothing was uploaded
+delete-confirm = Delete { $count ->
+        [one] this paste
+       *[other] these { $count } pastes
+    } for good?
+delete-cancelled = Nothing was deleted
+paste-deleted = Deleted { $id }
+sensitive-skipped = { $path } was skipped: it matches the sensitive file pattern `{ $pattern }` (use --force to include it)
+sensitive-not-uploaded = { $path } was not uploaded: it matches the sensitive file pattern `{ $pattern }` (use --force to upload it)
+binary-skipped = { $path } was skipped: it is a binary file
+unsupported-title = { $backend } pastes cannot have titles, so --title is ignored
+unsupported-duration = { $backend } pastes do not expire, so --duration is ignored
+unsupported-max-views = { $backend } pastes cannot be limited to a number of views, so --max-views is ignored
+fallback-paste-rs = there is no Pastery API key, so the paste is uploaded to paste.rs; give --api-key or --backend to choose
+paste-too-large = the paste is { $size } bytes, more than the { $max } bytes that { $backend } accepts
+
+## Errors from Pastery
+
+upload-network = Could not reach { $service }: { $message }
+upload-rejected = { $service } rejected the paste: { $message }
+upload-unexpected = { $service } returned an unexpected response ({ $status })
+upload-redirected = { $service } redirected the upload ({ $status }) to { $to }
+upload-redirected-nowhere = { $service } redirected the upload ({ $status }) without a valid location
+hint-network = check your connection and proxy settings
+hint-unauthorized = check the key given with --api-key or PASTERY_API_KEY; you can find yours at https://www.pastery.net/account/
+hint-unauthorized-gist = check the token given with --github-token or GITHUB_TOKEN; it needs the gist scope
+hint-unauthorized-gitlab = check the token given with --gitlab-token or GITLAB_TOKEN; it needs the api scope
+hint-unauthorized-hastebin = check that --hastebin-url or HASTEBIN_URL is a haste-server that accepts uploads without a key
+hint-unauthorized-anonymous = { $service } needs no credentials; check that a proxy is not asking for them
+hint-too-large = upload a smaller excerpt of the input
+hint-invalid-language = leave out --lang to have Pastery detect the language
+hint-redirect-nowhere = check the URL of the endpoint
+hint-redirect-https = the endpoint only accepts HTTPS; use an https:// URL for it
+hint-redirect-login = that looks like a login page; check that the URL is the paste API rather than the web interface, and that it can be reached without signing in
+hint-redirect-followable = use the new URL; redirects are only followed for pastes held in memory, such as scrubbed input, and only up to --max-redirects
+hint-redirect-drops-paste = use the new URL; following this redirect would send the request without the paste
+batch-failed = { $failed } of { $total } uploads failed
+batch-failed-skipped = { $failed } of { $total } uploads failed and { $skipped } were skipped
+
+## Watching
+
+clipboard-watching = Watching the clipboard; press Ctrl-C to stop.
+clipboard-confirm = Upload the clipboard ({ $lines ->
+        [one] { $lines } line
+       *[other] { $lines } lines
+    }, { $bytes } bytes)?
+directory-watching = Watching { $dir } for new files; press Ctrl-C to stop.
+listening = Listening on { $address }
+not-accepted = could not accept a connection: { $error }
+
+## Crashes
+
+crashed = patisserie crashed: { $message }
+crashed-again = patisserie also panicked: { $message }
+crash-written = A crash report was written to { $path }.
+crash-not-written = The crash report could not be written to { $path } ({ $error }), so here it is:
+crash-report-at = Please report the crash at { $url } and attach the report.
+crash-upload = Upload the crash report to Pastery, to link from the bug?
+crash-uploaded = The crash report was uploaded to { $url }
+crash-not-uploaded = The crash report could not be uploaded: { $error }
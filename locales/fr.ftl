@@ -0,0 +1,90 @@
+# Les messages de patisserie, en français.
+
+## Préfixes
+
+error = erreur : { $message }
+error-in = erreur : { $path } : { $message }
+warning = attention : { $message }
+hint = conseil : { $hint }
+
+## Questions
+
+prompt-choices = [o/N]
+answer-yes = o, oui
+
+## Envoi
+
+stdin-reading = Lecture de l’entrée standard, appuyez sur { $eof } pour terminer.
+title-shortened = le titre dépasse les { $max } caractères autorisés par { $backend } et a été raccourci en { $title }
+upload-retried = impossible de joindre le serveur ({ $error }) ; nouvel envoi du collage
+not-shortened = l’URL n’a pas pu être raccourcie : { $error }
+archive-packed = { $files ->
+        [one] { $files } fichier archivé
+       *[other] { $files } fichiers archivés
+    } dans { $name } : { $size } octets, SHA-256 { $digest }
+preflight-continue = Continuer ?
+languages-refreshed = Pastery connaît { $count } langages, { $new ->
+        [0] que cette version connaît tous
+        [one] dont { $new } nouveau depuis cette version
+       *[other] dont { $new } nouveaux depuis cette version
+    }.
+preflight-cancelled = Rien n’a été envoyé
+delete-confirm = Supprimer { $count ->
+        [one] ce paste
+       *[other] ces { $count } pastes
+    } définitivement ?
+delete-cancelled = Rien n’a été supprimé
+paste-deleted = { $id } a été supprimé
+sensitive-skipped = { $path } a été ignoré : il correspond au motif de fichier sensible `{ $pattern }` (utilisez --force pour l’inclure)
+sensitive-not-uploaded = { $path } n’a pas été envoyé : il correspond au motif de fichier sensible `{ $pattern }` (utilisez --force pour l’envoyer)
+binary-skipped = { $path } a été ignoré : c’est un fichier binaire
+unsupported-title = les pastes de { $backend } ne peuvent pas avoir de titre, --title est donc ignoré
+unsupported-duration = les pastes de { $backend } n’expirent pas, --duration est donc ignoré
+unsupported-max-views = les pastes de { $backend } ne peuvent pas être limités en nombre de vues, --max-views est donc ignoré
+fallback-paste-rs = il n'y a pas de clé d'API Pastery, donc le paste est envoyé sur paste.rs ; donnez --api-key ou --backend pour choisir
+paste-too-large = le paste fait { $size } octets, plus que les { $max } octets acceptés par { $backend }
+
+## Erreurs de Pastery
+
+upload-network = Impossible de joindre { $service } : { $message }
+upload-rejected = { $service } a refusé le paste : { $message }
+upload-unexpected = { $service } a renvoyé une réponse inattendue ({ $status })
+upload-redirected = { $service } a redirigé l’envoi ({ $status }) vers { $to }
+upload-redirected-nowhere = { $service } a redirigé l’envoi ({ $status }) sans adresse valide
+hint-network = vérifiez votre connexion et vos réglages de proxy
+hint-unauthorized = vérifiez la clé donnée avec --api-key ou PASTERY_API_KEY ; la vôtre se trouve sur https://www.pastery.net/account/
+hint-unauthorized-gist = vérifiez le jeton donné avec --github-token ou GITHUB_TOKEN ; il lui faut la portée gist
+hint-unauthorized-gitlab = vérifiez le jeton donné avec --gitlab-token ou GITLAB_TOKEN ; il lui faut la portée api
+hint-unauthorized-hastebin = vérifiez que --hastebin-url ou HASTEBIN_URL est un haste-server qui accepte les envois sans clé
+hint-unauthorized-anonymous = { $service } ne demande aucun identifiant ; vérifiez qu'un proxy ne les demande pas
+hint-too-large = envoyez un extrait plus court de l’entrée
+hint-invalid-language = retirez --lang pour que Pastery détecte le langage
+hint-redirect-nowhere = vérifiez l’URL du service
+hint-redirect-https = le service n’accepte que HTTPS ; utilisez une URL en https://
+hint-redirect-login = cela ressemble à une page de connexion ; vérifiez que l’URL est celle de l’API et non de l’interface web, et qu’elle est accessible sans se connecter
+hint-redirect-followable = utilisez la nouvelle URL ; les redirections ne sont suivies que pour les pastes gardés en mémoire, comme une entrée masquée, et seulement jusqu’à --max-redirects
+hint-redirect-drops-paste = utilisez la nouvelle URL ; suivre cette redirection enverrait la requête sans le paste
+batch-failed = { $failed } envois sur { $total } ont échoué
+batch-failed-skipped = { $failed } envois sur { $total } ont échoué et { $skipped } ont été sautés
+
+## Surveillance
+
+clipboard-watching = Surveillance du presse-papiers ; appuyez sur Ctrl-C pour arrêter.
+clipboard-confirm = Envoyer le presse-papiers ({ $lines ->
+        [one] { $lines } ligne
+       *[other] { $lines } lignes
+    }, { $bytes } octets) ?
+directory-watching = Surveillance des nouveaux fichiers dans { $dir } ; appuyez sur Ctrl-C pour arrêter.
+listening = En écoute sur { $address }
+not-accepted = impossible d’accepter une connexion : { $error }
+
+## Plantages
+
+crashed = patisserie a planté : { $message }
+crashed-again = patisserie a aussi paniqué : { $message }
+crash-written = Un rapport de plantage a été écrit dans { $path }.
+crash-not-written = Le rapport de plantage n’a pas pu être écrit dans { $path } ({ $error }), le voici :
+crash-report-at = Merci de signaler le plantage sur { $url } en joignant le rapport.
+crash-upload = Envoyer le rapport de plantage sur Pastery, pour le lier au bogue ?
+crash-uploaded = Le rapport de plantage a été envoyé sur { $url }
+crash-not-uploaded = Le rapport de plantage n’a pas pu être envoyé : { $error }
@@ -0,0 +1,370 @@
+use std::str::FromStr;
+
+use failure::{err_msg, format_err, Error};
+#[cfg(feature = "email")]
+use gethostname::gethostname;
+#[cfg(feature = "email")]
+use lettre::message::Mailbox;
+#[cfg(feature = "email")]
+use lettre::{Message, SendmailTransport, SmtpTransport, Transport};
+use reqwest::Client;
+use secrecy::{ExposeSecret, SecretString};
+use serde_json::{json, Value};
+
+use crate::har::{self, Captured, Har};
+use crate::Paste;
+
+/// The default message sent when announcing a paste.
+pub const DEFAULT_TEMPLATE: &str = "{title} ({lang}): {url} (expires in {expires})";
+
+/// Somewhere to post the URL of a paste once it has been uploaded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+    /// A comment on a GitHub issue or pull request.
+    GitHub {
+        owner: String,
+        repo: String,
+        number: u64,
+    },
+
+    /// A message to a Slack incoming webhook.
+    Slack,
+
+    /// A message to a Discord webhook.
+    Discord,
+
+    /// An email to the given address.
+    #[cfg(feature = "email")]
+    Email(String),
+}
+
+impl FromStr for Target {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let invalid = || {
+            format_err!(
+                "Invalid announce target {}, expected slack, discord, or \
+                 github:<owner>/<repo>#<number>",
+                s
+            )
+        };
+
+        if s == "slack" {
+            Ok(Target::Slack)
+        } else if s == "discord" {
+            Ok(Target::Discord)
+        } else if let Some(issue) = s.strip_prefix("github:") {
+            let (repo, number) = issue.split_once('#').ok_or_else(invalid)?;
+            let (owner, repo) = repo.split_once('/').ok_or_else(invalid)?;
+            let number = number.parse::<u64>().map_err(|_| invalid())?;
+
+            if owner.is_empty() || repo.is_empty() || repo.contains('/') {
+                return Err(invalid());
+            }
+
+            Ok(Target::GitHub {
+                owner: owner.into(),
+                repo: repo.into(),
+                number,
+            })
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+/// Credentials and formatting shared by all announcements.
+pub struct Announcer<'a> {
+    pub client: &'a Client,
+    pub har: Option<&'a Har>,
+    pub template: &'a str,
+    pub github_token: Option<&'a SecretString>,
+    pub slack_webhook: Option<&'a SecretString>,
+    pub discord_webhook: Option<&'a SecretString>,
+    #[cfg(feature = "email")]
+    pub smtp_url: Option<&'a SecretString>,
+    #[cfg(feature = "email")]
+    pub email_from: Option<&'a str>,
+    #[cfg(feature = "email")]
+    pub tor: bool,
+}
+
+impl<'a> Announcer<'a> {
+    /// Post the paste's URL to the target.
+    pub fn announce(&self, target: &Target, paste: &Paste) -> Result<(), Error> {
+        let message = render(self.template, paste);
+
+        match target {
+            Target::GitHub {
+                owner,
+                repo,
+                number,
+            } => {
+                let token = self.github_token.ok_or_else(|| {
+                    err_msg("Announcing to GitHub requires --github-token or GITHUB_TOKEN")
+                })?;
+
+                let url = format!(
+                    "https://api.github.com/repos/{}/{}/issues/{}/comments",
+                    owner, repo, number
+                );
+
+                let body = json!({ "body": message });
+                let request = self
+                    .client
+                    .post(&url)
+                    .bearer_auth(token.expose_secret())
+                    .header("Accept", "application/vnd.github+json")
+                    .header(
+                        "User-Agent",
+                        concat!("patisserie/", env!("CARGO_PKG_VERSION")),
+                    )
+                    .json(&body);
+                let mut rsp = har::send(self.har, self.client, request, self.captured(&body))?;
+
+                if !rsp.status().is_success() {
+                    return Err(format_err!(
+                        "Could not comment on {}/{}#{}: {} {}",
+                        owner,
+                        repo,
+                        number,
+                        rsp.status(),
+                        rsp.text().unwrap_or_default()
+                    ));
+                }
+
+                Ok(())
+            }
+
+            Target::Slack => {
+                let webhook = self.slack_webhook.ok_or_else(|| {
+                    err_msg("Announcing to Slack requires --slack-webhook or SLACK_WEBHOOK_URL")
+                })?;
+
+                self.post_webhook("Slack", webhook, &json!({ "text": message }))
+            }
+
+            Target::Discord => {
+                let webhook = self.discord_webhook.ok_or_else(|| {
+                    err_msg(
+                        "Announcing to Discord requires --discord-webhook or DISCORD_WEBHOOK_URL",
+                    )
+                })?;
+
+                self.post_webhook("Discord", webhook, &json!({ "content": message }))
+            }
+
+            #[cfg(feature = "email")]
+            Target::Email(to) => self.send_email(to, paste, message),
+        }
+    }
+
+    #[cfg(feature = "email")]
+    fn send_email(&self, to: &str, paste: &Paste, body: String) -> Result<(), Error> {
+        // Neither SMTP nor the local mail system can be routed through the Tor proxy.
+        if self.tor {
+            return Err(err_msg("Refusing to send email while --tor is in use"));
+        }
+
+        let from = match self.email_from {
+            Some(from) => from.to_owned(),
+            None => default_sender(),
+        };
+
+        let email = Message::builder()
+            .from(
+                from.parse::<Mailbox>()
+                    .map_err(|e| format_err!("Invalid sender address {}: {}", from, e))?,
+            )
+            .to(to
+                .parse::<Mailbox>()
+                .map_err(|e| format_err!("Invalid email address {}: {}", to, e))?)
+            .subject(paste.title.as_deref().unwrap_or("Paste"))
+            .body(body)?;
+
+        match self.smtp_url {
+            Some(url) => {
+                SmtpTransport::from_url(url.expose_secret())
+                    .map_err(|e| format_err!("Invalid SMTP URL: {}", e))?
+                    .build()
+                    .send(&email)
+                    .map_err(|e| format_err!("Could not send email to {}: {}", to, e))?;
+            }
+
+            None => {
+                SendmailTransport::new().send(&email).map_err(|e| {
+                    format_err!("Could not send email to {} with sendmail: {}", to, e)
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// What --har keeps of a JSON body.
+    fn captured(&self, body: &Value) -> Option<Captured> {
+        self.har
+            .map(|_| Captured::bytes(body.to_string().as_bytes()))
+    }
+
+    fn post_webhook(
+        &self,
+        service: &str,
+        webhook: &SecretString,
+        body: &Value,
+    ) -> Result<(), Error> {
+        // The webhook URL is itself the credential, so keep it out of any error messages.
+        let request = self.client.post(webhook.expose_secret()).json(body);
+        let mut rsp =
+            har::send(self.har, self.client, request, self.captured(body)).map_err(|e| {
+                format_err!(
+                    "Could not send the {} announcement: {}",
+                    service,
+                    crate::describe_request_error(&e)
+                )
+            })?;
+
+        if !rsp.status().is_success() {
+            return Err(format_err!(
+                "Could not send the {} announcement: {} {}",
+                service,
+                rsp.status(),
+                rsp.text().unwrap_or_default()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// The address mail is sent from when no sender is configured, i.e. `user@host`.
+#[cfg(feature = "email")]
+fn default_sender() -> String {
+    format!("{}@{}", crate::user_name(), gethostname().to_string_lossy())
+}
+
+/// Fill in the `{url}`, `{id}`, `{title}`, `{lang}`, and `{expires}` placeholders of a template.
+///
+/// The template is read once, so placeholders in the details, such as a file named
+/// `{expires}.log`, are left as they are. Anything else in braces is too.
+pub fn render(template: &str, paste: &Paste) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = rest.find('}').map(|end| end + 1);
+        let value = end.and_then(|end| match &rest[..end] {
+            "{url}" => Some(paste.url.clone()),
+            "{id}" => Some(paste.id.clone().unwrap_or_default()),
+            "{title}" => Some(paste.title.clone().unwrap_or_else(|| "Paste".into())),
+            "{lang}" => Some(paste.lang.into()),
+            "{expires}" => Some(expires(paste)),
+            _ => None,
+        });
+        match (value, end) {
+            (Some(value), Some(end)) => {
+                rendered.push_str(&value);
+                rest = &rest[end..];
+            }
+            _ => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+
+    rendered
+}
+
+/// When the paste expires, such as `1d` or, with a view limit, `1d or 10 views`.
+fn expires(paste: &Paste) -> String {
+    let duration = crate::duration::format(paste.duration);
+    match paste.max_views {
+        Some(1) => format!("{} or 1 view", duration),
+        Some(views) => format!("{} or {} views", duration, views),
+        None => duration,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_targets() {
+        assert_eq!(
+            "github:brennie/patisserie#12".parse::<Target>().unwrap(),
+            Target::GitHub {
+                owner: "brennie".into(),
+                repo: "patisserie".into(),
+                number: 12,
+            }
+        );
+
+        assert!("github:brennie/patisserie".parse::<Target>().is_err());
+        assert!("github:patisserie#12".parse::<Target>().is_err());
+        assert!("github:brennie/patisserie#pr".parse::<Target>().is_err());
+        assert!("github:/patisserie#12".parse::<Target>().is_err());
+        assert!("gitlab:brennie/patisserie#12".parse::<Target>().is_err());
+
+        assert_eq!("slack".parse::<Target>().unwrap(), Target::Slack);
+        assert_eq!("discord".parse::<Target>().unwrap(), Target::Discord);
+        assert!("irc".parse::<Target>().is_err());
+    }
+
+    #[test]
+    fn render_templates() {
+        let paste = Paste {
+            url: "https://www.pastery.net/abcdef/".into(),
+            id: Some("abcdef".into()),
+            title: Some("build.log".into()),
+            lang: "text",
+            duration: crate::duration::ONE_DAY,
+            max_views: None,
+        };
+
+        assert_eq!(
+            render(DEFAULT_TEMPLATE, &paste),
+            "build.log (text): https://www.pastery.net/abcdef/ (expires in 1d)"
+        );
+        assert_eq!(
+            render(
+                "{id} expires in {expires}",
+                &Paste {
+                    max_views: Some(10),
+                    ..paste.clone()
+                }
+            ),
+            "abcdef expires in 1d or 10 views"
+        );
+        assert_eq!(
+            render("Full logs ({lang}): {url}", &paste),
+            "Full logs (text): https://www.pastery.net/abcdef/"
+        );
+        assert_eq!(
+            render(
+                "{title} <{url}>",
+                &Paste {
+                    title: None,
+                    ..paste.clone()
+                }
+            ),
+            "Paste <https://www.pastery.net/abcdef/>"
+        );
+        assert_eq!(
+            render(
+                "{title} {{lang}} {nope} {",
+                &Paste {
+                    title: Some("{expires}.log".into()),
+                    ..paste
+                }
+            ),
+            "{expires}.log {text} {nope} {"
+        );
+    }
+}
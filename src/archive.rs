@@ -0,0 +1,226 @@
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use failure::{err_msg, format_err, Error};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::walk::Filter;
+
+/// The kinds of archive that --archive can pack files into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    TarGz,
+    Zip,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "tar.gz" | "tgz" => Ok(Format::TarGz),
+            "zip" => Ok(Format::Zip),
+            _ => Err(format_err!(
+                "Unknown archive format {}, expected one of tar.gz, zip",
+                s
+            )),
+        }
+    }
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::TarGz => "tar.gz",
+            Format::Zip => "zip",
+        }
+    }
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            Format::TarGz => "application/gzip",
+            Format::Zip => "application/zip",
+        }
+    }
+}
+
+/// Files packed into an archive.
+pub struct Archive {
+    pub name: String,
+    pub contents: Vec<u8>,
+    pub files: usize,
+}
+
+/// A file to pack, and the name it is given in the archive.
+struct Entry {
+    source: PathBuf,
+    name: String,
+}
+
+/// Pack files and directories into an archive.
+///
+/// Directories are walked with `filter`, which leaves out hidden and ignored files, build
+/// artifacts, and files on the denylist.
+pub fn pack(paths: &[PathBuf], format: Format, filter: &Filter) -> Result<Archive, Error> {
+    let mut entries = Vec::new();
+    for path in paths {
+        collect(path, filter, &mut entries)?;
+    }
+
+    if entries.is_empty() {
+        return Err(err_msg(
+            "There is nothing to archive: every file was ignored",
+        ));
+    }
+
+    let contents = match format {
+        Format::TarGz => tar_gz(&entries)?,
+        Format::Zip => zip(&entries)?,
+    };
+
+    // A single directory names the archive, as in `repro.tar.gz`.
+    let stem = match paths {
+        [path] if path.is_dir() => fs::canonicalize(path).ok().and_then(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        }),
+        _ => None,
+    };
+
+    Ok(Archive {
+        name: format!(
+            "{}.{}",
+            stem.as_deref().unwrap_or("patisserie-archive"),
+            format.extension()
+        ),
+        contents,
+        files: entries.len(),
+    })
+}
+
+fn collect(path: &Path, filter: &Filter, entries: &mut Vec<Entry>) -> Result<(), Error> {
+    if !path.is_dir() {
+        let name = path
+            .file_name()
+            .ok_or_else(|| format_err!("{} is not a file or directory", path.display()))?;
+
+        entries.push(Entry {
+            source: path.to_owned(),
+            name: name.to_string_lossy().into_owned(),
+        });
+        return Ok(());
+    }
+
+    // Entries are named relative to the directory, under its own name unless it is `.`.
+    let base = path.file_name().map(PathBuf::from).unwrap_or_default();
+
+    for source in filter.files(path)? {
+        let relative = source.strip_prefix(path).unwrap_or(&source);
+        let name = base
+            .join(relative)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        entries.push(Entry { source, name });
+    }
+
+    Ok(())
+}
+
+fn tar_gz(entries: &[Entry]) -> Result<Vec<u8>, Error> {
+    let mut tar = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+
+    for entry in entries {
+        tar.append_path_with_name(&entry.source, &entry.name)
+            .map_err(|e| format_err!("Could not archive {}: {}", entry.source.display(), e))?;
+    }
+
+    Ok(tar.into_inner()?.finish()?)
+}
+
+fn zip(entries: &[Entry]) -> Result<Vec<u8>, Error> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for entry in entries {
+        let mut file = fs::File::open(&entry.source)
+            .map_err(|e| format_err!("Could not archive {}: {}", entry.source.display(), e))?;
+
+        zip.start_file(entry.name.as_str(), options)?;
+        io::copy(&mut file, &mut zip)?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+    use crate::denylist::Denylist;
+
+    #[test]
+    fn pack_directories() {
+        let dir = env::temp_dir().join(format!("patisserie-archive-{}", std::process::id()));
+        let repro = dir.join("repro");
+        fs::create_dir_all(repro.join("src")).unwrap();
+        fs::write(repro.join(".gitignore"), "target/\n").unwrap();
+        fs::create_dir_all(repro.join("target")).unwrap();
+        fs::write(repro.join("target/output"), "built").unwrap();
+        fs::write(repro.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(repro.join(".env"), "SECRET=1\n").unwrap();
+        fs::write(repro.join("server.key"), "secret").unwrap();
+        fs::write(dir.join("notes.txt"), "notes").unwrap();
+
+        let denylist = Denylist::new(&[]).unwrap();
+        let paths = [repro.clone(), dir.join("notes.txt")];
+        let filter = |denylist| Filter {
+            include: &[],
+            exclude: &[],
+            denylist,
+            hidden: false,
+            no_ignore: false,
+            follow_symlinks: false,
+            max_depth: None,
+        };
+
+        let archive = pack(&paths, Format::TarGz, &filter(Some(&denylist))).unwrap();
+        assert_eq!(archive.name, "patisserie-archive.tar.gz");
+        assert_eq!(archive.files, 2);
+
+        let mut tar = tar::Archive::new(GzDecoder::new(archive.contents.as_slice()));
+        let mut names = vec![];
+        for entry in tar.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().into_owned();
+            if name == "repro/src/main.rs" {
+                let mut contents = String::new();
+                entry.read_to_string(&mut contents).unwrap();
+                assert_eq!(contents, "fn main() {}\n");
+            }
+            names.push(name);
+        }
+        assert_eq!(names, ["repro/src/main.rs", "notes.txt"]);
+
+        // Without the denylist, only the hidden .env is still left out.
+        let archive = pack(&paths[..1], Format::Zip, &filter(None)).unwrap();
+        assert_eq!(archive.name, "repro.zip");
+        let zip = zip::ZipArchive::new(Cursor::new(archive.contents)).unwrap();
+        let mut names = zip.file_names().collect::<Result<Vec<_>, _>>().unwrap();
+        names.sort();
+        assert_eq!(names, ["repro/server.key", "repro/src/main.rs"]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
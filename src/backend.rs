@@ -0,0 +1,381 @@
+//! The paste services that patisserie can upload to, chosen with --backend.
+
+#[cfg(feature = "backends")]
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use failure::{err_msg, format_err, Error};
+use reqwest::{Body, Client, RequestBuilder, StatusCode, Url};
+
+use crate::duration::ONE_DAY;
+use crate::error::UploadError;
+use crate::i18n::tr;
+use crate::{api_url, client, generate_url, Options, Paste, PasteBody};
+
+#[cfg(feature = "backends")]
+mod form;
+#[cfg(feature = "backends")]
+mod gist;
+#[cfg(feature = "backends")]
+mod gitlab;
+#[cfg(feature = "backends")]
+mod hastebin;
+mod paste_rs;
+#[cfg(feature = "privatebin")]
+mod privatebin;
+#[cfg(feature = "backends")]
+mod termbin;
+
+#[cfg(feature = "backends")]
+pub use self::gitlab::Visibility;
+#[cfg(feature = "backends")]
+pub use self::termbin::Address as TermbinAddress;
+
+/// What a service accepts, which is checked or adjusted before uploading.
+pub struct Capabilities {
+    /// The largest paste, in bytes, if the service has a known limit.
+    pub max_size: Option<u64>,
+    /// Whether pastes can have titles.
+    pub titles: bool,
+    /// The longest title, in characters, if there is a limit.
+    pub max_title_len: Option<usize>,
+    /// Whether pastes can expire after --duration.
+    pub expiry: bool,
+    /// Whether pastes can expire after --max-views.
+    pub max_views: bool,
+    /// Whether one paste can hold several files, so that they are uploaded together.
+    pub multiple_files: bool,
+    /// Whether the pastes under the API key can be listed, so that an upload whose response was
+    /// lost can be looked for before it is sent again.
+    pub listing: bool,
+}
+
+impl Capabilities {
+    /// Shorten a title that is too long, ending it with an ellipsis.
+    ///
+    /// Returns `None` if the title is within the limit.
+    pub fn truncate_title(&self, title: &str) -> Option<String> {
+        let (end, _) = title.char_indices().nth(self.max_title_len?)?;
+        let (end, _) = title[..end].char_indices().last()?;
+
+        Some(format!("{}…", &title[..end]))
+    }
+}
+
+/// Warn about the options that the --backend cannot honour, which are left out of the upload.
+pub fn warn_unsupported(options: &Options, capabilities: &Capabilities) {
+    let backend = kind(options).to_string();
+    let mut unsupported = vec![];
+
+    if options.title.is_some() && !capabilities.titles {
+        unsupported.push(tr!("unsupported-title", backend = backend.as_str()));
+    }
+    // --duration always has a value, so only one other than the default is noticed.
+    if options.duration != ONE_DAY && !capabilities.expiry {
+        unsupported.push(tr!("unsupported-duration", backend = backend.as_str()));
+    }
+    if options.max_views.is_some() && !capabilities.max_views {
+        unsupported.push(tr!("unsupported-max-views", backend = backend.as_str()));
+    }
+
+    for message in unsupported {
+        eprintln!("{}", tr!("warning", message = message));
+    }
+}
+
+/// Check that the URL of a self-hosted service, such as --gitlab-url, is a web server that can
+/// have paths added to it.
+pub fn parse_instance_url(s: &str) -> Result<Url, Error> {
+    let url = Url::parse(s)?;
+    if url.cannot_be_a_base() || !matches!(url.scheme(), "http" | "https") {
+        return Err(format_err!("{} is not the URL of a web server", s));
+    }
+    Ok(url)
+}
+
+/// `url` made into a directory, so that paths joined to it are added rather than replacing its
+/// last segment.
+pub fn directory(url: &Url) -> Url {
+    let mut url = url.clone();
+    url.path_segments_mut()
+        .expect("the URL is checked by parse_instance_url")
+        .pop_if_empty()
+        .push("");
+    url
+}
+
+/// The name of a paste that is not read from a file, for the services that name each file.
+#[cfg(feature = "backends")]
+const DEFAULT_FILE_NAME: &str = "paste.txt";
+
+/// The files of a paste with their names, for the services that can hold several.
+///
+/// A paste of a single file is named after it, and one that was not read from a file is named
+/// `DEFAULT_FILE_NAME`. The names have to differ.
+#[cfg(feature = "backends")]
+fn named_files(options: &Options, body: PasteBody) -> Result<Vec<(String, String)>, Error> {
+    let files = match body {
+        PasteBody::Files(files) => files,
+        body => {
+            let name = options
+                .paths
+                .first()
+                .and_then(|path| path.file_name())
+                .map_or_else(
+                    || DEFAULT_FILE_NAME.into(),
+                    |name| name.to_string_lossy().into_owned(),
+                );
+            vec![(name, body.into_text()?)]
+        }
+    };
+
+    let mut names = HashSet::new();
+    for (name, _) in &files {
+        if !names.insert(name) {
+            return Err(format_err!(
+                "More than one file is named {}, but {} can only hold one of them",
+                name,
+                kind(options)
+            ));
+        }
+    }
+
+    Ok(files)
+}
+
+/// A service that pastes are uploaded to.
+pub trait Backend: Send + Sync {
+    fn capabilities(&self) -> &Capabilities;
+
+    /// Check that the options have what is needed to upload, such as credentials.
+    fn check(&self, options: &Options) -> Result<(), Error>;
+
+    /// The URL that pastes are uploaded to, without any credentials.
+    fn api_url(&self, options: &Options) -> Url;
+
+    /// Turn the contents of a paste into what is sent, for services that want more than the
+    /// contents themselves.
+    ///
+    /// Only the services that can hold `multiple_files` are given `PasteBody::Files`, which
+    /// they have to turn into a single body.
+    fn wrap(&self, _options: &Options, body: PasteBody) -> Result<PasteBody, Error> {
+        Ok(body)
+    }
+
+    /// The host and port that the contents are written to over a plain TCP connection, for
+    /// the services that take pastes that way rather than over HTTP.
+    ///
+    /// The reply is given to `parse` as if it were a successful response.
+    fn tcp_address(&self, _options: &Options) -> Option<(String, u16)> {
+        None
+    }
+
+    /// The request that uploads `body` with the metadata in `options`.
+    fn request(&self, client: &Client, options: &Options, body: Body) -> RequestBuilder;
+
+    /// The paste that was created, from the response to the request.
+    ///
+    /// Failures are an `UploadError`, so that they exit with the right status.
+    fn parse(&self, status: StatusCode, text: &str, options: &Options) -> Result<Paste, Error>;
+}
+
+/// The services that --backend can choose.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Pastery,
+    #[cfg(feature = "backends")]
+    Gist,
+    #[cfg(feature = "backends")]
+    GitLab,
+    #[cfg(feature = "backends")]
+    Hastebin,
+    PasteRs,
+    #[cfg(feature = "privatebin")]
+    PrivateBin,
+    #[cfg(feature = "backends")]
+    Termbin,
+    #[cfg(feature = "backends")]
+    Form,
+}
+
+/// The names that --backend accepts, for the services that are built in.
+const NAMES: &[(&str, Kind)] = &[
+    ("pastery", Kind::Pastery),
+    #[cfg(feature = "backends")]
+    ("gist", Kind::Gist),
+    #[cfg(feature = "backends")]
+    ("gitlab", Kind::GitLab),
+    #[cfg(feature = "backends")]
+    ("hastebin", Kind::Hastebin),
+    ("paste.rs", Kind::PasteRs),
+    #[cfg(feature = "privatebin")]
+    ("privatebin", Kind::PrivateBin),
+    #[cfg(feature = "backends")]
+    ("termbin", Kind::Termbin),
+    #[cfg(feature = "backends")]
+    ("form", Kind::Form),
+];
+
+impl FromStr for Kind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match NAMES.iter().find(|(name, _)| *name == s) {
+            Some(&(_, kind)) => Ok(kind),
+            None => Err(format_err!(
+                "Unknown backend {}, expected one of {}",
+                s,
+                NAMES
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Pastery => "Pastery",
+            #[cfg(feature = "backends")]
+            Kind::Gist => "GitHub Gist",
+            #[cfg(feature = "backends")]
+            Kind::GitLab => "GitLab",
+            #[cfg(feature = "backends")]
+            Kind::Hastebin => "hastebin",
+            Kind::PasteRs => "paste.rs",
+            #[cfg(feature = "privatebin")]
+            Kind::PrivateBin => "PrivateBin",
+            #[cfg(feature = "backends")]
+            Kind::Termbin => "termbin",
+            #[cfg(feature = "backends")]
+            Kind::Form => "Form pastebin",
+        })
+    }
+}
+
+/// The --backend, or paste.rs if none is chosen and there is no Pastery API key, so that
+/// uploading works without any configuration.
+pub fn kind(options: &Options) -> Kind {
+    match options.backend {
+        Some(kind) => kind,
+        None if options.api_key.is_none() => Kind::PasteRs,
+        None => Kind::Pastery,
+    }
+}
+
+impl Kind {
+    pub fn backend(self) -> Box<dyn Backend> {
+        match self {
+            Kind::Pastery => Box::new(Pastery),
+            #[cfg(feature = "backends")]
+            Kind::Gist => Box::new(gist::Gist),
+            #[cfg(feature = "backends")]
+            Kind::GitLab => Box::new(gitlab::GitLab),
+            #[cfg(feature = "backends")]
+            Kind::Hastebin => Box::new(hastebin::Hastebin),
+            Kind::PasteRs => Box::new(paste_rs::PasteRs),
+            #[cfg(feature = "privatebin")]
+            Kind::PrivateBin => Box::new(privatebin::PrivateBin),
+            #[cfg(feature = "backends")]
+            Kind::Termbin => Box::new(termbin::Termbin),
+            #[cfg(feature = "backends")]
+            Kind::Form => Box::new(form::Form),
+        }
+    }
+
+    /// What to check when the service does not accept the credentials.
+    pub fn unauthorized_hint(self) -> String {
+        match self {
+            Kind::Pastery => tr!("hint-unauthorized"),
+            #[cfg(feature = "backends")]
+            Kind::Gist => tr!("hint-unauthorized-gist"),
+            #[cfg(feature = "backends")]
+            Kind::GitLab => tr!("hint-unauthorized-gitlab"),
+            #[cfg(feature = "backends")]
+            Kind::Hastebin => tr!("hint-unauthorized-hastebin"),
+            // The rest take pastes from anyone.
+            _ => tr!("hint-unauthorized-anonymous", service = self.to_string()),
+        }
+    }
+}
+
+/// Pastery, at --base-url, or the API given with --hermetic.
+pub struct Pastery;
+
+const PASTERY_CAPABILITIES: Capabilities = Capabilities {
+    max_size: None,
+    titles: true,
+    max_title_len: Some(255),
+    expiry: true,
+    max_views: true,
+    multiple_files: false,
+    listing: true,
+};
+
+impl Backend for Pastery {
+    fn capabilities(&self) -> &Capabilities {
+        &PASTERY_CAPABILITIES
+    }
+
+    fn check(&self, options: &Options) -> Result<(), Error> {
+        match options.api_key {
+            Some(_) => Ok(()),
+            None => Err(err_msg(
+                "Uploading to Pastery requires --api-key or PASTERY_API_KEY",
+            )),
+        }
+    }
+
+    fn api_url(&self, options: &Options) -> Url {
+        api_url(options)
+    }
+
+    fn request(&self, client: &Client, options: &Options, body: Body) -> RequestBuilder {
+        client.post(generate_url(options)).body(body)
+    }
+
+    fn parse(&self, status: StatusCode, text: &str, options: &Options) -> Result<Paste, Error> {
+        let created = client::parse_response(status, text).map_err(UploadError::from)?;
+        Ok(Paste::created(created, options))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn truncate_titles() {
+        let capabilities = Capabilities {
+            max_title_len: Some(5),
+            ..PASTERY_CAPABILITIES
+        };
+
+        assert_eq!(capabilities.truncate_title("short"), None);
+        assert_eq!(
+            capabilities.truncate_title("longer").as_deref(),
+            Some("long…")
+        );
+        assert_eq!(capabilities.truncate_title("ééééé"), None);
+        assert_eq!(
+            capabilities.truncate_title("éééééé").as_deref(),
+            Some("éééé…")
+        );
+        assert_eq!(
+            PASTERY_CAPABILITIES
+                .truncate_title(&"x".repeat(300))
+                .map(|title| title.chars().count()),
+            Some(255)
+        );
+
+        let unlimited = Capabilities {
+            max_title_len: None,
+            ..PASTERY_CAPABILITIES
+        };
+        assert_eq!(unlimited.truncate_title(&"x".repeat(300)), None);
+    }
+}
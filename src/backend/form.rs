@@ -0,0 +1,141 @@
+//! Pastebins that take a form with the paste in one field and answer with its URL, such as
+//! sprunge, ix.io, and clbin, with --backend form.
+//!
+//! Only --form-url and --form-field differ between them, so a new one needs no code.
+
+use failure::{err_msg, Error};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Body, Client, RequestBuilder, StatusCode, Url};
+
+use super::{Backend, Capabilities};
+use crate::duration::ONE_HUNDRED_YEARS;
+use crate::error::UploadError;
+use crate::{Options, Paste, PasteBody};
+
+pub struct Form;
+
+const CAPABILITIES: Capabilities = Capabilities {
+    max_size: None,
+    titles: false,
+    max_title_len: None,
+    expiry: false,
+    max_views: false,
+    multiple_files: false,
+    listing: false,
+};
+
+/// What separates the fields of the form, which is fixed so that the request can name it
+/// however the body is sent.
+const BOUNDARY: &str = "patisserie-form-7c1f48e0a9d3b2e5";
+
+/// The form, with the paste as the only field, as `curl -F field=<file` sends it.
+fn multipart(field: &str, text: &str) -> Result<String, Error> {
+    if text.contains(BOUNDARY) {
+        return Err(err_msg(
+            "The paste cannot be sent as a form, since it contains the boundary between fields",
+        ));
+    }
+
+    Ok(format!(
+        "--{boundary}\r\n\
+         Content-Disposition: form-data; name=\"{field}\"\r\n\
+         \r\n\
+         {text}\r\n\
+         --{boundary}--\r\n",
+        boundary = BOUNDARY,
+        field = field,
+        text = text
+    ))
+}
+
+impl Backend for Form {
+    fn capabilities(&self) -> &Capabilities {
+        &CAPABILITIES
+    }
+
+    fn check(&self, options: &Options) -> Result<(), Error> {
+        if options.form_url.is_none() {
+            return Err(err_msg(
+                "Uploading a form requires --form-url or PATISSERIE_FORM_URL",
+            ));
+        }
+        match options.form_field {
+            Some(ref field) if field.contains(['"', '\r', '\n']) => {
+                Err(err_msg("--form-field cannot contain quotes or line breaks"))
+            }
+            Some(_) => Ok(()),
+            None => Err(err_msg(
+                "Uploading a form requires --form-field or PATISSERIE_FORM_FIELD",
+            )),
+        }
+    }
+
+    fn api_url(&self, options: &Options) -> Url {
+        match options.hermetic {
+            Some(ref url) => url.clone(),
+            None => options
+                .form_url
+                .clone()
+                .expect("--form-url is checked before uploading"),
+        }
+    }
+
+    fn wrap(&self, options: &Options, body: PasteBody) -> Result<PasteBody, Error> {
+        let field = options.form_field.as_deref().unwrap_or_default();
+        multipart(field, &body.into_text()?).map(PasteBody::Text)
+    }
+
+    fn request(&self, client: &Client, options: &Options, body: Body) -> RequestBuilder {
+        client
+            .post(self.api_url(options))
+            .header(
+                CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", BOUNDARY),
+            )
+            .body(body)
+    }
+
+    /// The response is the URL of the paste on its own, or the reason that there is none.
+    fn parse(&self, status: StatusCode, text: &str, options: &Options) -> Result<Paste, Error> {
+        let text = text.trim();
+
+        match Url::parse(text) {
+            Ok(url) if status.is_success() && matches!(url.scheme(), "http" | "https") => {
+                Ok(Paste {
+                    id: url
+                        .path_segments()
+                        .and_then(|mut segments| segments.next_back())
+                        .map(String::from),
+                    url: url.into_string(),
+                    title: None,
+                    lang: options.lang,
+                    // These services do not say how long they keep pastes.
+                    duration: ONE_HUNDRED_YEARS,
+                    max_views: None,
+                })
+            }
+            _ if !status.is_success() && !text.is_empty() => {
+                Err(UploadError::from_api(status, text.into()).into())
+            }
+            _ => Err(UploadError::Unexpected(status).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_forms() {
+        assert_eq!(
+            multipart("f:1", "hello\n").unwrap(),
+            "--patisserie-form-7c1f48e0a9d3b2e5\r\n\
+             Content-Disposition: form-data; name=\"f:1\"\r\n\
+             \r\n\
+             hello\n\r\n\
+             --patisserie-form-7c1f48e0a9d3b2e5--\r\n"
+        );
+        assert!(multipart("sprunge", &format!("--{}--", BOUNDARY)).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! GitHub Gist, with --backend gist.
+
+use failure::{err_msg, Error};
+use reqwest::header::{ACCEPT, CONTENT_TYPE, USER_AGENT};
+use reqwest::{Body, Client, RequestBuilder, StatusCode, Url};
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use serde_json::{json, Map};
+
+use super::{named_files, Backend, Capabilities};
+use crate::duration::ONE_HUNDRED_YEARS;
+use crate::error::UploadError;
+use crate::{paste_title, Options, Paste, PasteBody};
+
+const GIST_URL: &str = "https://api.github.com/gists";
+
+pub struct Gist;
+
+const CAPABILITIES: Capabilities = Capabilities {
+    max_size: None,
+    titles: true,
+    max_title_len: None,
+    expiry: false,
+    max_views: false,
+    multiple_files: true,
+    listing: false,
+};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Response {
+    Gist { id: String, html_url: String },
+    Error { message: String },
+}
+
+impl Backend for Gist {
+    fn capabilities(&self) -> &Capabilities {
+        &CAPABILITIES
+    }
+
+    fn check(&self, options: &Options) -> Result<(), Error> {
+        match options.github_token {
+            Some(_) => Ok(()),
+            None => Err(err_msg(
+                "Uploading to GitHub Gist requires --github-token or GITHUB_TOKEN",
+            )),
+        }
+    }
+
+    fn api_url(&self, options: &Options) -> Url {
+        match options.hermetic {
+            Some(ref url) => url.clone(),
+            None => Url::parse(GIST_URL).unwrap(),
+        }
+    }
+
+    /// The gist holds each file under its name, which GitHub highlights it by, and is
+    /// described by the title.
+    fn wrap(&self, options: &Options, body: PasteBody) -> Result<PasteBody, Error> {
+        let files = named_files(options, body)?
+            .into_iter()
+            .map(|(name, content)| (name, json!({ "content": content })))
+            .collect::<Map<_, _>>();
+
+        let gist = json!({
+            "description": paste_title(options).unwrap_or_default(),
+            "public": options.public,
+            "files": files,
+        });
+        Ok(PasteBody::Text(gist.to_string()))
+    }
+
+    fn request(&self, client: &Client, options: &Options, body: Body) -> RequestBuilder {
+        let token = options
+            .github_token
+            .as_ref()
+            .map(ExposeSecret::expose_secret);
+
+        client
+            .post(self.api_url(options))
+            .bearer_auth(token.unwrap_or_default())
+            .header(ACCEPT, "application/vnd.github+json")
+            .header(CONTENT_TYPE, "application/json")
+            .header(
+                USER_AGENT,
+                concat!("patisserie/", env!("CARGO_PKG_VERSION")),
+            )
+            .body(body)
+    }
+
+    fn parse(&self, status: StatusCode, text: &str, options: &Options) -> Result<Paste, Error> {
+        match serde_json::from_str::<Response>(text) {
+            Ok(Response::Gist { id, html_url }) if status.is_success() => Ok(Paste {
+                url: html_url,
+                id: Some(id),
+                title: paste_title(options),
+                lang: options.lang,
+                // Gists last until they are deleted, which is as long as a duration can be.
+                duration: ONE_HUNDRED_YEARS,
+                max_views: None,
+            }),
+            Ok(Response::Error { message }) => Err(UploadError::from_api(status, message).into()),
+            Ok(Response::Gist { .. }) | Err(_) => Err(UploadError::Unexpected(status).into()),
+        }
+    }
+}
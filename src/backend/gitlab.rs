@@ -0,0 +1,229 @@
+//! GitLab snippets, with --backend gitlab, on gitlab.com or a self-hosted instance.
+
+use std::str::FromStr;
+
+use failure::{err_msg, format_err, Error};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Body, Client, RequestBuilder, StatusCode, Url};
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{named_files, Backend, Capabilities, DEFAULT_FILE_NAME};
+use crate::duration::ONE_HUNDRED_YEARS;
+use crate::error::UploadError;
+use crate::{paste_title, Options, Paste, PasteBody};
+
+/// Who can see a snippet, with --visibility.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Visibility {
+    Private,
+    Internal,
+    Public,
+}
+
+impl FromStr for Visibility {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "private" => Ok(Visibility::Private),
+            "internal" => Ok(Visibility::Internal),
+            "public" => Ok(Visibility::Public),
+            _ => Err(format_err!(
+                "Unknown visibility {}, expected one of private, internal, public",
+                s
+            )),
+        }
+    }
+}
+
+impl Visibility {
+    fn as_str(self) -> &'static str {
+        match self {
+            Visibility::Private => "private",
+            Visibility::Internal => "internal",
+            Visibility::Public => "public",
+        }
+    }
+}
+
+pub struct GitLab;
+
+const CAPABILITIES: Capabilities = Capabilities {
+    max_size: None,
+    titles: true,
+    max_title_len: Some(255),
+    expiry: false,
+    max_views: false,
+    multiple_files: true,
+    listing: false,
+};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Response {
+    Snippet {
+        id: u64,
+        web_url: String,
+    },
+    /// The message is either text or, when the snippet is invalid, a list of problems for each
+    /// field.
+    Error {
+        message: Value,
+    },
+    /// Some errors, such as a token without the api scope, come from the OAuth layer instead.
+    OAuthError {
+        error: String,
+    },
+}
+
+/// The message of an error response, with each field's problems joined if there are several.
+fn message(message: Value) -> String {
+    match message {
+        Value::String(message) => message,
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(field, problems)| match problems {
+                Value::Array(problems) => {
+                    let problems = problems
+                        .iter()
+                        .map(|problem| {
+                            problem
+                                .as_str()
+                                .map_or_else(|| problem.to_string(), String::from)
+                        })
+                        .collect::<Vec<_>>();
+                    format!("{} {}", field, problems.join(", "))
+                }
+                problem => format!("{} {}", field, problem),
+            })
+            .collect::<Vec<_>>()
+            .join("; "),
+        message => message.to_string(),
+    }
+}
+
+impl Backend for GitLab {
+    fn capabilities(&self) -> &Capabilities {
+        &CAPABILITIES
+    }
+
+    fn check(&self, options: &Options) -> Result<(), Error> {
+        match options.gitlab_token {
+            Some(_) => Ok(()),
+            None => Err(err_msg(
+                "Uploading to GitLab requires --gitlab-token or GITLAB_TOKEN",
+            )),
+        }
+    }
+
+    /// The personal snippets of the instance, or those of --gitlab-project if it is given.
+    fn api_url(&self, options: &Options) -> Url {
+        if let Some(ref url) = options.hermetic {
+            return url.clone();
+        }
+
+        let mut url = options.gitlab_url.clone();
+        {
+            let mut segments = url
+                .path_segments_mut()
+                .expect("--gitlab-url is checked by parse_instance_url");
+            segments.pop_if_empty().extend(&["api", "v4"]);
+            // A project's path is one segment, with its slashes escaped.
+            if let Some(ref project) = options.gitlab_project {
+                segments.extend(&["projects", project.as_str()]);
+            }
+            segments.push("snippets");
+        }
+        url
+    }
+
+    fn wrap(&self, options: &Options, body: PasteBody) -> Result<PasteBody, Error> {
+        let files = named_files(options, body)?
+            .into_iter()
+            .map(|(name, content)| json!({ "file_path": name, "content": content }))
+            .collect::<Vec<_>>();
+
+        // GitLab requires a title.
+        let title = paste_title(options).unwrap_or_else(|| DEFAULT_FILE_NAME.into());
+        let visibility = match options.visibility {
+            Some(visibility) => visibility,
+            None if options.public => Visibility::Public,
+            None => Visibility::Private,
+        };
+
+        let snippet = json!({
+            "title": title,
+            "visibility": visibility.as_str(),
+            "files": files,
+        });
+        Ok(PasteBody::Text(snippet.to_string()))
+    }
+
+    fn request(&self, client: &Client, options: &Options, body: Body) -> RequestBuilder {
+        let token = options
+            .gitlab_token
+            .as_ref()
+            .map(ExposeSecret::expose_secret);
+
+        client
+            .post(self.api_url(options))
+            .header("PRIVATE-TOKEN", token.unwrap_or_default())
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+    }
+
+    fn parse(&self, status: StatusCode, text: &str, options: &Options) -> Result<Paste, Error> {
+        match serde_json::from_str::<Response>(text) {
+            Ok(Response::Snippet { id, web_url }) if status.is_success() => Ok(Paste {
+                url: web_url,
+                id: Some(id.to_string()),
+                title: paste_title(options),
+                lang: options.lang,
+                // Snippets last until they are deleted, which is as long as a duration can be.
+                duration: ONE_HUNDRED_YEARS,
+                max_views: None,
+            }),
+            Ok(Response::Error { message: text }) => {
+                Err(UploadError::from_api(status, message(text)).into())
+            }
+            Ok(Response::OAuthError { error }) => Err(UploadError::from_api(status, error).into()),
+            Ok(Response::Snippet { .. }) | Err(_) => Err(UploadError::Unexpected(status).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use structopt::StructOpt;
+
+    #[test]
+    fn snippet_urls() {
+        let url = |args: &[&str]| {
+            let options = Options::from_iter(["patisserie"].iter().chain(args));
+            GitLab.api_url(&options).to_string()
+        };
+
+        assert_eq!(url(&[]), "https://gitlab.com/api/v4/snippets");
+        assert_eq!(
+            url(&[
+                "--gitlab-url",
+                "https://git.example.com/gitlab",
+                "--gitlab-project",
+                "group/project"
+            ]),
+            "https://git.example.com/gitlab/api/v4/projects/group%2Fproject/snippets"
+        );
+    }
+
+    #[test]
+    fn error_messages() {
+        assert_eq!(message(json!("401 Unauthorized")), "401 Unauthorized");
+        assert_eq!(
+            message(json!({ "title": ["can't be blank"], "files": ["are invalid", "are empty"] })),
+            "files are invalid, are empty; title can't be blank"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! haste-server, with --backend hastebin, on the instance given with --hastebin-url.
+
+use failure::{err_msg, Error};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Body, Client, RequestBuilder, StatusCode, Url};
+use serde::Deserialize;
+
+use super::{directory, Backend, Capabilities};
+use crate::duration::ONE_HUNDRED_YEARS;
+use crate::error::UploadError;
+use crate::{Options, Paste};
+
+pub struct Hastebin;
+
+const CAPABILITIES: Capabilities = Capabilities {
+    max_size: None,
+    titles: false,
+    max_title_len: None,
+    expiry: false,
+    max_views: false,
+    multiple_files: false,
+    listing: false,
+};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Response {
+    Document { key: String },
+    Error { message: String },
+}
+
+/// The instance, as a directory that the API's paths are added to.
+fn instance(options: &Options) -> Url {
+    let url = options
+        .hastebin_url
+        .as_ref()
+        .expect("--hastebin-url is checked before uploading");
+    directory(url)
+}
+
+impl Backend for Hastebin {
+    fn capabilities(&self) -> &Capabilities {
+        &CAPABILITIES
+    }
+
+    fn check(&self, options: &Options) -> Result<(), Error> {
+        match options.hastebin_url {
+            Some(_) => Ok(()),
+            None => Err(err_msg(
+                "Uploading to hastebin requires --hastebin-url or HASTEBIN_URL",
+            )),
+        }
+    }
+
+    fn api_url(&self, options: &Options) -> Url {
+        match options.hermetic {
+            Some(ref url) => url.clone(),
+            None => instance(options).join("documents").unwrap(),
+        }
+    }
+
+    fn request(&self, client: &Client, options: &Options, body: Body) -> RequestBuilder {
+        client
+            .post(self.api_url(options))
+            .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(body)
+    }
+
+    fn parse(&self, status: StatusCode, text: &str, options: &Options) -> Result<Paste, Error> {
+        match serde_json::from_str::<Response>(text) {
+            Ok(Response::Document { key }) if status.is_success() => Ok(Paste {
+                url: instance(options).join(&key)?.into_string(),
+                id: Some(key),
+                title: None,
+                lang: options.lang,
+                // Documents last for as long as the instance keeps them, which it does not say.
+                duration: ONE_HUNDRED_YEARS,
+                max_views: None,
+            }),
+            Ok(Response::Error { message }) => Err(UploadError::from_api(status, message).into()),
+            Ok(Response::Document { .. }) | Err(_) => Err(UploadError::Unexpected(status).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use structopt::StructOpt;
+
+    #[test]
+    fn document_urls() {
+        let urls = |instance: &str| {
+            let options = Options::from_iter(&["patisserie", "--hastebin-url", instance]);
+            let paste = Hastebin
+                .parse(StatusCode::OK, r#"{"key": "abcdef"}"#, &options)
+                .unwrap();
+            (Hastebin.api_url(&options).to_string(), paste.url)
+        };
+
+        assert_eq!(
+            urls("https://haste.example.com"),
+            (
+                "https://haste.example.com/documents".into(),
+                "https://haste.example.com/abcdef".into()
+            )
+        );
+        assert_eq!(
+            urls("https://example.com/haste"),
+            (
+                "https://example.com/haste/documents".into(),
+                "https://example.com/haste/abcdef".into()
+            )
+        );
+    }
+}
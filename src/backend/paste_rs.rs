@@ -0,0 +1,75 @@
+//! https://paste.rs/, with --backend paste.rs, which needs no account.
+
+use failure::Error;
+use reqwest::{Body, Client, RequestBuilder, StatusCode, Url};
+
+use super::{Backend, Capabilities};
+use crate::duration::ONE_HUNDRED_YEARS;
+use crate::error::UploadError;
+use crate::{Options, Paste};
+
+const PASTE_RS_URL: &str = "https://paste.rs/";
+
+pub struct PasteRs;
+
+const CAPABILITIES: Capabilities = Capabilities {
+    max_size: None,
+    titles: false,
+    max_title_len: None,
+    expiry: false,
+    max_views: false,
+    multiple_files: false,
+    listing: false,
+};
+
+impl Backend for PasteRs {
+    fn capabilities(&self) -> &Capabilities {
+        &CAPABILITIES
+    }
+
+    fn check(&self, _options: &Options) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn api_url(&self, options: &Options) -> Url {
+        match options.hermetic {
+            Some(ref url) => url.clone(),
+            None => Url::parse(PASTE_RS_URL).unwrap(),
+        }
+    }
+
+    fn request(&self, client: &Client, options: &Options, body: Body) -> RequestBuilder {
+        client.post(self.api_url(options)).body(body)
+    }
+
+    /// The response is the URL of the paste on its own, or the reason that there is none.
+    fn parse(&self, status: StatusCode, text: &str, options: &Options) -> Result<Paste, Error> {
+        let text = text.trim();
+
+        match status {
+            StatusCode::CREATED | StatusCode::OK => match Url::parse(text) {
+                Ok(url) => Ok(Paste {
+                    id: url
+                        .path_segments()
+                        .and_then(|mut s| s.next_back())
+                        .map(String::from),
+                    url: url.into_string(),
+                    title: None,
+                    lang: options.lang,
+                    // Pastes last until they are deleted, which is as long as a duration can be.
+                    duration: ONE_HUNDRED_YEARS,
+                    max_views: None,
+                }),
+                Err(_) => Err(UploadError::Unexpected(status).into()),
+            },
+            // The paste was created without the end of the contents.
+            StatusCode::PARTIAL_CONTENT => Err(UploadError::TooLarge(format!(
+                "the paste is too large, and only part of it was uploaded to {}",
+                text
+            ))
+            .into()),
+            _ if !text.is_empty() => Err(UploadError::from_api(status, text.into()).into()),
+            _ => Err(UploadError::Unexpected(status).into()),
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//! PrivateBin, with --backend privatebin, on the instance given with --privatebin-url.
+//!
+//! PrivateBin only ever sees pastes encrypted. Each is encrypted with AES-256-GCM under a new
+//! random key, which is kept in the fragment of the paste's URL so that it is not sent to the
+//! server when the paste is viewed.
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::time::Duration;
+
+use aes_gcm::aead::consts::U16;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::aes::Aes256;
+use aes_gcm::AesGcm;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use failure::{err_msg, format_err, Error};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use pbkdf2::pbkdf2_hmac;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Body, Client, RequestBuilder, StatusCode, Url};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+
+use super::{directory, Backend, Capabilities};
+use crate::duration::{
+    ONE_DAY, ONE_HOUR, ONE_HUNDRED_YEARS, ONE_MINUTE, ONE_MONTH, ONE_WEEK, ONE_YEAR,
+};
+use crate::error::UploadError;
+use crate::{Options, Paste, PasteBody, AUTODETECT};
+
+/// PrivateBin's cipher, which uses a 128-bit nonce rather than the usual 96 bits.
+type Cipher = AesGcm<Aes256, U16>;
+
+/// How many rounds of PBKDF2 derive the encryption key, which is what PrivateBin's own client
+/// uses.
+const ITERATIONS: u32 = 100_000;
+
+/// The lifetimes that PrivateBin offers, from the shortest.
+const EXPIRIES: &[(Duration, &str)] = &[
+    (ONE_MINUTE.checked_mul(5).unwrap(), "5min"),
+    (ONE_MINUTE.checked_mul(10).unwrap(), "10min"),
+    (ONE_HOUR, "1hour"),
+    (ONE_DAY, "1day"),
+    (ONE_WEEK, "1week"),
+    (ONE_MONTH, "1month"),
+    (ONE_YEAR, "1year"),
+];
+
+thread_local! {
+    /// The key of the paste being sent on this thread, which `wrap` makes and `parse` adds to
+    /// the URL. Both are called in turn by the thread that sends the paste.
+    static KEY: RefCell<Option<[u8; 32]>> = const { RefCell::new(None) };
+}
+
+pub struct PrivateBin;
+
+const CAPABILITIES: Capabilities = Capabilities {
+    max_size: None,
+    titles: false,
+    max_title_len: None,
+    expiry: true,
+    max_views: true,
+    multiple_files: false,
+    listing: false,
+};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Response {
+    Paste { id: String },
+    Error { message: String },
+}
+
+/// The shortest lifetime that PrivateBin offers that is at least --duration, with how long it
+/// is, or `never` if the duration is longer than all of them.
+fn expiry(duration: Duration) -> (Duration, &'static str) {
+    EXPIRIES
+        .iter()
+        .copied()
+        .find(|&(expiry, _)| expiry >= duration)
+        .unwrap_or((ONE_HUNDRED_YEARS, "never"))
+}
+
+/// How PrivateBin displays the paste.
+fn formatter(lang: &str) -> &'static str {
+    match lang {
+        AUTODETECT | "text" => "plaintext",
+        "markdown" => "markdown",
+        _ => "syntaxhighlighting",
+    }
+}
+
+fn random<const N: usize>() -> Result<[u8; N], Error> {
+    let mut bytes = [0; N];
+    getrandom::getrandom(&mut bytes).map_err(|e| format_err!("Could not make a key: {}", e))?;
+    Ok(bytes)
+}
+
+/// The instance, made into a directory so that the paste's URL keeps its path.
+fn instance(options: &Options) -> Url {
+    let url = options
+        .privatebin_url
+        .as_ref()
+        .expect("--privatebin-url is checked before uploading");
+    directory(url)
+}
+
+/// Encrypt `text` with `key` into what PrivateBin stores: the authenticated data, which
+/// describes how the paste was encrypted and how to show it, and the ciphertext.
+fn encrypt(
+    key: &[u8; 32],
+    text: &str,
+    formatter: &str,
+    burn: bool,
+) -> Result<(serde_json::Value, String), Error> {
+    let iv = random::<16>()?;
+    let salt = random::<8>()?;
+    let mut derived = [0; 32];
+    pbkdf2_hmac::<Sha256>(key, &salt, ITERATIONS, &mut derived);
+
+    let adata = json!([
+        [
+            BASE64.encode(iv),
+            BASE64.encode(salt),
+            ITERATIONS,
+            256,
+            128,
+            "aes",
+            "gcm",
+            "zlib"
+        ],
+        formatter,
+        0,
+        u8::from(burn),
+    ]);
+
+    let mut compressed = DeflateEncoder::new(Vec::new(), Compression::default());
+    compressed.write_all(json!({ "paste": text }).to_string().as_bytes())?;
+    let compressed = compressed.finish()?;
+
+    let ciphertext = Cipher::new(&derived.into())
+        .encrypt(
+            &iv.into(),
+            Payload {
+                msg: &compressed,
+                // The data is authenticated as it is sent, so it has to be serialized once.
+                aad: adata.to_string().as_bytes(),
+            },
+        )
+        .map_err(|_| err_msg("Could not encrypt the paste"))?;
+
+    Ok((adata, BASE64.encode(ciphertext)))
+}
+
+impl Backend for PrivateBin {
+    fn capabilities(&self) -> &Capabilities {
+        &CAPABILITIES
+    }
+
+    fn check(&self, options: &Options) -> Result<(), Error> {
+        if options.privatebin_url.is_none() {
+            return Err(err_msg(
+                "Uploading to PrivateBin requires --privatebin-url or PRIVATEBIN_URL",
+            ));
+        }
+        match options.max_views {
+            None | Some(1) => Ok(()),
+            Some(_) => Err(err_msg(
+                "PrivateBin can only delete a paste after it has been viewed once, with \
+                 --max-views burn",
+            )),
+        }
+    }
+
+    fn api_url(&self, options: &Options) -> Url {
+        match options.hermetic {
+            Some(ref url) => url.clone(),
+            None => instance(options),
+        }
+    }
+
+    fn wrap(&self, options: &Options, body: PasteBody) -> Result<PasteBody, Error> {
+        let key = random::<32>()?;
+        let (adata, ciphertext) = encrypt(
+            &key,
+            &body.into_text()?,
+            formatter(options.lang),
+            options.max_views == Some(1),
+        )?;
+        KEY.with(|cell| *cell.borrow_mut() = Some(key));
+
+        let paste = json!({
+            "v": 2,
+            "adata": adata,
+            "ct": ciphertext,
+            "meta": { "expire": expiry(options.duration).1 },
+        });
+        Ok(PasteBody::Text(paste.to_string()))
+    }
+
+    fn request(&self, client: &Client, options: &Options, body: Body) -> RequestBuilder {
+        client
+            .post(self.api_url(options))
+            // PrivateBin answers with JSON rather than a page only when it is asked this way.
+            .header("X-Requested-With", "JSONHttpRequest")
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+    }
+
+    fn parse(&self, status: StatusCode, text: &str, options: &Options) -> Result<Paste, Error> {
+        match serde_json::from_str::<Response>(text) {
+            Ok(Response::Paste { id }) if status.is_success() => {
+                let key = KEY
+                    .with(|cell| cell.borrow_mut().take())
+                    .expect("the paste is wrapped before it is sent");
+
+                let mut url = instance(options);
+                url.set_query(Some(&id));
+                url.set_fragment(Some(&bs58::encode(key).into_string()));
+
+                Ok(Paste {
+                    url: url.into_string(),
+                    id: Some(id),
+                    title: None,
+                    lang: options.lang,
+                    duration: expiry(options.duration).0,
+                    max_views: options.max_views,
+                })
+            }
+            Ok(Response::Error { message }) => Err(UploadError::from_api(status, message).into()),
+            Ok(Response::Paste { .. }) | Err(_) => Err(UploadError::Unexpected(status).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn choose_expiries() {
+        assert_eq!(
+            expiry(ONE_MINUTE),
+            (ONE_MINUTE.checked_mul(5).unwrap(), "5min")
+        );
+        assert_eq!(expiry(ONE_DAY), (ONE_DAY, "1day"));
+        assert_eq!(expiry(ONE_DAY.checked_mul(2).unwrap()), (ONE_WEEK, "1week"));
+        assert_eq!(
+            expiry(ONE_YEAR.checked_mul(2).unwrap()),
+            (ONE_HUNDRED_YEARS, "never")
+        );
+    }
+
+    #[test]
+    fn encrypt_pastes() {
+        let key = [7; 32];
+        let (adata, ciphertext) =
+            encrypt(&key, "fn main() {}\n", "syntaxhighlighting", true).unwrap();
+
+        assert_eq!(adata[0][2], ITERATIONS);
+        assert_eq!(adata[0][7], "zlib");
+        assert_eq!(adata[1], "syntaxhighlighting");
+        assert_eq!(adata[3], 1);
+
+        // Decrypt it as PrivateBin's client would.
+        let decode = |value: &serde_json::Value| BASE64.decode(value.as_str().unwrap()).unwrap();
+        let (iv, salt) = (decode(&adata[0][0]), decode(&adata[0][1]));
+        let mut derived = [0; 32];
+        pbkdf2_hmac::<Sha256>(&key, &salt, ITERATIONS, &mut derived);
+
+        let compressed = Cipher::new(&derived.into())
+            .decrypt(
+                iv.as_slice().into(),
+                Payload {
+                    msg: &BASE64.decode(ciphertext).unwrap(),
+                    aad: adata.to_string().as_bytes(),
+                },
+            )
+            .unwrap();
+        let mut decompressed = String::new();
+        DeflateDecoder::new(compressed.as_slice())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, r#"{"paste":"fn main() {}\n"}"#);
+    }
+}
@@ -0,0 +1,138 @@
+//! termbin, with --backend termbin, which takes pastes over a plain TCP connection as netcat
+//! would send them, for networks that block HTTPS but not other traffic.
+
+use std::fmt;
+use std::str::FromStr;
+
+use failure::{format_err, Error};
+use reqwest::{Body, Client, RequestBuilder, StatusCode, Url};
+
+use super::{Backend, Capabilities};
+use crate::duration::ONE_MONTH;
+use crate::error::UploadError;
+use crate::{Options, Paste};
+
+/// The host and port of a termbin server, with --termbin-address.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Address {
+    host: String,
+    port: u16,
+}
+
+impl FromStr for Address {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let invalid = || format_err!("Invalid address {}: expected the form host:port", s);
+
+        let (host, port) = s.rsplit_once(':').ok_or_else(invalid)?;
+        // An IPv6 address is written in brackets, as in a URL.
+        let host = host
+            .strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+            .unwrap_or(host);
+        if host.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Address {
+            host: host.into(),
+            port: port.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+pub struct Termbin;
+
+const CAPABILITIES: Capabilities = Capabilities {
+    max_size: None,
+    titles: false,
+    max_title_len: None,
+    expiry: false,
+    max_views: false,
+    multiple_files: false,
+    listing: false,
+};
+
+impl Backend for Termbin {
+    fn capabilities(&self) -> &Capabilities {
+        &CAPABILITIES
+    }
+
+    #[cfg_attr(not(feature = "pinning"), allow(unused_variables))]
+    fn check(&self, options: &Options) -> Result<(), Error> {
+        #[cfg(feature = "pinning")]
+        {
+            if !options.pinned_pubkeys.is_empty() {
+                return Err(format_err!(
+                    "--pinned-pubkey cannot be used with termbin, which does not use TLS"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The server, as a URL for the messages that name it.
+    fn api_url(&self, options: &Options) -> Url {
+        Url::parse(&format!("tcp://{}/", options.termbin_address)).unwrap()
+    }
+
+    fn tcp_address(&self, options: &Options) -> Option<(String, u16)> {
+        let Address { ref host, port } = options.termbin_address;
+        Some((host.clone(), port))
+    }
+
+    fn request(&self, _client: &Client, _options: &Options, _body: Body) -> RequestBuilder {
+        unreachable!("termbin pastes are sent to the tcp_address rather than over HTTP")
+    }
+
+    /// The reply is the URL of the paste, or a message saying why there is none.
+    fn parse(&self, status: StatusCode, text: &str, options: &Options) -> Result<Paste, Error> {
+        // termbin ends the URL with a NUL as well as a newline.
+        let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+
+        match Url::parse(text) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(Paste {
+                id: url
+                    .path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .map(String::from),
+                url: url.into_string(),
+                title: None,
+                lang: options.lang,
+                // termbin removes pastes after a month.
+                duration: ONE_MONTH,
+                max_views: None,
+            }),
+            _ if !text.is_empty() => Err(UploadError::from_api(status, text.into()).into()),
+            _ => Err(UploadError::Unexpected(status).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_addresses() {
+        let address = |s: &str| s.parse::<Address>().map(|address| address.to_string());
+
+        assert_eq!(address("termbin.com:9999").unwrap(), "termbin.com:9999");
+        assert_eq!(address("[::1]:9999").unwrap(), "[::1]:9999");
+        assert!(address("termbin.com").is_err());
+        assert!(address(":9999").is_err());
+        assert!(address("termbin.com:99999").is_err());
+    }
+}
@@ -0,0 +1,342 @@
+//! Uploads described by a manifest, so that they can be reviewed and repeated.
+//!
+//! A manifest is a TOML file with a `[[paste]]` table for each upload:
+//!
+//! ```toml
+//! [[paste]]
+//! path = "build.log"
+//! title = "Build log"
+//! lang = "text"
+//! duration = "1w"
+//!
+//! [[paste]]
+//! path = "core.png"
+//! backend = "file-host"
+//! ```
+//!
+//! Each paste has either a `path`, which is relative to the manifest, or its `content` inline:
+//!
+//! ```toml
+//! [[paste]]
+//! content = "Built from 4f2a9c1."
+//! title = "Release notes"
+//! max-views = 10
+//! ```
+//!
+//! Everything else defaults to the command line options. With --write, the URL of each paste is
+//! written back into its table as `url`, keeping the rest of the manifest as it was.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use failure::{format_err, Error};
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer};
+use structopt::StructOpt;
+use toml_edit::DocumentMut;
+
+use crate::backend;
+use crate::config::deserialize_max_views;
+use crate::filehost::{self, File};
+use crate::preflight::{self, Planned, Summary};
+use crate::{
+    check_denylist, duration, lang_of, lang_of_text, lang_suggestion, langcache, parse_lang,
+    upload_files, Input, Options, Paste, Uploader,
+};
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct Batch {
+    /// The manifest listing the files to upload.
+    #[structopt(parse(from_os_str))]
+    manifest: PathBuf,
+
+    /// Write the URL of each paste back into the manifest.
+    #[structopt(long = "write")]
+    write: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    #[serde(default, rename = "paste")]
+    pastes: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    path: Option<PathBuf>,
+    content: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_lang")]
+    lang: Option<String>,
+    title: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    duration: Option<Duration>,
+    #[serde(
+        default,
+        rename = "max-views",
+        deserialize_with = "deserialize_max_views"
+    )]
+    max_views: Option<u32>,
+    #[serde(default)]
+    backend: Backend,
+    /// The URL written by an earlier --write, which is replaced.
+    #[serde(default, rename = "url")]
+    _url: Option<IgnoredAny>,
+}
+
+/// Where an entry is uploaded to.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+enum Backend {
+    /// The --backend, as a paste.
+    #[default]
+    Pastery,
+    /// The --file-host, as a file, for content that Pastery cannot hold.
+    FileHost,
+}
+
+fn deserialize_lang<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    let lang = String::deserialize(deserializer)?;
+
+    if langcache::get(&lang).is_some() {
+        Ok(Some(lang))
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "unknown language {}{}",
+            lang,
+            lang_suggestion(&lang)
+        )))
+    }
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    duration::parse(&s)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+impl Batch {
+    /// Upload every entry in the manifest in order, with `options` for what they leave out.
+    ///
+    /// Like a batch of files on the command line, the uploads are confirmed first unless --yes
+    /// is given, run --jobs at a time, every entry is attempted unless --fail-fast is given, and
+    /// the failures are reported at the end. Files that match the denylist are refused before
+    /// anything is uploaded.
+    pub fn run<F>(&self, options: &Options, mut publish: F) -> Result<(), Error>
+    where
+        F: FnMut(&Path, &Paste) -> Result<(), Error>,
+    {
+        let text = fs::read_to_string(&self.manifest)
+            .map_err(|e| format_err!("Could not read {}: {}", self.manifest.display(), e))?;
+        let manifest = toml_edit::de::from_str::<Manifest>(&text)
+            .map_err(|e| format_err!("Invalid manifest {}: {}", self.manifest.display(), e))?;
+
+        for (i, entry) in manifest.pastes.iter().enumerate() {
+            let problem = match (&entry.path, &entry.content, entry.backend) {
+                (Some(_), Some(_), _) => "has both a path and content",
+                (None, None, _) => "needs a path or content",
+                (None, Some(_), Backend::FileHost) => {
+                    "needs a path to be uploaded to the file host"
+                }
+                _ => continue,
+            };
+            return Err(format_err!(
+                "Invalid manifest {}: paste {} {}",
+                self.manifest.display(),
+                i + 1,
+                problem
+            ));
+        }
+
+        // Inline pastes are shown as where they are in the manifest.
+        let base = self.manifest.parent().unwrap_or_else(|| Path::new(""));
+        let paths = manifest
+            .pastes
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| match entry.path {
+                Some(ref path) => base.join(path),
+                None => PathBuf::from(format!("{}#{}", self.manifest.display(), i + 1)),
+            })
+            .collect::<Vec<_>>();
+
+        check_denylist(&Options {
+            paths: manifest
+                .pastes
+                .iter()
+                .zip(&paths)
+                .filter(|(entry, _)| entry.path.is_some())
+                .map(|(_, path)| path.clone())
+                .collect(),
+            ..options.clone()
+        })?;
+
+        if paths.len() > 1 {
+            let planned = manifest
+                .pastes
+                .iter()
+                .zip(&paths)
+                .map(|(entry, path)| Planned {
+                    path,
+                    lang: match (&entry.lang, &entry.path) {
+                        (Some(lang), _) => parse_lang(lang),
+                        (None, Some(_)) => lang_of(options, path),
+                        (None, None) => options.lang,
+                    },
+                    duration: entry.duration.unwrap_or(options.duration),
+                    max_views: entry.max_views.or(options.max_views),
+                    host: match entry.backend {
+                        Backend::Pastery => backend::kind(options).backend().api_url(options),
+                        Backend::FileHost => options.file_host.clone(),
+                    }
+                    .host_str()
+                    .unwrap_or_default()
+                    .to_owned(),
+                })
+                .collect::<Vec<_>>();
+            preflight::confirm(&Summary::new(&planned), options.yes)?;
+        }
+
+        let mut urls = vec![None; paths.len()];
+        let result = upload_files(
+            options,
+            &paths,
+            |i, path| upload(options, &manifest.pastes[i], path),
+            |i, path, paste| {
+                publish(path, paste)?;
+                urls[i] = Some(paste.url.clone());
+                Ok(())
+            },
+        );
+
+        if self.write {
+            self.write_urls(&text, &urls)?;
+        }
+
+        result
+    }
+
+    /// Write the URLs of the pastes into the manifest, leaving the entries that failed alone.
+    fn write_urls(&self, text: &str, urls: &[Option<String>]) -> Result<(), Error> {
+        let mut document = text.parse::<DocumentMut>()?;
+
+        if let Some(tables) = document
+            .get_mut("paste")
+            .and_then(|item| item.as_array_of_tables_mut())
+        {
+            for (table, url) in tables.iter_mut().zip(urls) {
+                if let Some(url) = url {
+                    table["url"] = toml_edit::value(url.as_str());
+                }
+            }
+        }
+
+        fs::write(&self.manifest, document.to_string())
+            .map_err(|e| format_err!("Could not write {}: {}", self.manifest.display(), e))
+    }
+}
+
+fn upload(options: &Options, entry: &Entry, path: &Path) -> Result<Paste, Error> {
+    let mut options = Options {
+        title: entry.title.clone().or_else(|| options.title.clone()),
+        ..options.clone()
+    };
+    if let Some(ref lang) = entry.lang {
+        options.lang = parse_lang(lang);
+    }
+    if let Some(duration) = entry.duration {
+        options.duration = duration;
+    }
+    if entry.max_views.is_some() {
+        options.max_views = entry.max_views;
+    }
+
+    let uploader = Uploader::new(&options)?;
+    match entry.backend {
+        Backend::Pastery => match entry.content {
+            Some(ref content) => uploader.upload(Input {
+                body: content.clone(),
+                title: None,
+                lang: lang_of_text(content),
+            }),
+            None => uploader.upload_file(path),
+        },
+        Backend::FileHost => {
+            let contents = fs::read(path)
+                .map_err(|e| format_err!("Could not read {}: {}", path.display(), e))?;
+            let text = std::str::from_utf8(&contents).is_ok();
+
+            filehost::upload(
+                &uploader,
+                File {
+                    name: path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| "file".into()),
+                    mime: if text {
+                        "text/plain; charset=utf-8"
+                    } else {
+                        "application/octet-stream"
+                    },
+                    contents,
+                    kind: if text { options.lang } else { "file" },
+                    title: options.title.clone(),
+                },
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_manifests() {
+        let manifest = toml_edit::de::from_str::<Manifest>(
+            r#"
+            [[paste]]
+            path = "build.log"
+            title = "Build log"
+            lang = "rust"
+            duration = "1w"
+            url = "https://www.pastery.net/abcdef/"
+
+            [[paste]]
+            path = "core.png"
+            backend = "file-host"
+
+            [[paste]]
+            content = "Built from 4f2a9c1."
+            max-views = "burn"
+            "#,
+        )
+        .unwrap();
+
+        let [log, core, notes] = manifest.pastes.as_slice() else {
+            panic!("expected three entries");
+        };
+        assert_eq!(log.lang.as_deref(), Some("rust"));
+        assert_eq!(log.duration, Some(crate::duration::ONE_WEEK));
+        assert_eq!(log.backend, Backend::Pastery);
+        assert_eq!(core.path.as_deref(), Some(Path::new("core.png")));
+        assert_eq!(core.backend, Backend::FileHost);
+        assert_eq!(notes.path, None);
+        assert_eq!(notes.content.as_deref(), Some("Built from 4f2a9c1."));
+        assert_eq!(notes.max_views, Some(1));
+
+        let invalid = |manifest| toml_edit::de::from_str::<Manifest>(manifest).is_err();
+        assert!(invalid("[[paste]]\npath = \"a\"\nlang = \"klingon\"\n"));
+        assert!(invalid("[[paste]]\npath = \"a\"\nduration = \"forever\"\n"));
+        assert!(invalid("[[paste]]\npath = \"a\"\ntitel = \"typo\"\n"));
+        assert!(invalid("[[paste]]\npath = \"a\"\nbackend = \"gist\"\n"));
+        assert!(invalid("[[paste]]\npath = \"a\"\nmax-views = 0\n"));
+    }
+}
@@ -0,0 +1,150 @@
+//! The local address that connections are made from, for --source-ip and --interface.
+
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+
+use failure::{format_err, Error};
+use socket2::{Domain, Socket, Type};
+
+/// Check that `ip` belongs to this machine, so that a mistyped address fails before anything
+/// is uploaded rather than as an obscure error from the first connection.
+pub fn check_local(ip: IpAddr) -> Result<IpAddr, Error> {
+    UdpSocket::bind((ip, 0))
+        .map(|_| ip)
+        .map_err(|e| format_err!("Cannot send from {}: {}", ip, e))
+}
+
+/// Connect to `host`, from `local` if it is given.
+pub fn connect(host: &str, port: u16, local: Option<IpAddr>) -> io::Result<TcpStream> {
+    let Some(local) = local else {
+        return TcpStream::connect((host, port));
+    };
+
+    let mut last = None;
+    for addr in (host, port).to_socket_addrs()? {
+        if addr.is_ipv4() != local.is_ipv4() {
+            continue;
+        }
+
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+        socket.bind(&SocketAddr::new(local, 0).into())?;
+        match socket.connect(&addr.into()) {
+            Ok(()) => return Ok(socket.into()),
+            Err(e) => last = Some(e),
+        }
+    }
+
+    Err(last.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::AddrNotAvailable,
+            format!("{} has no address that can be reached from {}", host, local),
+        )
+    }))
+}
+
+/// The address of a network interface, preferring IPv4 and skipping link-local IPv6 addresses,
+/// which cannot reach anything beyond the link.
+#[cfg(unix)]
+pub fn interface_address(name: &str) -> Result<IpAddr, Error> {
+    let mut addrs = interface_addresses(name)?;
+    addrs.sort_by_key(|addr| !addr.is_ipv4());
+    addrs
+        .into_iter()
+        .find(|addr| match addr {
+            IpAddr::V4(_) => true,
+            IpAddr::V6(v6) => (v6.segments()[0] & 0xffc0) != 0xfe80,
+        })
+        .ok_or_else(|| format_err!("The interface {} has no usable address", name))
+}
+
+#[cfg(not(unix))]
+pub fn interface_address(name: &str) -> Result<IpAddr, Error> {
+    Err(format_err!(
+        "--interface {} is not supported on this platform; use --source-ip with the \
+         interface's address instead",
+        name
+    ))
+}
+
+/// Every address of the interface called `name`.
+#[cfg(unix)]
+fn interface_addresses(name: &str) -> Result<Vec<IpAddr>, Error> {
+    use std::ffi::CStr;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    let mut head = std::ptr::null_mut();
+    // SAFETY: getifaddrs fills in `head` with a list that is freed below.
+    if unsafe { libc::getifaddrs(&mut head) } != 0 {
+        return Err(format_err!(
+            "Could not list the network interfaces: {}",
+            io::Error::last_os_error()
+        ));
+    }
+
+    let mut found = false;
+    let mut addrs = Vec::new();
+    let mut entry = head;
+    while !entry.is_null() {
+        // SAFETY: `entry` is a node of the list from getifaddrs, which has not been freed.
+        let ifaddr = unsafe { &*entry };
+        entry = ifaddr.ifa_next;
+
+        // SAFETY: interface names are NUL-terminated strings.
+        if unsafe { CStr::from_ptr(ifaddr.ifa_name) }.to_bytes() != name.as_bytes() {
+            continue;
+        }
+        found = true;
+        if ifaddr.ifa_addr.is_null() {
+            continue;
+        }
+
+        // SAFETY: the family says which kind of socket address `ifa_addr` points to.
+        unsafe {
+            match i32::from((*ifaddr.ifa_addr).sa_family) {
+                libc::AF_INET => {
+                    let addr = &*(ifaddr.ifa_addr as *const libc::sockaddr_in);
+                    addrs.push(Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr)).into());
+                }
+                libc::AF_INET6 => {
+                    let addr = &*(ifaddr.ifa_addr as *const libc::sockaddr_in6);
+                    addrs.push(Ipv6Addr::from(addr.sin6_addr.s6_addr).into());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // SAFETY: `head` came from getifaddrs and nothing refers to the list any more.
+    unsafe { libc::freeifaddrs(head) };
+
+    if found {
+        Ok(addrs)
+    } else {
+        Err(format_err!("There is no network interface called {}", name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn find_interface_addresses() {
+        // Every Unix machine has a loopback interface, under one of these names.
+        let addr = interface_address("lo").or_else(|_| interface_address("lo0"));
+        assert_eq!(addr.unwrap(), IpAddr::from([127, 0, 0, 1]));
+
+        assert!(interface_address("no-such-interface")
+            .unwrap_err()
+            .to_string()
+            .contains("no network interface"));
+    }
+
+    #[test]
+    fn check_local_addresses() {
+        assert!(check_local(IpAddr::from([127, 0, 0, 1])).is_ok());
+        // An address from TEST-NET-1, which no machine should have.
+        assert!(check_local(IpAddr::from([192, 0, 2, 1])).is_err());
+    }
+}
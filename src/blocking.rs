@@ -0,0 +1,57 @@
+//! A client that blocks until each request is done, for programs without an async runtime.
+//!
+//! It drives `client::Client` on a runtime of its own, so both send the same requests and read
+//! the same responses.
+
+use reqwest::Url;
+use tokio::runtime::{self, Runtime};
+
+use crate::client::{self, ApiError, Created, PasteRequest};
+
+/// A client for creating pastes with an API key.
+pub struct Client {
+    inner: client::Client,
+    runtime: Runtime,
+}
+
+impl Client {
+    /// A client that uploads to Pastery itself.
+    ///
+    /// # Panics
+    ///
+    /// If the runtime cannot be started, as `reqwest::blocking::Client::new` does when it cannot
+    /// start its own.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Client {
+            inner: client::Client::new(api_key),
+            runtime: runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Could not start a runtime for the blocking client"),
+        }
+    }
+
+    /// Upload to another instance of the API, `api_url`.
+    pub fn api_url(mut self, api_url: Url) -> Self {
+        self.inner = self.inner.api_url(api_url);
+        self
+    }
+
+    /// Send requests with `http`, such as one that goes through a proxy.
+    pub fn http_client(mut self, http: reqwest_async::Client) -> Self {
+        self.inner = self.inner.http_client(http);
+        self
+    }
+
+    /// Create a paste of `body`, waiting for Pastery to answer.
+    ///
+    /// Like `reqwest::blocking`, this panics if it is called from inside an async runtime, where
+    /// `client::Client` should be used instead.
+    pub fn upload(
+        &self,
+        request: &PasteRequest,
+        body: impl Into<reqwest_async::Body>,
+    ) -> Result<Created, ApiError> {
+        self.runtime.block_on(self.inner.upload(request, body))
+    }
+}
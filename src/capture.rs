@@ -0,0 +1,101 @@
+use std::io::{pipe, Read};
+use std::process::{Command, ExitStatus, Stdio};
+
+use failure::{format_err, Error};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /// CSI sequences (colours, cursor movement) and OSC sequences (window titles, hyperlinks).
+    static ref ANSI_ESCAPE: Regex =
+        Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[@-Z\\-_]")
+            .unwrap();
+}
+
+/// Run a command, capturing its stdout and stderr interleaved as they would appear on a terminal.
+pub fn run(command: &mut Command) -> Result<(String, ExitStatus), Error> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let (mut reader, writer) = pipe()?;
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(writer.try_clone()?)
+        .stderr(writer)
+        .spawn()
+        .map_err(|e| format_err!("Could not run {}: {}", program, e))?;
+
+    // The command still holds our copies of the write end of the pipe. Replace them so that the
+    // child holds the only remaining copies and the read below finishes when it exits.
+    command.stdout(Stdio::null()).stderr(Stdio::null());
+
+    let mut output = vec![];
+    reader.read_to_end(&mut output)?;
+    let status = child.wait()?;
+
+    Ok((String::from_utf8_lossy(&output).into_owned(), status))
+}
+
+/// Remove ANSI escape sequences from terminal output.
+pub fn strip_ansi(text: &str) -> String {
+    ANSI_ESCAPE.replace_all(text, "").into_owned()
+}
+
+/// Clean up terminal output so that it reads like the final state of the screen.
+///
+/// Escape sequences are removed, lines redrawn with carriage returns (such as progress bars)
+/// are reduced to their last version, and any other control characters are dropped.
+pub fn clean_terminal_output(text: &str) -> String {
+    let text = strip_ansi(text);
+    let mut cleaned = String::with_capacity(text.len());
+
+    for line in text.split_inclusive('\n') {
+        let (line, newline) = match line.strip_suffix('\n') {
+            Some(line) => (line.strip_suffix('\r').unwrap_or(line), "\n"),
+            None => (line, ""),
+        };
+
+        let line = line.rsplit('\r').next().unwrap_or(line);
+        cleaned.extend(line.chars().filter(|c| *c == '\t' || !c.is_control()));
+        cleaned.push_str(newline);
+    }
+
+    cleaned
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strip_escapes() {
+        assert_eq!(
+            strip_ansi("\x1b[1m\x1b[31merror\x1b[0m: oops"),
+            "error: oops"
+        );
+        assert_eq!(
+            strip_ansi("\x1b]0;title\x07\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\"),
+            "link"
+        );
+        assert_eq!(strip_ansi("plain text"), "plain text");
+    }
+
+    #[test]
+    fn clean_output() {
+        assert_eq!(
+            clean_terminal_output(
+                "Building [=>   ] 1/3\rBuilding [==> ] 2/3\rFinished\nok\r\n\x07done\ttoo"
+            ),
+            "Finished\nok\ndone\ttoo"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_merges_output() {
+        let (output, status) =
+            run(Command::new("sh").args(["-c", "echo out; echo err >&2; exit 3"])).unwrap();
+
+        assert_eq!(output, "out\nerr\n");
+        assert_eq!(status.code(), Some(3));
+    }
+}
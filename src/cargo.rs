@@ -0,0 +1,174 @@
+use std::process::Command;
+
+use failure::Error;
+use structopt::StructOpt;
+
+use crate::capture;
+use crate::{Input, Paste, Uploader};
+
+/// Lines cargo prints while it works that say nothing about the outcome of the build.
+const NOISE_PREFIXES: &[&str] = &[
+    "Adding ",
+    "Blocking ",
+    "Checking ",
+    "Compiling ",
+    "Documenting ",
+    "Downloaded ",
+    "Downloading ",
+    "Fresh ",
+    "Locking ",
+    "Packaging ",
+    "Updating ",
+];
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct Cargo {
+    /// Only keep error and warning diagnostics.
+    #[structopt(long = "errors-only")]
+    pub errors_only: bool,
+
+    /// The arguments to pass to cargo, e.g. `build --release`.
+    #[structopt(raw(allow_hyphen_values = "true"))]
+    pub args: Vec<String>,
+}
+
+impl Cargo {
+    /// Run cargo and upload its cleaned-up output.
+    pub fn run(&self, uploader: &Uploader) -> Result<Paste, Error> {
+        let (output, status) = capture::run(
+            Command::new("cargo")
+                .args(&self.args)
+                .env("CARGO_TERM_COLOR", "never")
+                .env("CARGO_TERM_PROGRESS_WHEN", "never"),
+        )?;
+
+        let output = filter_output(&capture::clean_terminal_output(&output), self.errors_only);
+        let (errors, warnings) = count_diagnostics(&output);
+
+        let mut title = format!("cargo {}", self.args.join(" "));
+        if !status.success() {
+            title.push_str(" (failed)");
+        }
+        title.push_str(&format!(
+            ": {} error{}, {} warning{}",
+            errors,
+            if errors == 1 { "" } else { "s" },
+            warnings,
+            if warnings == 1 { "" } else { "s" },
+        ));
+
+        uploader.upload(Input {
+            body: output,
+            title: Some(title),
+            lang: Some(crate::parse_lang("text")),
+        })
+    }
+}
+
+fn is_diagnostic_start(line: &str) -> bool {
+    ["error", "warning"].iter().any(|level| {
+        line.strip_prefix(level)
+            .map(|rest| rest.starts_with(':') || rest.starts_with('['))
+            .unwrap_or(false)
+    })
+}
+
+/// Whether the line is one of cargo's summaries, such as `error: could not compile`.
+fn is_summary(line: &str) -> bool {
+    line.starts_with("error: could not compile")
+        || line.starts_with("error: aborting due to")
+        || (line.starts_with("warning: `") && line.contains(" generated "))
+}
+
+/// Remove cargo's status lines, or everything but the diagnostics if `errors_only` is set.
+fn filter_output(output: &str, errors_only: bool) -> String {
+    let mut filtered = String::with_capacity(output.len());
+    let mut in_diagnostic = false;
+
+    for line in output.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+
+        if errors_only {
+            if is_diagnostic_start(line) {
+                in_diagnostic = true;
+            } else if line.trim().is_empty() {
+                if in_diagnostic {
+                    filtered.push_str(line);
+                }
+                in_diagnostic = false;
+                continue;
+            }
+
+            if in_diagnostic {
+                filtered.push_str(line);
+            }
+        } else if !NOISE_PREFIXES.iter().any(|p| trimmed.starts_with(p)) {
+            filtered.push_str(line);
+        }
+    }
+
+    filtered
+}
+
+fn count_diagnostics(output: &str) -> (usize, usize) {
+    output
+        .lines()
+        .filter(|line| is_diagnostic_start(line) && !is_summary(line))
+        .fold((0, 0), |(errors, warnings), line| {
+            if line.starts_with("error") {
+                (errors + 1, warnings)
+            } else {
+                (errors, warnings + 1)
+            }
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const OUTPUT: &str = "\
+    Updating crates.io index
+   Compiling foo v0.1.0 (/src/foo)
+warning: unused variable: `x`
+ --> src/main.rs:2:9
+  |
+2 |     let x = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_x`
+
+error[E0308]: mismatched types
+ --> src/main.rs:3:18
+  |
+3 |     let y: u32 = \"a\";
+  |                  ^^^ expected `u32`, found `&str`
+
+warning: `foo` (bin \"foo\") generated 1 warning
+error: could not compile `foo` (bin \"foo\") due to 1 previous error; 1 warning emitted
+";
+
+    #[test]
+    fn filter_noise() {
+        let filtered = filter_output(OUTPUT, false);
+
+        assert!(!filtered.contains("Updating"));
+        assert!(!filtered.contains("Compiling"));
+        assert!(filtered.starts_with("warning: unused variable"));
+        assert!(filtered.ends_with("1 warning emitted\n"));
+    }
+
+    #[test]
+    fn filter_errors_only() {
+        let filtered = filter_output(
+            "   Compiling foo\nsome program output\n\nerror: oops\n  |\n\nmore output\n",
+            true,
+        );
+
+        assert_eq!(filtered, "error: oops\n  |\n\n");
+    }
+
+    #[test]
+    fn count() {
+        assert_eq!(count_diagnostics(OUTPUT), (1, 1));
+        assert_eq!(count_diagnostics(""), (0, 0));
+    }
+}
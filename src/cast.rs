@@ -0,0 +1,185 @@
+use std::env;
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use failure::{err_msg, format_err, Error};
+use reqwest::header::ACCEPT;
+use reqwest::multipart::{Form, Part};
+use reqwest::{StatusCode, Url};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use structopt::StructOpt;
+
+use crate::error::UploadError;
+use crate::{describe_request_error, user_name, Paste, Uploader};
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct Cast {
+    /// The recording to upload, as made by `asciinema rec`.
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+
+    /// The asciinema server to upload the recording to.
+    #[structopt(
+        long = "server",
+        env = "ASCIINEMA_API_URL",
+        default_value = "https://asciinema.org"
+    )]
+    server: Url,
+
+    /// The install ID that identifies you to the server.
+    ///
+    /// By default the one saved by the asciinema CLI is used. Run `asciinema auth` to link it
+    /// to an account, or recordings are only kept for a few days.
+    #[structopt(
+        long = "install-id",
+        env = "ASCIINEMA_INSTALL_ID",
+        parse(from_str),
+        hide_env_values = true
+    )]
+    install_id: Option<SecretString>,
+}
+
+/// The first line of an asciicast v2 or v3 recording, or the whole of a v1 recording.
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+    title: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    url: String,
+    message: Option<String>,
+}
+
+impl Cast {
+    /// Upload a terminal recording and return the URL of its player.
+    pub fn run(&self, uploader: &Uploader) -> Result<Paste, Error> {
+        let options = uploader.options;
+        let contents = fs::read(&self.path)
+            .map_err(|e| format_err!("Could not read {}: {}", self.path.display(), e))?;
+        let header = header(&contents)
+            .ok_or_else(|| format_err!("{} is not an asciicast recording", self.path.display()))?;
+
+        let install_id = match self.install_id {
+            Some(ref install_id) => install_id.clone(),
+            None => saved_install_id()?,
+        };
+
+        let len = contents.len() as u64;
+        let part = match uploader.limiter {
+            Some(ref limiter) => {
+                Part::reader_with_length(limiter.throttle(Cursor::new(contents)), len)
+            }
+            None => Part::bytes(contents),
+        };
+        let form = Form::new().part("asciicast", part.file_name("ascii.cast"));
+
+        let network = |e: reqwest::Error| UploadError::Network(describe_request_error(&e));
+        let url = self
+            .server
+            .join("api/asciicasts")
+            .map_err(|e| format_err!("Invalid asciinema server {}: {}", self.server, e))?;
+
+        let request = uploader
+            .client
+            .post(url)
+            .basic_auth(user_name(), Some(install_id.expose_secret()))
+            .header(ACCEPT, "application/json")
+            .multipart(form);
+        let mut rsp = uploader.request(request, None).map_err(network)?;
+        let status = rsp.status();
+        let text = rsp.text().map_err(network)?;
+        let host = self.server.host_str().unwrap_or("The asciinema server");
+
+        match status {
+            status if status.is_success() => {}
+            StatusCode::UNAUTHORIZED => {
+                return Err(format_err!(
+                    "{} did not accept the install ID; run `asciinema auth` to link it to an \
+                     account",
+                    host
+                ));
+            }
+            status if text.trim().is_empty() => return Err(UploadError::Unexpected(status).into()),
+            _ => {
+                return Err(format_err!(
+                    "{} rejected the recording: {}",
+                    host,
+                    text.trim()
+                ))
+            }
+        }
+
+        // Older servers ignore the Accept header and respond with the URL as plain text.
+        let rsp = match serde_json::from_str::<Response>(&text) {
+            Ok(rsp) => rsp,
+            Err(_) => Response {
+                url: Url::parse(text.trim())
+                    .map_err(|_| UploadError::Unexpected(status))?
+                    .into_string(),
+                message: None,
+            },
+        };
+        if let Some(message) = rsp.message {
+            eprintln!("{}", message.trim());
+        }
+
+        Ok(Paste {
+            url: rsp.url,
+            id: None,
+            title: options.title.clone().or(header.title),
+            lang: "asciicast",
+            duration: options.duration,
+            max_views: None,
+        })
+    }
+}
+
+fn header(contents: &[u8]) -> Option<Header> {
+    let first_line = contents.split(|&b| b == b'\n').next()?;
+
+    serde_json::from_slice::<Header>(first_line)
+        .or_else(|_| serde_json::from_slice::<Header>(contents))
+        .ok()
+        .filter(|header| (1..=3).contains(&header.version))
+}
+
+/// The install ID saved by the asciinema CLI, in its configuration directory.
+fn saved_install_id() -> Result<SecretString, Error> {
+    let dir = env::var_os("ASCIINEMA_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("XDG_CONFIG_HOME").map(|dir| PathBuf::from(dir).join("asciinema")))
+        .or_else(|| env::var_os("HOME").map(|dir| PathBuf::from(dir).join(".config/asciinema")))
+        .ok_or_else(|| err_msg("Could not find the asciinema configuration directory"))?;
+
+    match fs::read_to_string(dir.join("install-id")) {
+        Ok(id) if !id.trim().is_empty() => Ok(id.trim().into()),
+        _ => Err(format_err!(
+            "No asciinema install ID was found in {}; run `asciinema auth` or give --install-id",
+            dir.display()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_headers() {
+        let v2 = b"{\"version\": 2, \"width\": 80, \"height\": 24, \"title\": \"demo\"}\n\
+                   [0.5, \"o\", \"hello\"]\n";
+        let parsed = header(v2).unwrap();
+        assert_eq!(parsed.version, 2);
+        assert_eq!(parsed.title.as_deref(), Some("demo"));
+
+        let v1 = b"{\n  \"version\": 1,\n  \"stdout\": []\n}\n";
+        assert_eq!(header(v1).unwrap().version, 1);
+
+        assert!(header(b"[0.5, \"o\", \"hello\"]\n").is_none());
+        assert!(header(b"{\"version\": 9}\n").is_none());
+    }
+}
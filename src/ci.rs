@@ -0,0 +1,219 @@
+use std::env;
+use std::process::Command;
+
+use crate::Paste;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Provider {
+    GitHubActions,
+    GitLabCi,
+    Jenkins,
+    Buildkite,
+}
+
+impl Provider {
+    fn name(self) -> &'static str {
+        match self {
+            Provider::GitHubActions => "GitHub Actions",
+            Provider::GitLabCi => "GitLab CI",
+            Provider::Jenkins => "Jenkins",
+            Provider::Buildkite => "Buildkite",
+        }
+    }
+}
+
+/// The CI job that patisserie is running in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CiEnvironment {
+    pub provider: Provider,
+    pub job: Option<String>,
+    pub build: Option<String>,
+    pub commit: Option<String>,
+}
+
+/// Detect the CI environment from the variables each provider sets for its jobs.
+pub fn detect() -> Option<CiEnvironment> {
+    detect_from(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+}
+
+fn detect_from<F: Fn(&str) -> Option<String>>(var: F) -> Option<CiEnvironment> {
+    let (provider, job, build, commit) = if var("GITHUB_ACTIONS").is_some() {
+        let job = match (var("GITHUB_WORKFLOW"), var("GITHUB_JOB")) {
+            (Some(workflow), Some(job)) => Some(format!("{}/{}", workflow, job)),
+            (workflow, job) => workflow.or(job),
+        };
+
+        (
+            Provider::GitHubActions,
+            job,
+            var("GITHUB_RUN_NUMBER"),
+            var("GITHUB_SHA"),
+        )
+    } else if var("GITLAB_CI").is_some() {
+        (
+            Provider::GitLabCi,
+            var("CI_JOB_NAME"),
+            var("CI_PIPELINE_IID").or_else(|| var("CI_JOB_ID")),
+            var("CI_COMMIT_SHA"),
+        )
+    } else if var("BUILDKITE").is_some() {
+        (
+            Provider::Buildkite,
+            var("BUILDKITE_LABEL").or_else(|| var("BUILDKITE_PIPELINE_SLUG")),
+            var("BUILDKITE_BUILD_NUMBER"),
+            var("BUILDKITE_COMMIT"),
+        )
+    } else if var("JENKINS_URL").is_some() {
+        (
+            Provider::Jenkins,
+            var("JOB_NAME"),
+            var("BUILD_NUMBER"),
+            var("GIT_COMMIT"),
+        )
+    } else {
+        return None;
+    };
+
+    Some(CiEnvironment {
+        provider,
+        job,
+        build,
+        commit,
+    })
+}
+
+impl CiEnvironment {
+    /// A short description of the job, e.g. `GitHub Actions ci/test #42 @1a2b3c4`.
+    pub fn describe(&self) -> String {
+        let mut description = self.provider.name().to_owned();
+
+        if let Some(ref job) = self.job {
+            description.push(' ');
+            description.push_str(job);
+        }
+
+        if let Some(ref build) = self.build {
+            description.push_str(" #");
+            description.push_str(build);
+        }
+
+        if let Some(ref commit) = self.commit {
+            description.push_str(" @");
+            description.extend(commit.chars().take(7));
+        }
+
+        description
+    }
+
+    /// Add the job description to a paste title.
+    pub fn enrich_title(&self, title: Option<String>) -> String {
+        match title {
+            Some(title) => format!("{} ({})", title, self.describe()),
+            None => self.describe(),
+        }
+    }
+
+    /// Surface the paste's URL in the CI provider's interface, where it has a way to do so.
+    pub fn annotate(&self, paste: &Paste) {
+        let title = paste.title.as_deref().unwrap_or("Paste");
+
+        match self.provider {
+            // The runner picks up workflow commands from stderr as well as stdout, and using
+            // stderr keeps stdout to just the URL for scripts that capture it.
+            Provider::GitHubActions => eprintln!("{}", github_notice(title, &paste.url)),
+
+            // Failing to annotate should not fail the job; the URL has already been printed.
+            Provider::Buildkite => {
+                let _ = Command::new("buildkite-agent")
+                    .args(["annotate", "--style", "info", "--context", "patisserie"])
+                    .arg(format!("{}: {}", title, paste.url))
+                    .status();
+            }
+
+            Provider::GitLabCi | Provider::Jenkins => {}
+        }
+    }
+}
+
+/// Format a GitHub Actions `::notice` workflow command, escaping the properties and message.
+fn github_notice(title: &str, url: &str) -> String {
+    let escape_message = |s: &str| {
+        s.replace('%', "%25")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A")
+    };
+    let escape_property = |s: &str| escape_message(s).replace(':', "%3A").replace(',', "%2C");
+
+    format!(
+        "::notice title={}::{}",
+        escape_property(title),
+        escape_message(url)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn detect_with(vars: &[(&str, &str)]) -> Option<CiEnvironment> {
+        let vars = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        detect_from(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn detect_providers() {
+        assert_eq!(detect_with(&[("PATH", "/usr/bin")]), None);
+
+        let github = detect_with(&[
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_WORKFLOW", "ci"),
+            ("GITHUB_JOB", "test"),
+            ("GITHUB_RUN_NUMBER", "42"),
+            ("GITHUB_SHA", "1a2b3c4d5e6f7a8b9c0d1a2b3c4d5e6f7a8b9c0d"),
+        ])
+        .unwrap();
+        assert_eq!(github.provider, Provider::GitHubActions);
+        assert_eq!(github.describe(), "GitHub Actions ci/test #42 @1a2b3c4");
+
+        let gitlab = detect_with(&[
+            ("GITLAB_CI", "true"),
+            ("CI_JOB_NAME", "build"),
+            ("CI_JOB_ID", "1234"),
+        ])
+        .unwrap();
+        assert_eq!(gitlab.describe(), "GitLab CI build #1234");
+
+        let jenkins = detect_with(&[("JENKINS_URL", "https://ci.example.com/")]).unwrap();
+        assert_eq!(jenkins.describe(), "Jenkins");
+
+        let buildkite = detect_with(&[
+            ("BUILDKITE", "true"),
+            ("BUILDKITE_PIPELINE_SLUG", "deploy"),
+            ("BUILDKITE_BUILD_NUMBER", "7"),
+        ])
+        .unwrap();
+        assert_eq!(buildkite.describe(), "Buildkite deploy #7");
+    }
+
+    #[test]
+    fn titles_and_notices() {
+        let ci = detect_with(&[("JENKINS_URL", "x"), ("BUILD_NUMBER", "3")]).unwrap();
+
+        assert_eq!(
+            ci.enrich_title(Some("build.log".into())),
+            "build.log (Jenkins #3)"
+        );
+        assert_eq!(ci.enrich_title(None), "Jenkins #3");
+
+        assert_eq!(
+            github_notice("build.log: 50%, done", "https://www.pastery.net/abc/"),
+            "::notice title=build.log%3A 50%25%2C done::https://www.pastery.net/abc/"
+        );
+    }
+}
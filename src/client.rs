@@ -0,0 +1,303 @@
+//! Creating pastes with the Pastery API.
+
+use std::fmt;
+use std::time::Duration;
+
+use reqwest::{StatusCode, Url};
+#[cfg(feature = "async")]
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+use crate::duration::ONE_DAY;
+use crate::languages::{self, AUTODETECT};
+
+/// The URL that pastes are uploaded to.
+pub const PASTERY_URL: &str = "https://www.pastery.net/api/paste/";
+
+/// What to ask Pastery for when creating a paste.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PasteRequest {
+    title: Option<String>,
+    language: &'static str,
+    duration: Duration,
+    max_views: Option<u32>,
+}
+
+impl Default for PasteRequest {
+    fn default() -> Self {
+        PasteRequest {
+            title: None,
+            language: AUTODETECT,
+            duration: ONE_DAY,
+            max_views: None,
+        }
+    }
+}
+
+impl PasteRequest {
+    /// A paste that lasts for a day, in a language that Pastery detects.
+    pub fn new() -> Self {
+        PasteRequest::default()
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// The language to highlight the paste in, which is detected if Pastery does not know it.
+    pub fn language(mut self, language: &str) -> Self {
+        self.language = languages::parse(language);
+        self
+    }
+
+    /// The language to highlight the paste in, as it is given, for languages that Pastery has
+    /// added since this version of the library was built.
+    pub fn known_language(mut self, language: &'static str) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// How long the paste lasts, to the minute.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// The number of views after which the paste is deleted, where 0 means no limit.
+    pub fn max_views(mut self, max_views: u32) -> Self {
+        self.max_views = Some(max_views).filter(|&views| views > 0);
+        self
+    }
+
+    /// The URL to create the paste with at `api_url`.
+    pub fn url(&self, api_url: &Url, api_key: &str) -> Url {
+        let mut url = api_url.clone();
+        {
+            let mut query_pairs = url.query_pairs_mut();
+
+            let duration_in_min = self.duration.as_secs() / 60;
+
+            query_pairs
+                .append_pair("api_key", api_key)
+                .append_pair("language", self.language)
+                .append_pair("duration", &duration_in_min.to_string());
+
+            if let Some(max_views) = self.max_views {
+                query_pairs.append_pair("max_views", &max_views.to_string());
+            }
+
+            if let Some(ref title) = self.title {
+                query_pairs.append_pair("title", title);
+            }
+        }
+
+        url
+    }
+}
+
+/// A paste as Pastery describes it once it has been created.
+///
+/// Only the URL is always there. The rest is what Pastery made of the request, such as the
+/// language it detected.
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct Created {
+    pub url: String,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    /// How long the paste lasts, in minutes.
+    #[serde(default)]
+    pub duration: Option<u64>,
+    #[serde(default)]
+    pub max_views: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Response {
+    Error { error_msg: String },
+    Paste(Created),
+}
+
+/// Why Pastery did not create a paste.
+#[derive(Debug, PartialEq)]
+pub enum ApiError {
+    /// The request could not be sent or the response could not be read.
+    Network(String),
+
+    /// Pastery refused the request, with its reason.
+    Rejected { status: StatusCode, message: String },
+
+    /// A response that was neither a paste nor an error.
+    Unexpected(StatusCode),
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Network(message) => write!(f, "Could not reach Pastery: {}", message),
+            ApiError::Rejected { message, .. } => {
+                write!(f, "Pastery rejected the paste: {}", message)
+            }
+            ApiError::Unexpected(status) => {
+                write!(f, "Pastery returned an unexpected response ({})", status)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Read the response to a request to create a paste.
+pub fn parse_response(status: StatusCode, text: &str) -> Result<Created, ApiError> {
+    match serde_json::from_str::<Response>(text) {
+        Ok(Response::Paste(created)) if status.is_success() => Ok(created),
+        Ok(Response::Error { error_msg }) => Err(ApiError::Rejected {
+            status,
+            message: error_msg,
+        }),
+        Ok(Response::Paste(_)) | Err(_) => Err(ApiError::Unexpected(status)),
+    }
+}
+
+/// Describe a request error without the request URL, which may contain credentials.
+pub fn describe_request_error(e: &reqwest::Error) -> String {
+    let message = e.to_string();
+
+    match e.url() {
+        Some(url) => message
+            .strip_prefix(&format!("{}: ", url))
+            .map(String::from)
+            .unwrap_or(message),
+        None => message,
+    }
+}
+
+/// A client for creating pastes with an API key, from inside a Tokio runtime.
+///
+/// `blocking::Client` does the same without one.
+#[cfg(feature = "async")]
+pub struct Client {
+    http: reqwest_async::Client,
+    api_url: Url,
+    api_key: SecretString,
+}
+
+#[cfg(feature = "async")]
+impl Client {
+    /// A client that uploads to Pastery itself.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Client {
+            http: reqwest_async::Client::new(),
+            api_url: Url::parse(PASTERY_URL).unwrap(),
+            api_key: api_key.into().into(),
+        }
+    }
+
+    /// Upload to another instance of the API, `api_url`.
+    pub fn api_url(mut self, api_url: Url) -> Self {
+        self.api_url = api_url;
+        self
+    }
+
+    /// Send requests with `http`, such as one that goes through a proxy.
+    pub fn http_client(mut self, http: reqwest_async::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Create a paste of `body`.
+    pub async fn upload(
+        &self,
+        request: &PasteRequest,
+        body: impl Into<reqwest_async::Body>,
+    ) -> Result<Created, ApiError> {
+        // The request URL contains the API key, so it is kept out of the error.
+        let network = |e: reqwest_async::Error| ApiError::Network(e.without_url().to_string());
+
+        let url = request.url(&self.api_url, self.api_key.expose_secret());
+        let rsp = self
+            .http
+            .post(url.as_str())
+            .body(body)
+            .send()
+            .await
+            .map_err(network)?;
+        let status = StatusCode::from_u16(rsp.status().as_u16()).unwrap();
+        let text = rsp.text().await.map_err(network)?;
+
+        parse_response(status, &text)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::duration::ONE_WEEK;
+
+    #[test]
+    fn build_urls() {
+        let api_url = Url::parse(PASTERY_URL).unwrap();
+
+        assert_eq!(
+            PasteRequest::new().url(&api_url, "foo").as_str(),
+            "https://www.pastery.net/api/paste/?api_key=foo&language=autodetect&duration=1440"
+        );
+        assert_eq!(
+            PasteRequest::new()
+                .title("main.rs")
+                .language("rust")
+                .duration(ONE_WEEK)
+                .max_views(10)
+                .url(&api_url, "foo")
+                .as_str(),
+            "https://www.pastery.net/api/paste/?api_key=foo&language=rust&duration=10080&max_views=10&title=main.rs"
+        );
+        assert_eq!(
+            PasteRequest::new()
+                .language("asdf")
+                .max_views(0)
+                .url(&api_url, "foo")
+                .as_str(),
+            "https://www.pastery.net/api/paste/?api_key=foo&language=autodetect&duration=1440"
+        );
+    }
+
+    #[test]
+    fn parse_responses() {
+        let created = parse_response(
+            StatusCode::OK,
+            r#"{"id": "abcdef", "url": "https://www.pastery.net/abcdef/", "duration": 60}"#,
+        )
+        .unwrap();
+        assert_eq!(created.id.as_deref(), Some("abcdef"));
+        assert_eq!(created.duration, Some(60));
+        assert_eq!(created.language, None);
+
+        assert_eq!(
+            parse_response(
+                StatusCode::UNAUTHORIZED,
+                r#"{"error_msg": "Invalid API key."}"#
+            ),
+            Err(ApiError::Rejected {
+                status: StatusCode::UNAUTHORIZED,
+                message: "Invalid API key.".into(),
+            })
+        );
+        assert_eq!(
+            parse_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                r#"{"url": "https://www.pastery.net/abcdef/"}"#
+            ),
+            Err(ApiError::Unexpected(StatusCode::INTERNAL_SERVER_ERROR))
+        );
+        assert_eq!(
+            parse_response(StatusCode::OK, "<html>"),
+            Err(ApiError::Unexpected(StatusCode::OK))
+        );
+    }
+}
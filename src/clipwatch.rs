@@ -0,0 +1,264 @@
+use std::env;
+use std::fs;
+use std::io::{stderr, stdin, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use chrono::Local;
+use failure::{err_msg, format_err, Error};
+use structopt::StructOpt;
+
+use crate::i18n::{self, tr};
+use crate::{parse_in_range, Input, Paste, Uploader};
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct Clipwatch {
+    /// When to upload what was copied: confirm, size:<bytes>, or file:<path>.
+    ///
+    /// confirm asks on the terminal about each copy. size:<bytes> uploads every copy of at least
+    /// that many bytes. file:<path> uploads the clipboard whenever the file is touched, which
+    /// can be bound to a hotkey with e.g. `touch ~/.cache/patisserie-clip`.
+    #[structopt(long = "trigger", default_value = "confirm", parse(try_from_str))]
+    trigger: Trigger,
+
+    /// How often to check the clipboard, in milliseconds.
+    #[structopt(
+        long = "interval",
+        default_value = "500",
+        parse(try_from_str = "parse_interval")
+    )]
+    interval: Duration,
+
+    /// Stop after the first upload.
+    #[structopt(long = "once")]
+    once: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Trigger {
+    Confirm,
+    Size(usize),
+    File(PathBuf),
+}
+
+impl FromStr for Trigger {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        if s == "confirm" {
+            Ok(Trigger::Confirm)
+        } else if let Some(size) = s.strip_prefix("size:") {
+            size.parse()
+                .map(Trigger::Size)
+                .map_err(|_| format_err!("Invalid size {}, expected a number of bytes", size))
+        } else if let Some(path) = s.strip_prefix("file:").filter(|path| !path.is_empty()) {
+            Ok(Trigger::File(path.into()))
+        } else {
+            Err(format_err!(
+                "Invalid trigger {}, expected confirm, size:<bytes>, or file:<path>",
+                s
+            ))
+        }
+    }
+}
+
+fn parse_interval(s: &str) -> Result<Duration, Error> {
+    parse_in_range(s, 50, 60_000).map(|ms| Duration::from_millis(ms.into()))
+}
+
+/// The clipboard tools that patisserie knows how to drive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Clipboard {
+    /// wl-paste and wl-copy, on Wayland.
+    Wayland,
+    /// xclip, on X11.
+    Xclip,
+    /// pbpaste and pbcopy, on macOS.
+    Pasteboard,
+}
+
+impl Clipboard {
+    fn detect() -> Result<Self, Error> {
+        if cfg!(target_os = "macos") {
+            Ok(Clipboard::Pasteboard)
+        } else if cfg!(unix) && env::var_os("WAYLAND_DISPLAY").is_some() {
+            Ok(Clipboard::Wayland)
+        } else if cfg!(unix) && env::var_os("DISPLAY").is_some() {
+            Ok(Clipboard::Xclip)
+        } else {
+            Err(err_msg(
+                "The clipboard can only be watched on Wayland (with wl-clipboard), X11 (with \
+                 xclip), and macOS",
+            ))
+        }
+    }
+
+    fn paste_command(self) -> Command {
+        match self {
+            Clipboard::Wayland => command("wl-paste", &["--no-newline", "--type", "text"]),
+            Clipboard::Xclip => command("xclip", &["-selection", "clipboard", "-out"]),
+            Clipboard::Pasteboard => command("pbpaste", &[]),
+        }
+    }
+
+    fn copy_command(self) -> Command {
+        match self {
+            Clipboard::Wayland => command("wl-copy", &[]),
+            Clipboard::Xclip => command("xclip", &["-selection", "clipboard", "-in"]),
+            Clipboard::Pasteboard => command("pbcopy", &[]),
+        }
+    }
+
+    /// The text on the clipboard, which is empty when nothing or something other than text
+    /// was copied.
+    fn read(self) -> Result<String, Error> {
+        let mut command = self.paste_command();
+        let program = command.get_program().to_string_lossy().into_owned();
+        let output = command
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| format_err!("Could not run {}: {}", program, e))?;
+
+        if !output.status.success() {
+            return Ok(String::new());
+        }
+
+        Ok(String::from_utf8(output.stdout).unwrap_or_default())
+    }
+
+    fn write(self, text: &str) -> Result<(), Error> {
+        let mut command = self.copy_command();
+        let program = command.get_program().to_string_lossy().into_owned();
+
+        // wl-copy and xclip stay behind to serve the clipboard, so their output is not waited on.
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format_err!("Could not run {}: {}", program, e))?;
+        child.stdin.take().unwrap().write_all(text.as_bytes())?;
+
+        match child.wait()? {
+            status if status.success() => Ok(()),
+            status => Err(format_err!("{} failed ({})", program, status)),
+        }
+    }
+}
+
+fn command(program: &str, args: &[&str]) -> Command {
+    let mut command = Command::new(program);
+    command.args(args);
+    command
+}
+
+impl Clipwatch {
+    /// Watch the clipboard, upload what is copied when the trigger says so, and replace it with
+    /// the URL of the paste.
+    ///
+    /// Whatever was on the clipboard when watching started is left alone. Failed uploads are
+    /// reported and watching carries on.
+    pub fn run<F>(&self, uploader: &Uploader, mut publish: F) -> Result<(), Error>
+    where
+        F: FnMut(&Paste) -> Result<(), Error>,
+    {
+        let clipboard = Clipboard::detect()?;
+        if self.trigger == Trigger::Confirm && !(stdin().is_terminal() && stderr().is_terminal()) {
+            return Err(err_msg(
+                "--trigger confirm needs a terminal; use --trigger size:<bytes> or file:<path>",
+            ));
+        }
+
+        let mut last = clipboard.read()?;
+        let mut touched = self.touched();
+        eprintln!("{}", tr!("clipboard-watching"));
+
+        loop {
+            thread::sleep(self.interval);
+
+            let text = clipboard.read()?;
+            let changed = text != last;
+            last = text.clone();
+
+            let upload = match self.trigger {
+                Trigger::Confirm => changed && !text.trim().is_empty() && confirm(&text),
+                Trigger::Size(size) => changed && !text.trim().is_empty() && text.len() >= size,
+                Trigger::File(_) => {
+                    let now = self.touched();
+                    let upload = now != touched && !text.trim().is_empty();
+                    touched = now;
+                    upload
+                }
+            };
+            if !upload {
+                continue;
+            }
+
+            let input = Input {
+                body: text,
+                title: Some(format!(
+                    "Clipboard {}",
+                    Local::now().format("%Y-%m-%d %H:%M")
+                )),
+                lang: None,
+            };
+            match uploader.upload(input) {
+                Ok(paste) => {
+                    clipboard.write(&paste.url)?;
+                    last = paste.url.clone();
+                    publish(&paste)?;
+                }
+                Err(e) => eprintln!("{}", tr!("error", message = e.to_string())),
+            }
+
+            if self.once {
+                return Ok(());
+            }
+        }
+    }
+
+    /// When the --trigger file was last touched.
+    fn touched(&self) -> Option<SystemTime> {
+        match self.trigger {
+            Trigger::File(ref path) => fs::metadata(path).and_then(|m| m.modified()).ok(),
+            _ => None,
+        }
+    }
+}
+
+fn confirm(text: &str) -> bool {
+    let question = tr!(
+        "clipboard-confirm",
+        lines = text.lines().count(),
+        bytes = text.len()
+    );
+    eprint!("{} {} ", question, tr!("prompt-choices"));
+    let _ = stderr().flush();
+
+    let mut answer = String::new();
+    match stdin().lock().read_line(&mut answer) {
+        Ok(_) => i18n::is_yes(&answer),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_triggers() {
+        assert_eq!("confirm".parse::<Trigger>().unwrap(), Trigger::Confirm);
+        assert_eq!("size:1024".parse::<Trigger>().unwrap(), Trigger::Size(1024));
+        assert_eq!(
+            "file:/tmp/clip".parse::<Trigger>().unwrap(),
+            Trigger::File("/tmp/clip".into())
+        );
+        assert!("size:big".parse::<Trigger>().is_err());
+        assert!("file:".parse::<Trigger>().is_err());
+        assert!("hotkey".parse::<Trigger>().is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::ops::Deref;
+use std::path::Path;
+use std::str;
+use std::sync::Arc;
+
+use failure::Error;
+use memmap2::Mmap;
+
+/// Files at least this large are memory-mapped instead of read into memory.
+const MMAP_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// The contents of a file, either memory-mapped or read into a buffer.
+pub enum Contents {
+    Mapped(Mmap),
+    Read(Vec<u8>),
+}
+
+impl Contents {
+    /// Open a file, memory-mapping it if it is large.
+    ///
+    /// Anything that cannot be mapped, such as a pipe or a file in `/proc`, is read instead.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        open_mapped_above(path, MMAP_THRESHOLD)
+    }
+}
+
+impl AsRef<[u8]> for Contents {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Deref for Contents {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Contents::Mapped(map) => map,
+            Contents::Read(buffer) => buffer,
+        }
+    }
+}
+
+/// Contents that can be read more than once without being copied.
+#[derive(Clone)]
+pub struct Shared(Arc<Contents>);
+
+impl Shared {
+    pub fn new(contents: Contents) -> Self {
+        Shared(Arc::new(contents))
+    }
+}
+
+impl AsRef<[u8]> for Shared {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// A reader that fails if its input is not valid UTF-8.
+///
+/// Only the bytes of the most recent read are held, so arbitrarily long input can be checked
+/// as it is streamed.
+pub struct Utf8Reader<R> {
+    inner: R,
+    unchecked: Vec<u8>,
+}
+
+impl<R: Read> Utf8Reader<R> {
+    pub fn new(inner: R) -> Self {
+        Utf8Reader {
+            inner,
+            unchecked: Vec::new(),
+        }
+    }
+}
+
+impl<R: Read> Read for Utf8Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "input is not valid UTF-8");
+
+        let n = self.inner.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            return if self.unchecked.is_empty() {
+                Ok(0)
+            } else {
+                Err(invalid())
+            };
+        }
+
+        // Anything left over from the last read is the start of a character that was split
+        // between reads.
+        self.unchecked.extend_from_slice(&buf[..n]);
+        match str::from_utf8(&self.unchecked) {
+            Ok(_) => self.unchecked.clear(),
+            Err(e) if e.error_len().is_none() => {
+                self.unchecked.drain(..e.valid_up_to());
+            }
+            Err(_) => return Err(invalid()),
+        }
+
+        Ok(n)
+    }
+}
+
+fn open_mapped_above(path: &Path, threshold: u64) -> Result<Contents, Error> {
+    let mut f = File::open(path)?;
+    let metadata = f.metadata()?;
+
+    if metadata.is_file() && metadata.len() >= threshold {
+        // SAFETY: The map is only ever read. If another process truncates the file while it is
+        // mapped, reading past the new end raises SIGBUS, which is the same risk every tool that
+        // maps its input accepts; a concurrent write can at worst produce a torn paste.
+        if let Ok(map) = unsafe { Mmap::map(&f) } {
+            return Ok(Contents::Mapped(map));
+        }
+    }
+
+    let mut buffer = Vec::new();
+    f.read_to_end(&mut buffer)?;
+
+    Ok(Contents::Read(buffer))
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn map_and_read_files() {
+        let path = env::temp_dir().join(format!("patisserie-contents-{}", std::process::id()));
+        fs::write(&path, "fn main() {}\n").unwrap();
+
+        let mapped = open_mapped_above(&path, 0).unwrap();
+        assert!(matches!(mapped, Contents::Mapped(_)));
+        assert_eq!(&*mapped, b"fn main() {}\n");
+
+        let read = Contents::open(&path).unwrap();
+        assert!(matches!(read, Contents::Read(_)));
+        assert_eq!(&*read, b"fn main() {}\n");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    fn read_bytewise(input: &[u8]) -> io::Result<Vec<u8>> {
+        let mut reader = Utf8Reader::new(input);
+        let mut output = Vec::new();
+        let mut byte = [0];
+
+        while reader.read(&mut byte)? == 1 {
+            output.push(byte[0]);
+        }
+
+        Ok(output)
+    }
+
+    #[test]
+    fn validate_utf8() {
+        let text = "crème brûlée 🍮\n".as_bytes();
+        assert_eq!(read_bytewise(text).unwrap(), text);
+
+        let mut output = Vec::new();
+        Utf8Reader::new(text).read_to_end(&mut output).unwrap();
+        assert_eq!(output, text);
+
+        assert!(read_bytewise(b"caf\xe9 au lait").is_err());
+        assert!(read_bytewise(&text[..text.len() - 2]).is_err());
+    }
+}
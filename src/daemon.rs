@@ -0,0 +1,360 @@
+//! A daemon that uploads pastes for other patisserie processes over a Unix socket.
+//!
+//! Each connection carries one request, a line of JSON with `{"content", "title"?,
+//! "language"?}`, and gets one line back: `{"url", "title", "language", "duration"}` with the
+//! duration in seconds, or `{"error"}`. The daemon keeps its HTTP client, so repeated pastes
+//! reuse its connections and TLS sessions instead of paying for them every time.
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::os::unix::io::FromRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use failure::{err_msg, format_err, Error};
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+use crate::i18n::tr;
+use crate::systemd::{self, Idle};
+use crate::{
+    lang_of, parse_lang, parse_timeout, paste_title, Input, Options, Paste, Uploader, AUTODETECT,
+};
+
+/// How long the daemon installed with `patisserie daemon install` waits for pastes, in seconds.
+const DEFAULT_IDLE_TIMEOUT: u64 = 600;
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct Daemon {
+    /// The socket to listen on, instead of patisserie.sock in $XDG_RUNTIME_DIR, or without it
+    /// in a directory of your own in the temporary directory.
+    ///
+    /// Ignored when systemd passes the socket to the daemon.
+    #[structopt(long = "socket", parse(from_os_str))]
+    socket: Option<PathBuf>,
+
+    /// Exit once no pastes have been sent for this many seconds.
+    #[structopt(long = "idle-timeout", parse(try_from_str = "parse_timeout"))]
+    idle_timeout: Option<Duration>,
+
+    #[structopt(subcommand)]
+    command: Option<DaemonCommand>,
+}
+
+#[derive(Clone, Debug, StructOpt)]
+enum DaemonCommand {
+    /// Write systemd user units that start the daemon when something connects to its socket.
+    ///
+    /// The daemon reads PASTERY_API_KEY from patisserie/daemon.env in your configuration
+    /// directory, and exits again once it has been idle for --idle-timeout.
+    #[structopt(name = "install")]
+    Install(Install),
+}
+
+#[derive(Clone, Debug, StructOpt)]
+struct Install {
+    /// Where to write the units, instead of $XDG_CONFIG_HOME/systemd/user.
+    #[structopt(long = "dir", parse(from_os_str))]
+    dir: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Request {
+    content: String,
+    title: Option<String>,
+    language: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum Response {
+    Paste {
+        url: String,
+        #[serde(default)]
+        id: Option<String>,
+        title: Option<String>,
+        language: String,
+        duration: u64,
+        #[serde(default)]
+        max_views: Option<u32>,
+    },
+    Error {
+        error: String,
+    },
+}
+
+/// The user that patisserie runs as.
+fn uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+/// Where the daemon listens unless told otherwise: in the user's runtime directory, which only
+/// they can reach.
+///
+/// Without one, it is in a directory of the user's own in the temporary directory, which is
+/// created if it is missing. Since anyone can create it first, one that belongs to another user
+/// or that others can reach is refused.
+pub fn default_socket() -> Result<PathBuf, Error> {
+    if let Some(dir) = env::var_os("XDG_RUNTIME_DIR") {
+        return Ok(PathBuf::from(dir).join("patisserie.sock"));
+    }
+
+    let dir = env::temp_dir().join(format!("patisserie-{}", uid()));
+    if let Err(e) = fs::DirBuilder::new().mode(0o700).create(&dir) {
+        if e.kind() != io::ErrorKind::AlreadyExists {
+            return Err(format_err!("Could not create {}: {}", dir.display(), e));
+        }
+    }
+
+    let metadata = fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid() || metadata.mode() & 0o077 != 0 {
+        return Err(format_err!(
+            "{} is not a directory that only you can reach, so the daemon's socket cannot be \
+             kept in it; give --socket or set XDG_RUNTIME_DIR",
+            dir.display()
+        ));
+    }
+
+    Ok(dir.join("patisserie.sock"))
+}
+
+impl Daemon {
+    /// Listen for pastes until killed or idle, uploading each with the daemon's own options.
+    pub fn run(&self, uploader: &Uploader) -> Result<(), Error> {
+        if let Some(DaemonCommand::Install(ref install)) = self.command {
+            return self.install(install);
+        }
+
+        let listener = match systemd::listen_fd()? {
+            // systemd created the socket, and keeps it for the next daemon once this one exits.
+            Some(fd) => unsafe { UnixListener::from_raw_fd(fd) },
+            None => {
+                let path = match self.socket {
+                    Some(ref path) => path.clone(),
+                    None => default_socket()?,
+                };
+                let listener = bind(&path)?;
+                eprintln!("{}", tr!("listening", address = path.display().to_string()));
+                listener
+            }
+        };
+        let idle = self.idle_timeout.map(Idle::new);
+
+        thread::scope(|scope| {
+            if let Some(ref idle) = idle {
+                scope.spawn(move || idle.exit_when_idle());
+            }
+
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let busy = idle.as_ref().map(Idle::busy);
+                        scope.spawn(move || {
+                            let _busy = busy;
+                            if let Err(e) = serve(uploader, stream) {
+                                eprintln!("{}", tr!("error", message = e.to_string()));
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        let message = tr!("not-accepted", error = e.to_string());
+                        eprintln!("{}", tr!("error", message = message));
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn install(&self, install: &Install) -> Result<(), Error> {
+        let dir = match install.dir {
+            Some(ref dir) => dir.clone(),
+            None => systemd::user_unit_dir()?,
+        };
+        let idle_timeout = self
+            .idle_timeout
+            .unwrap_or(Duration::from_secs(DEFAULT_IDLE_TIMEOUT));
+
+        for path in systemd::install(&dir, &env::current_exe()?, idle_timeout)? {
+            println!("Wrote {}", path.display());
+        }
+        println!(
+            "Put PASTERY_API_KEY=<key> in patisserie/daemon.env in your configuration directory, \
+             then run:\n    systemctl --user daemon-reload\n    systemctl --user enable --now \
+             patisserie.socket"
+        );
+
+        Ok(())
+    }
+}
+
+/// Listen on `path`, replacing a socket left behind by a daemon that is no longer running.
+fn bind(path: &Path) -> Result<UnixListener, Error> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format_err!(
+                "A daemon is already listening on {}",
+                path.display()
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+
+    // Anyone who can connect can paste with the daemon's API key, so the socket is created
+    // without access for others rather than being opened up until it is changed. Nothing else is
+    // running yet to be affected by the umask.
+    let umask = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(path);
+    unsafe { libc::umask(umask) };
+    let listener =
+        listener.map_err(|e| format_err!("Could not listen on {}: {}", path.display(), e))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+
+    Ok(listener)
+}
+
+fn serve(uploader: &Uploader, stream: UnixStream) -> Result<(), Error> {
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let response = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => {
+            let input = Input {
+                body: request.content,
+                title: request.title,
+                lang: request.language.as_deref().map(parse_lang),
+            };
+
+            match uploader.upload(input) {
+                Ok(paste) => Response::Paste {
+                    url: paste.url,
+                    id: paste.id,
+                    title: paste.title,
+                    language: paste.lang.into(),
+                    duration: paste.duration.as_secs(),
+                    max_views: paste.max_views,
+                },
+                Err(e) => Response::Error {
+                    error: e.to_string(),
+                },
+            }
+        }
+        Err(e) => Response::Error {
+            error: format!("Invalid request: {}", e),
+        },
+    };
+
+    let mut stream = &stream;
+    writeln!(stream, "{}", serde_json::to_string(&response)?)?;
+    Ok(())
+}
+
+/// Hand the paste to a running daemon instead of uploading it, for --via-daemon.
+///
+/// Only the contents, title, and language are sent; everything else comes from the options the
+/// daemon was started with.
+pub fn forward(options: &Options) -> Result<Paste, Error> {
+    let content = match options.paths.as_slice() {
+        [] => {
+            let mut content = String::new();
+            std::io::stdin().read_to_string(&mut content)?;
+            content
+        }
+        [path] => fs::read_to_string(path)
+            .map_err(|e| format_err!("Could not read {}: {}", path.display(), e))?,
+        _ => {
+            return Err(err_msg(
+                "--via-daemon uploads a single file or standard input",
+            ))
+        }
+    };
+
+    let request = Request {
+        content,
+        title: paste_title(options),
+        language: match options
+            .paths
+            .first()
+            .map_or(options.lang, |path| lang_of(options, path))
+        {
+            AUTODETECT => None,
+            lang => Some(lang.into()),
+        },
+    };
+
+    let path = match options.daemon_socket {
+        Some(ref path) => path.clone(),
+        None => default_socket()?,
+    };
+    // Whoever owns the socket gets the paste, so it has to be the daemon of this user.
+    if let Ok(metadata) = fs::metadata(&path) {
+        if metadata.uid() != uid() {
+            return Err(format_err!(
+                "{} belongs to another user, so the paste is not sent to it",
+                path.display()
+            ));
+        }
+    }
+    let mut stream = UnixStream::connect(&path).map_err(|e| {
+        format_err!(
+            "Could not reach the daemon at {} ({}); start it with `patisserie daemon`",
+            path.display(),
+            e
+        )
+    })?;
+    writeln!(stream, "{}", serde_json::to_string(&request)?)?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    match serde_json::from_str(&line) {
+        Ok(Response::Paste {
+            url,
+            id,
+            title,
+            language,
+            duration,
+            max_views,
+        }) => Ok(Paste {
+            url,
+            id,
+            title,
+            lang: parse_lang(&language),
+            duration: Duration::from_secs(duration),
+            max_views,
+        }),
+        Ok(Response::Error { error }) => Err(format_err!(
+            "The daemon could not upload the paste: {}",
+            error
+        )),
+        Err(_) => Err(err_msg("The daemon sent an invalid response")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_responses() {
+        let paste: Response = serde_json::from_str(
+            r#"{"url":"https://www.pastery.net/abcdef/","title":null,"language":"rust","duration":86400}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            paste,
+            Response::Paste {
+                duration: 86400,
+                ..
+            }
+        ));
+
+        let error: Response = serde_json::from_str(r#"{"error":"Invalid API key"}"#).unwrap();
+        assert!(matches!(error, Response::Error { .. }));
+    }
+}
@@ -0,0 +1,106 @@
+use std::io::{stderr, stdin, BufRead, IsTerminal, Write};
+
+use failure::{err_msg, Error};
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use structopt::StructOpt;
+
+use crate::error::UploadError;
+use crate::get::{self, paste_id};
+use crate::i18n::{self, tr};
+use crate::{api_url, describe_request_error, Uploader};
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct Delete {
+    /// The IDs or URLs of the pastes, such as abcdef or https://www.pastery.net/abcdef/.
+    #[structopt(raw(required = "true", min_values = "1"))]
+    pastes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error_msg: String,
+}
+
+/// Ask whether to delete the pastes, listing them by ID and title.
+fn confirm(uploader: &Uploader, ids: &[String]) -> Result<bool, Error> {
+    // Fetching the pastes first also makes sure that they all exist before any is deleted.
+    for paste in get::fetch(uploader, ids)? {
+        match paste.title.trim() {
+            "" => eprintln!("  {}", paste.id),
+            title => eprintln!("  {}  {}", paste.id, title),
+        }
+    }
+    eprint!(
+        "{} {} ",
+        tr!("delete-confirm", count = ids.len()),
+        tr!("prompt-choices")
+    );
+    let _ = stderr().flush();
+
+    let mut answer = String::new();
+    stdin().lock().read_line(&mut answer)?;
+    Ok(i18n::is_yes(&answer))
+}
+
+/// Delete the paste called `id`.
+fn delete(uploader: &Uploader, id: &str) -> Result<(), Error> {
+    let mut url = api_url(uploader.options).join(&format!("{}/", id))?;
+    url.query_pairs_mut().append_pair(
+        "api_key",
+        uploader
+            .options
+            .api_key
+            .as_ref()
+            .map_or("", ExposeSecret::expose_secret),
+    );
+
+    let network = |e: reqwest::Error| UploadError::Network(describe_request_error(&e));
+    let mut rsp = uploader
+        .request(uploader.client.delete(url), None)
+        .map_err(network)?;
+    let status = rsp.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let text = rsp.text().map_err(network)?;
+    match serde_json::from_str::<ErrorResponse>(&text) {
+        Ok(ErrorResponse { error_msg }) => Err(UploadError::from_api(status, error_msg).into()),
+        Err(_) => Err(UploadError::Unexpected(status).into()),
+    }
+}
+
+impl Delete {
+    /// Delete the pastes, in the order they are given, once the user has agreed to.
+    ///
+    /// A deleted paste cannot be brought back, so outside a terminal, where nobody can be
+    /// asked, nothing is deleted without --yes. Each paste is reported as it is deleted, and
+    /// the first that cannot be deleted stops the rest.
+    pub fn run(&self, uploader: &Uploader) -> Result<(), Error> {
+        get::check_backend(uploader.options, "delete")?;
+        let ids = self
+            .pastes
+            .iter()
+            .map(|paste| paste_id(paste, &uploader.options.base_url))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if !uploader.options.yes {
+            if !(stdin().is_terminal() && stderr().is_terminal()) {
+                return Err(err_msg(
+                    "Deleting a paste cannot be undone; give --yes to delete without being asked",
+                ));
+            }
+            if !confirm(uploader, &ids)? {
+                return Err(err_msg(tr!("delete-cancelled")));
+            }
+        }
+
+        for id in &ids {
+            delete(uploader, id)?;
+            eprintln!("{}", tr!("paste-deleted", id = id.as_str()));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use std::path::Path;
+
+use failure::Error;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+/// Files that almost certainly contain secrets and should never be pasted by accident.
+pub const DEFAULT_PATTERNS: &[&str] = &[
+    "id_rsa*",
+    "id_dsa*",
+    "id_ecdsa*",
+    "id_ed25519*",
+    "*.pem",
+    "*.key",
+    "*.p12",
+    "*.pfx",
+    "*.kdbx",
+    "*.gpg",
+    ".env",
+    ".env.*",
+    ".netrc",
+    ".pgpass",
+    ".htpasswd",
+    "credentials",
+    "credentials.json",
+];
+
+/// A set of glob patterns matching files that must not be uploaded without `--force`.
+pub struct Denylist {
+    patterns: Vec<String>,
+    set: GlobSet,
+}
+
+impl Denylist {
+    /// Build a denylist from the default patterns and any extra user-provided patterns.
+    pub fn new(extra: &[String]) -> Result<Self, Error> {
+        let patterns = DEFAULT_PATTERNS
+            .iter()
+            .map(|p| String::from(*p))
+            .chain(extra.iter().cloned())
+            .collect::<Vec<_>>();
+
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &patterns {
+            builder.add(Glob::new(pattern)?);
+        }
+
+        Ok(Denylist {
+            set: builder.build()?,
+            patterns,
+        })
+    }
+
+    /// Return the first pattern that matches either the file name or the full path.
+    pub fn matching_pattern(&self, path: &Path) -> Option<&str> {
+        let mut matches = self.set.matches(path);
+
+        if let Some(file_name) = path.file_name() {
+            matches.extend(self.set.matches(file_name));
+        }
+
+        matches
+            .into_iter()
+            .min()
+            .map(|idx| self.patterns[idx].as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_patterns() {
+        let denylist = Denylist::new(&[]).unwrap();
+
+        assert_eq!(
+            denylist.matching_pattern(Path::new("/home/me/.ssh/id_rsa")),
+            Some("id_rsa*")
+        );
+        assert_eq!(
+            denylist.matching_pattern(Path::new("id_ed25519.pub")),
+            Some("id_ed25519*")
+        );
+        assert_eq!(
+            denylist.matching_pattern(Path::new("certs/server.pem")),
+            Some("*.pem")
+        );
+        assert_eq!(denylist.matching_pattern(Path::new(".env")), Some(".env"));
+        assert_eq!(
+            denylist.matching_pattern(Path::new("app/.env.production")),
+            Some(".env.*")
+        );
+        assert_eq!(denylist.matching_pattern(Path::new("src/main.rs")), None);
+        assert_eq!(
+            denylist.matching_pattern(Path::new("environment.txt")),
+            None
+        );
+    }
+
+    #[test]
+    fn extra_patterns() {
+        let denylist = Denylist::new(&["secrets/**".into(), "*.tfstate".into()]).unwrap();
+
+        assert_eq!(
+            denylist.matching_pattern(Path::new("secrets/prod/db.txt")),
+            Some("secrets/**")
+        );
+        assert_eq!(
+            denylist.matching_pattern(Path::new("infra/terraform.tfstate")),
+            Some("*.tfstate")
+        );
+        assert_eq!(denylist.matching_pattern(Path::new("README.md")), None);
+
+        assert!(Denylist::new(&["[".into()]).is_err());
+    }
+}
@@ -0,0 +1,315 @@
+//! Guessing the language of a paste from its content, with the detect feature, for pastes that
+//! have no extension or shebang line to go by.
+//!
+//! Each language has patterns for the keywords and syntax that give it away, each with a
+//! weight. A paste is in the language whose patterns it matches the most weight of, as long as
+//! that is enough to be sure and no other language comes close. Otherwise the paste is left for
+//! Pastery to detect.
+
+use std::cmp::Reverse;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// How much of a paste is looked at, which is plenty to tell the language of anything.
+const SAMPLE_LEN: usize = 64 * 1024;
+
+/// The least weight that a paste has to match for its language to be chosen.
+const MIN_SCORE: u32 = 4;
+
+type Patterns = &'static [(&'static str, u32)];
+
+const JS: Patterns = &[
+    (r"\bconst\s+\w+\s*(:\s*[\w<>\[\]]+\s*)?=", 1),
+    (r"\bfunction\s*\w*\s*\(", 2),
+    (r"=>\s*[{(]", 1),
+    (r"\bconsole\.(log|error)\(", 3),
+    (r#"\brequire\(['"]"#, 3),
+    (r"\bdocument\.\w+", 2),
+    (r"[^=!]===?[^=]", 1),
+    (r"\bexport\s+(default|const|function)\b", 1),
+];
+
+/// The patterns of each language, in groups that a paste has to match at least one pattern of
+/// each of. TypeScript is JavaScript with types, so it has both, and JavaScript without them is
+/// not taken for TypeScript.
+const RULES: &[(&str, &[Patterns])] = &[
+    (
+        "rust",
+        &[&[
+            (r"\bfn\s+\w+\s*(<[^>]*>)?\s*\(", 2),
+            (r"\blet\s+mut\b", 3),
+            (r"\buse\s+\w+(::\w+)+", 3),
+            (r"\bpub\s+(fn|struct|enum|mod|trait)\b", 2),
+            (r"\b(println|format|vec)!", 3),
+            (r"->\s*(Result|Option|Self)\b", 2),
+            (r"#\[derive\(", 3),
+            (r"\bimpl\b[^{;]*\{", 2),
+        ]],
+    ),
+    (
+        "python",
+        &[&[
+            (r"(?m)^\s*def\s+\w+\(.*\)\s*(->.*)?:\s*$", 3),
+            (r"(?m)^\s*class\s+\w+(\(.*\))?:\s*$", 3),
+            (
+                r"(?m)^\s*(from\s+[\w.]+\s+)?import\s+[\w.]+(\s+as\s+\w+)?\s*$",
+                1,
+            ),
+            (r"(?m)^\s*elif\b", 3),
+            (r"\bif\s+__name__\s*==", 3),
+            (r"\bself\.\w+", 1),
+            (r"\b(None|True|False)\b", 1),
+        ]],
+    ),
+    ("js", &[JS]),
+    (
+        "ts",
+        &[
+            JS,
+            &[
+                (r":\s*(string|number|boolean|any|void)\b", 3),
+                (r"\binterface\s+\w+\s*\{", 2),
+                (r"\b(export\s+)?type\s+\w+\s*=", 2),
+            ],
+        ],
+    ),
+    (
+        "go",
+        &[&[
+            (r"(?m)^package\s+\w+\s*$", 3),
+            (r"\bfunc\s+(\(\w+\s+\*?\w+\)\s*)?\w+\(", 3),
+            (r":=", 1),
+            (r"\bfmt\.\w+\(", 3),
+            (r"\bif\s+err\s*!=\s*nil\b", 3),
+        ]],
+    ),
+    (
+        "c",
+        &[&[
+            (r"(?m)^#include\s*<\w+\.h>", 3),
+            (r"\bint\s+main\s*\(", 2),
+            (r"\b(printf|malloc|free)\(", 2),
+        ]],
+    ),
+    (
+        "cpp",
+        &[&[
+            (r"(?m)^#include\s*<\w+>", 3),
+            (r"\bstd::", 3),
+            (r"\btemplate\s*<", 3),
+            (r"\b(cout|cerr)\s*<<", 3),
+        ]],
+    ),
+    (
+        "java",
+        &[&[
+            (r"\bpublic\s+(static\s+)?(class|void|final)\b", 2),
+            (r"\bSystem\.out\.print", 3),
+            (r"(?m)^import\s+java\.", 3),
+            (r"@Override\b", 3),
+            (r"(?m)^package\s+[\w.]+;", 3),
+        ]],
+    ),
+    (
+        "csharp",
+        &[&[
+            (r"(?m)^using\s+System", 3),
+            (r"\bConsole\.Write", 3),
+            (r"\bpublic\s+async\s+Task\b", 3),
+            (r"\bnamespace\s+[\w.]+", 1),
+        ]],
+    ),
+    ("php", &[&[(r"<\?php", 5), (r"\$\w+\s*=", 1)]]),
+    (
+        "rb",
+        &[&[
+            (r"(?m)^\s*def\s+\w+[?!]?(\(.*\))?\s*$", 2),
+            (r"(?m)^\s*end\s*$", 2),
+            (r"\bputs\b", 2),
+            (r"\.each\s+do\b", 3),
+            (r"\battr_(accessor|reader|writer)\b", 3),
+        ]],
+    ),
+    (
+        "bash",
+        &[&[
+            (r"(?m)^\s*(if|while)\s+\[\[?\s", 3),
+            (r"(?m)^\s*fi\s*$", 3),
+            (r"(?m)^\s*done\s*$", 2),
+            (r"\besac\b", 3),
+            (r"(?m)^\s*export\s+\w+=", 2),
+            (r"\$\{\w+", 1),
+            (r"(?m)^\s*echo\s", 1),
+        ]],
+    ),
+    (
+        "sql",
+        &[&[
+            (r"(?im)^\s*select\s.+\sfrom\s", 3),
+            (
+                r"(?im)^\s*(insert\s+into|create\s+table|update\s+\w+\s+set|delete\s+from)\b",
+                3,
+            ),
+            (r"(?i)\bwhere\s+\w+\s*=", 1),
+        ]],
+    ),
+    (
+        "html",
+        &[&[
+            (r"(?i)<!doctype\s+html", 5),
+            (r"(?i)<(html|head|body|div|span|p|a)\b[^>]*>", 2),
+            (r"</\w+>", 1),
+        ]],
+    ),
+    (
+        "css",
+        &[&[
+            (r"(?m)^\s*[.#]?[\w-]+(\s*[,>]?\s*[.#:]?[\w-]+)*\s*\{\s*$", 1),
+            (r"(?m)^\s*[\w-]+\s*:\s*[^;{]+;\s*$", 2),
+            (r"@media\b", 3),
+            (r"\b\d+(px|em|rem)\b", 1),
+        ]],
+    ),
+    (
+        "yaml",
+        &[&[
+            (r"(?m)^---\s*$", 2),
+            (r"(?m)^[\w-]+:\s*$", 1),
+            (r"(?m)^\s*-\s+[\w-]+:\s", 2),
+        ]],
+    ),
+    (
+        "markdown",
+        &[&[
+            (r"(?m)^#{1,6}\s+\S", 2),
+            (r"(?m)^```", 2),
+            (r"\[[^\]]+\]\([^)]+\)", 2),
+        ]],
+    ),
+    (
+        "diff",
+        &[&[
+            (r"(?m)^@@ -\d+(,\d+)? \+\d+(,\d+)? @@", 5),
+            (r"(?m)^diff --git ", 5),
+            (r"(?m)^(---|\+\+\+) \S", 2),
+        ]],
+    ),
+    ("xml", &[&[(r"^<\?xml\s", 5)]]),
+];
+
+/// The groups of a language's patterns, compiled.
+type Compiled = Vec<Vec<(Regex, u32)>>;
+
+lazy_static! {
+    static ref COMPILED: Vec<(&'static str, Compiled)> = RULES
+        .iter()
+        .map(|&(lang, groups)| {
+            let groups = groups
+                .iter()
+                .map(|group| {
+                    group
+                        .iter()
+                        .map(|&(pattern, weight)| (Regex::new(pattern).unwrap(), weight))
+                        .collect()
+                })
+                .collect();
+            (lang, groups)
+        })
+        .collect();
+}
+
+/// The language that `text` looks like it is written in, if it plainly looks like one.
+///
+/// Each pattern counts once however often it matches, so that a long paste does not pile up
+/// weight for a language from one common construct.
+pub fn guess(text: &str) -> Option<&'static str> {
+    let text = match text.char_indices().nth(SAMPLE_LEN) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    };
+
+    if text.trim_start().starts_with(['{', '['])
+        && serde_json::from_str::<serde_json::Value>(text).is_ok()
+    {
+        return Some("json");
+    }
+
+    let mut scores = COMPILED
+        .iter()
+        .map(|(lang, groups)| {
+            let scores = groups.iter().map(|patterns| {
+                patterns
+                    .iter()
+                    .filter(|(regex, _)| regex.is_match(text))
+                    .map(|&(_, weight)| weight)
+                    .sum::<u32>()
+            });
+            let score = scores
+                .map(|score| Some(score).filter(|&score| score > 0))
+                .sum::<Option<u32>>()
+                .unwrap_or(0);
+            (score, *lang)
+        })
+        .collect::<Vec<_>>();
+    scores.sort_by_key(|&(score, _)| Reverse(score));
+
+    match scores.as_slice() {
+        [(best, lang), (second, _), ..] if *best >= MIN_SCORE && best > second => Some(*lang),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use patisserie::languages::LANGUAGES;
+
+    #[test]
+    fn known_langs() {
+        for (lang, _) in RULES {
+            assert!(LANGUAGES.contains(lang), "{}", lang);
+        }
+        assert_eq!(COMPILED.len(), RULES.len());
+    }
+
+    #[test]
+    fn guess_langs() {
+        assert_eq!(
+            guess("use std::io;\n\nfn main() {\n    let mut line = String::new();\n}\n"),
+            Some("rust")
+        );
+        assert_eq!(
+            guess("import os\n\ndef main():\n    print(os.getcwd())\n\nif __name__ == '__main__':\n    main()\n"),
+            Some("python")
+        );
+        assert_eq!(
+            guess("package main\n\nimport \"fmt\"\n\nfunc main() {\n\tfmt.Println(\"hi\")\n}\n"),
+            Some("go")
+        );
+        assert_eq!(
+            guess("const add = (a: number, b: number): number => {\n  return a + b;\n};\n"),
+            Some("ts")
+        );
+        assert_eq!(
+            guess("const fs = require('fs');\nconsole.log(fs.readdirSync('.'));\n"),
+            Some("js")
+        );
+        assert_eq!(
+            guess("#include <stdio.h>\n\nint main(void) {\n    printf(\"hi\\n\");\n}\n"),
+            Some("c")
+        );
+        assert_eq!(
+            guess("diff --git a/x b/x\n--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b\n"),
+            Some("diff")
+        );
+        assert_eq!(guess("{\"a\": [1, 2, 3]}\n"), Some("json"));
+    }
+
+    #[test]
+    fn leave_unclear_pastes() {
+        assert_eq!(guess(""), None);
+        assert_eq!(guess("hello, world\n"), None);
+        assert_eq!(guess("x = 1\n"), None);
+    }
+}
@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use failure::{err_msg, format_err, Error};
+
+pub const ONE_MINUTE: Duration = Duration::from_secs(60);
+pub const ONE_HOUR: Duration = ONE_MINUTE.checked_mul(60).unwrap();
+pub const ONE_DAY: Duration = ONE_HOUR.checked_mul(24).unwrap();
+pub const ONE_WEEK: Duration = ONE_DAY.checked_mul(7).unwrap();
+pub const ONE_MONTH: Duration = ONE_WEEK.checked_mul(4).unwrap();
+pub const ONE_YEAR: Duration = ONE_DAY.checked_mul(365).unwrap();
+pub const ONE_HUNDRED_YEARS: Duration = ONE_YEAR.checked_mul(100).unwrap();
+
+/// Parse a duration such as `30m` or `2w`.
+pub fn parse(s: &str) -> Result<Duration, Error> {
+    if let Some(split_at) = s.find(|c: char| !c.is_ascii_digit()) {
+        let (amount_s, unit) = s.split_at(split_at);
+        let amount = amount_s.parse::<u32>()?;
+
+        let unit = match unit {
+            "m" => ONE_MINUTE,
+            "h" => ONE_HOUR,
+            "d" => ONE_DAY,
+            "w" => ONE_WEEK,
+            "mo" => ONE_MONTH,
+            "y" => ONE_YEAR,
+            _ => {
+                return Err(format_err!(
+                    "Unknown unit {}, expected one of m, h, d, w, mo, y",
+                    unit
+                ));
+            }
+        };
+
+        match unit.checked_mul(amount) {
+            Some(duration) => {
+                if duration > ONE_HUNDRED_YEARS {
+                    Err(format_err!(
+                        "Duration {} is too long; maximum duration is 100y",
+                        s
+                    ))
+                } else {
+                    Ok(duration)
+                }
+            }
+
+            None => Err(format_err!(
+                "Duration {} is too long; maximum duration is 100y",
+                s
+            )),
+        }
+    } else {
+        Err(err_msg(
+            "Did not find a unit, expected one of m, h, d, w, mo, y",
+        ))
+    }
+}
+
+/// Format a duration in the largest unit that represents it exactly, e.g. `2w` or `90m`.
+pub fn format(duration: Duration) -> String {
+    let units = [
+        (ONE_YEAR, "y"),
+        (ONE_MONTH, "mo"),
+        (ONE_WEEK, "w"),
+        (ONE_DAY, "d"),
+        (ONE_HOUR, "h"),
+    ];
+
+    let secs = duration.as_secs();
+    for (unit, suffix) in units.iter() {
+        if secs > 0 && secs.is_multiple_of(unit.as_secs()) {
+            return format!("{}{}", secs / unit.as_secs(), suffix);
+        }
+    }
+
+    format!("{}m", secs / 60)
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(parse("1m").unwrap(), ONE_MINUTE);
+        assert_eq!(parse("5m").unwrap(), ONE_MINUTE.checked_mul(5).unwrap());
+        assert_eq!(parse("1d").unwrap(), ONE_DAY);
+        assert_eq!(parse("1w").unwrap(), ONE_WEEK);
+        assert_eq!(parse("1mo").unwrap(), ONE_MONTH);
+        assert_eq!(parse("1y").unwrap(), ONE_YEAR);
+        assert_eq!(parse("100y").unwrap(), ONE_HUNDRED_YEARS);
+
+        assert!(parse("101y").is_err());
+        assert!(parse("m").is_err());
+        assert!(parse("100").is_err());
+        assert!(parse("100j").is_err());
+    }
+
+    #[test]
+    fn format_durations() {
+        assert_eq!(format(ONE_MINUTE), "1m");
+        assert_eq!(format(ONE_MINUTE.checked_mul(90).unwrap()), "90m");
+        assert_eq!(format(ONE_HOUR), "1h");
+        assert_eq!(format(ONE_DAY), "1d");
+        assert_eq!(format(ONE_WEEK.checked_mul(2).unwrap()), "2w");
+        assert_eq!(format(ONE_MONTH), "1mo");
+        assert_eq!(format(ONE_HUNDRED_YEARS), "100y");
+
+        for s in &["5m", "3h", "2d", "1w", "6mo", "10y"] {
+            assert_eq!(&format(parse(s).unwrap()), s);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn parse_anything(s in "\\PC*") {
+            let _ = parse(&s);
+        }
+
+        #[test]
+        fn parse_what_is_formatted(minutes in 0..=ONE_HUNDRED_YEARS.as_secs() / 60) {
+            let duration = Duration::from_secs(minutes * 60);
+            prop_assert_eq!(parse(&format(duration)).unwrap(), duration);
+        }
+
+        #[test]
+        fn reject_durations_over_a_hundred_years(
+            amount in any::<u32>(),
+            unit in prop::sample::select(&["m", "h", "d", "w", "mo", "y"][..]),
+        ) {
+            let s = format!("{}{}", amount, unit);
+            if let Ok(duration) = parse(&s) {
+                prop_assert!(duration <= ONE_HUNDRED_YEARS);
+            }
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! A JSON-RPC 2.0 server for editor plugins.
+//!
+//! Messages are exchanged as single-line JSON objects on stdin and stdout, so that a plugin can
+//! keep one process around instead of spawning one per paste. The supported methods are:
+//!
+//! - `createPaste` with `{"content", "title"?, "language"?}`, returning `{"url"}`. While the
+//!   upload is in flight, `$/progress` notifications with `{"id", "message"}` are sent.
+//! - `listLanguages`, returning the list of language aliases.
+//! - `getHistory`, returning the pastes created by this process as `[{"url", "title",
+//!   "language"}]`.
+//! - `shutdown`, after which the server exits once all pending requests have finished.
+//!
+//! A pending `createPaste` can be cancelled with a `$/cancelRequest` notification containing
+//! `{"id"}`, in which case the request fails with the `RequestCancelled` error code. A paste
+//! whose upload had already started when it was cancelled may still have been created; its URL
+//! is then given as `{"url"}` in the error's data, and it is kept in the history.
+
+use std::collections::HashMap;
+use std::io::{stdin, stdout, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use failure::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{Input, Uploader, LANGUAGES};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const UPLOAD_FAILED: i64 = -32000;
+const REQUEST_CANCELLED: i64 = -32800;
+
+#[derive(Deserialize)]
+struct Message {
+    id: Option<Value>,
+    method: Option<String>,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct CreatePaste {
+    content: String,
+    title: Option<String>,
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CancelRequest {
+    id: Value,
+}
+
+#[derive(Clone, Serialize)]
+struct HistoryEntry {
+    url: String,
+    title: Option<String>,
+    language: &'static str,
+}
+
+/// State shared between the reader loop and the threads handling uploads.
+#[derive(Default)]
+struct Server {
+    output: Mutex<()>,
+    history: Mutex<Vec<HistoryEntry>>,
+    pending: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl Server {
+    fn send(&self, message: &Value) {
+        let _guard = self.output.lock().unwrap();
+        let stdout = stdout();
+        let mut stdout = stdout.lock();
+
+        // If the editor has gone away there is nobody left to report the error to.
+        let _ = writeln!(stdout, "{}", message).and_then(|_| stdout.flush());
+    }
+
+    fn respond(&self, id: &Value, result: Value) {
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "result": result }));
+    }
+
+    fn respond_error(&self, id: &Value, code: i64, message: &str) {
+        self.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }));
+    }
+
+    fn respond_error_with(&self, id: &Value, code: i64, message: &str, data: Value) {
+        self.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message, "data": data },
+        }));
+    }
+
+    /// Start tracking a request, so that it can be cancelled from then on.
+    fn register(&self, id: &Value) -> Arc<AtomicBool> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), cancelled.clone());
+        cancelled
+    }
+
+    fn progress(&self, id: &Value, message: &str) {
+        self.send(&json!({
+            "jsonrpc": "2.0",
+            "method": "$/progress",
+            "params": { "id": id, "message": message },
+        }));
+    }
+
+    fn create_paste(
+        &self,
+        uploader: &Uploader,
+        id: &Value,
+        params: CreatePaste,
+        cancelled: &AtomicBool,
+    ) {
+        let key = id.to_string();
+
+        // Nothing has been sent yet, so nothing is left behind.
+        if cancelled.load(Ordering::SeqCst) {
+            self.pending.lock().unwrap().remove(&key);
+            self.respond_error(id, REQUEST_CANCELLED, "Request cancelled");
+            return;
+        }
+
+        self.progress(id, "Uploading");
+        let result = uploader.upload(Input {
+            body: params.content,
+            title: params.title,
+            lang: Some(crate::parse_lang(params.language.as_deref().unwrap_or(""))),
+        });
+
+        self.pending.lock().unwrap().remove(&key);
+
+        let paste = match result {
+            Ok(paste) => paste,
+            Err(_) if cancelled.load(Ordering::SeqCst) => {
+                self.respond_error(id, REQUEST_CANCELLED, "Request cancelled");
+                return;
+            }
+            Err(e) => {
+                self.respond_error(id, UPLOAD_FAILED, &e.to_string());
+                return;
+            }
+        };
+
+        // A paste that was created is kept in the history even if the editor stopped waiting
+        // for it, so that it can still be found.
+        self.history.lock().unwrap().push(HistoryEntry {
+            url: paste.url.clone(),
+            title: paste.title,
+            language: paste.lang,
+        });
+
+        // An upload that is already in flight cannot be aborted, so the editor is told where
+        // the paste it cancelled ended up.
+        if cancelled.load(Ordering::SeqCst) {
+            self.respond_error_with(
+                id,
+                REQUEST_CANCELLED,
+                "Request cancelled after the paste was created",
+                json!({ "url": paste.url }),
+            );
+            return;
+        }
+
+        self.progress(id, "Done");
+        self.respond(id, json!({ "url": paste.url }));
+    }
+
+    fn cancel(&self, params: CancelRequest) {
+        if let Some(cancelled) = self.pending.lock().unwrap().get(&params.id.to_string()) {
+            cancelled.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+fn sorted_languages() -> Vec<&'static str> {
+    let mut languages = LANGUAGES.iter().cloned().collect::<Vec<_>>();
+    languages.sort_unstable();
+    languages
+}
+
+/// Serve editor requests until stdin is closed or a shutdown is requested.
+pub fn serve(uploader: &Uploader) -> Result<(), Error> {
+    let server = Server::default();
+    let stdin = stdin();
+
+    thread::scope(|scope| -> Result<(), Error> {
+        for line in stdin.lock().lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let message = match serde_json::from_str::<Message>(&line) {
+                Ok(message) => message,
+                Err(e) => {
+                    server.respond_error(&Value::Null, PARSE_ERROR, &e.to_string());
+                    continue;
+                }
+            };
+
+            let (id, method) = match (message.id, message.method) {
+                (id, Some(method)) => (id, method),
+                (id, None) => {
+                    server.respond_error(
+                        &id.unwrap_or(Value::Null),
+                        INVALID_REQUEST,
+                        "Missing method",
+                    );
+                    continue;
+                }
+            };
+
+            match (id, method.as_str()) {
+                (None, "$/cancelRequest") => {
+                    if let Ok(params) = serde_json::from_value(message.params) {
+                        server.cancel(params);
+                    }
+                }
+
+                (None, "exit") => break,
+
+                // Other notifications are ignored, as the specification requires.
+                (None, _) => {}
+
+                (Some(id), "createPaste") => match serde_json::from_value(message.params) {
+                    Ok(params) => {
+                        // The request is tracked before its thread starts, so that a
+                        // cancellation that follows straight after it is not missed.
+                        let cancelled = server.register(&id);
+                        let server = &server;
+                        scope.spawn(move || server.create_paste(uploader, &id, params, &cancelled));
+                    }
+                    Err(e) => server.respond_error(&id, INVALID_PARAMS, &e.to_string()),
+                },
+
+                (Some(id), "listLanguages") => server.respond(&id, json!(sorted_languages())),
+
+                (Some(id), "getHistory") => {
+                    let history = server.history.lock().unwrap().clone();
+                    server.respond(&id, json!(history));
+                }
+
+                (Some(id), "shutdown") => {
+                    server.respond(&id, Value::Null);
+                    break;
+                }
+
+                (Some(id), method) => server.respond_error(
+                    &id,
+                    METHOD_NOT_FOUND,
+                    &format!("Unknown method {}", method),
+                ),
+            }
+        }
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_messages() {
+        let message: Message = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","id":1,"method":"createPaste","params":{"content":"fn main() {}","language":"rust"}}"#,
+        )
+        .unwrap();
+        assert_eq!(message.id, Some(json!(1)));
+        assert_eq!(message.method.as_deref(), Some("createPaste"));
+
+        let params: CreatePaste = serde_json::from_value(message.params).unwrap();
+        assert_eq!(params.content, "fn main() {}");
+        assert_eq!(params.language.as_deref(), Some("rust"));
+        assert!(params.title.is_none());
+
+        let message: Message =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","method":"listLanguages"}"#).unwrap();
+        assert!(message.id.is_none());
+        assert_eq!(message.params, Value::Null);
+
+        assert!(serde_json::from_value::<CreatePaste>(json!({ "title": "x" })).is_err());
+    }
+
+    #[test]
+    fn languages_are_sorted() {
+        let languages = sorted_languages();
+
+        assert_eq!(languages.len(), LANGUAGES.len());
+        assert!(languages.windows(2).all(|w| w[0] < w[1]));
+        assert!(languages.contains(&"rust"));
+    }
+}
@@ -0,0 +1,128 @@
+use std::str::FromStr;
+
+use failure::{format_err, Error};
+
+use crate::Paste;
+
+/// The markup that --embed links to a paste with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Markdown,
+    Html,
+    Bbcode,
+    Rst,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "markdown" | "md" => Ok(Format::Markdown),
+            "html" => Ok(Format::Html),
+            "bbcode" => Ok(Format::Bbcode),
+            "rst" => Ok(Format::Rst),
+            _ => Err(format_err!(
+                "Unknown embed format {}, expected one of markdown, html, bbcode, rst",
+                s
+            )),
+        }
+    }
+}
+
+/// A snippet of markup that links to a paste, titled with the paste's title.
+///
+/// Images from --as-image and screenshots are embedded as images rather than linked, and HTML
+/// pastes are embedded in an iframe when the markup is HTML.
+pub fn snippet(format: Format, paste: &Paste) -> String {
+    let title = paste.title.as_deref().unwrap_or("Paste");
+    let url = &paste.url;
+    let image = paste.lang == "png";
+
+    match format {
+        Format::Markdown if image => format!("![{}]({})", markdown(title), url),
+        Format::Markdown => format!("[{}]({})", markdown(title), url),
+        Format::Html if image => format!("<img src=\"{}\" alt=\"{}\">", html(url), html(title)),
+        Format::Html if paste.lang == "html" => format!(
+            "<iframe src=\"{}\" title=\"{}\" width=\"100%\" height=\"400\"></iframe>",
+            html(url),
+            html(title)
+        ),
+        Format::Html => format!("<a href=\"{}\">{}</a>", html(url), html(title)),
+        Format::Bbcode if image => format!("[img]{}[/img]", url),
+        Format::Bbcode => format!(
+            "[url={}]{}[/url]",
+            url,
+            title.replace('[', "(").replace(']', ")")
+        ),
+        Format::Rst if image => format!(".. image:: {}\n   :alt: {}", url, title),
+        Format::Rst => format!("`{} <{}>`__", rst(title), url),
+    }
+}
+
+pub fn markdown(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+fn html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn rst(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('`', "\\`")
+        .replace('<', "\\<")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn embed_pastes() {
+        let paste = Paste {
+            url: "https://www.pastery.net/abcdef/".into(),
+            id: Some("abcdef".into()),
+            title: Some("build [x86] <log>".into()),
+            lang: "text",
+            duration: crate::duration::ONE_DAY,
+            max_views: None,
+        };
+        let snippet = |format, lang| {
+            snippet(
+                format,
+                &Paste {
+                    lang,
+                    ..paste.clone()
+                },
+            )
+        };
+
+        assert_eq!(
+            snippet(Format::Markdown, "text"),
+            "[build \\[x86\\] <log>](https://www.pastery.net/abcdef/)"
+        );
+        assert_eq!(
+            snippet(Format::Html, "text"),
+            "<a href=\"https://www.pastery.net/abcdef/\">build [x86] &lt;log&gt;</a>"
+        );
+        assert_eq!(
+            snippet(Format::Bbcode, "text"),
+            "[url=https://www.pastery.net/abcdef/]build (x86) <log>[/url]"
+        );
+        assert_eq!(
+            snippet(Format::Rst, "text"),
+            "`build [x86] \\<log> <https://www.pastery.net/abcdef/>`__"
+        );
+        assert!(snippet(Format::Html, "html").starts_with("<iframe "));
+        assert_eq!(
+            snippet(Format::Markdown, "png"),
+            "![build \\[x86\\] <log>](https://www.pastery.net/abcdef/)"
+        );
+    }
+}
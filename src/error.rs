@@ -0,0 +1,391 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use failure::Error;
+use patisserie::client::ApiError;
+use reqwest::{StatusCode, Url};
+
+use crate::backend::Kind as BackendKind;
+use crate::i18n::tr;
+
+/// The exit status for failures not covered below.
+pub const EXIT_FAILURE: i32 = 1;
+
+/// The exit status when Pastery could not be reached.
+pub const EXIT_NETWORK: i32 = 2;
+
+/// The exit status when Pastery did not accept the API key.
+pub const EXIT_UNAUTHORIZED: i32 = 3;
+
+/// The exit status when Pastery rejected the paste itself.
+pub const EXIT_REJECTED: i32 = 4;
+
+/// A description of the exit statuses for the end of `--help`.
+pub const EXIT_STATUS_HELP: &str = "EXIT STATUS:
+    0    The paste was uploaded.
+    1    Any other failure.
+    2    Pastery could not be reached or sent an unexpected response.
+    3    Pastery did not accept the API key.
+    4    Pastery rejected the paste, e.g. because it is too large.";
+
+/// The service that pastes are uploaded to, which is named in the messages about them.
+static SERVICE: OnceLock<BackendKind> = OnceLock::new();
+
+/// Name the --backend in the messages about failed uploads, which otherwise name Pastery.
+pub fn set_service(kind: BackendKind) {
+    let _ = SERVICE.set(kind);
+}
+
+fn service() -> BackendKind {
+    SERVICE.get().copied().unwrap_or(BackendKind::Pastery)
+}
+
+/// Why an upload to Pastery failed.
+#[derive(Debug, PartialEq)]
+pub enum UploadError {
+    /// The request could not be sent or the response could not be read.
+    Network(String),
+
+    /// The API key is missing or invalid.
+    Unauthorized(String),
+
+    /// The paste is larger than Pastery allows.
+    TooLarge(String),
+
+    /// The language is not one Pastery knows.
+    InvalidLanguage(String),
+
+    /// Any other error reported by Pastery.
+    Rejected(String),
+
+    /// A response that was neither a paste nor an error.
+    Unexpected(StatusCode),
+
+    /// A redirect that was not followed, either because following it would drop the paste or
+    /// because of --max-redirects.
+    ///
+    /// The queries are removed from both URLs, since they may contain the API key.
+    Redirected {
+        status: StatusCode,
+        from: Url,
+        to: Option<Url>,
+    },
+}
+
+impl UploadError {
+    /// Classify an error message returned by the API.
+    pub fn from_api(status: StatusCode, message: String) -> Self {
+        let lower = message.to_lowercase();
+
+        if status == StatusCode::UNAUTHORIZED
+            || status == StatusCode::FORBIDDEN
+            || lower.contains("api key")
+            || lower.contains("api_key")
+        {
+            UploadError::Unauthorized(message)
+        } else if status == StatusCode::PAYLOAD_TOO_LARGE
+            || lower.contains("too large")
+            || lower.contains("too long")
+        {
+            UploadError::TooLarge(message)
+        } else if lower.contains("language") {
+            UploadError::InvalidLanguage(message)
+        } else {
+            UploadError::Rejected(message)
+        }
+    }
+
+    /// A redirect from `from` to the `Location`, if it had a valid one.
+    pub fn redirected(status: StatusCode, from: &Url, to: Option<Url>) -> Self {
+        let without_query = |mut url: Url| {
+            url.set_query(None);
+            url
+        };
+
+        UploadError::Redirected {
+            status,
+            from: without_query(from.clone()),
+            to: to.map(without_query),
+        }
+    }
+
+    fn hint(&self) -> Option<String> {
+        match self {
+            UploadError::Network(_) => Some(tr!("hint-network")),
+            UploadError::Unauthorized(_) => Some(service().unauthorized_hint()),
+            UploadError::TooLarge(_) => Some(tr!("hint-too-large")),
+            UploadError::InvalidLanguage(_) => Some(tr!("hint-invalid-language")),
+            UploadError::Rejected(_) | UploadError::Unexpected(_) => None,
+            UploadError::Redirected { status, from, to } => Some(redirect_hint(*status, from, to)),
+        }
+    }
+
+    fn exit_code(&self) -> i32 {
+        match self {
+            UploadError::Network(_)
+            | UploadError::Unexpected(_)
+            | UploadError::Redirected { .. } => EXIT_NETWORK,
+            UploadError::Unauthorized(_) => EXIT_UNAUTHORIZED,
+            UploadError::TooLarge(_)
+            | UploadError::InvalidLanguage(_)
+            | UploadError::Rejected(_) => EXIT_REJECTED,
+        }
+    }
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            UploadError::Network(message) => tr!(
+                "upload-network",
+                service = service().to_string(),
+                message = message.as_str()
+            ),
+            UploadError::Unauthorized(message)
+            | UploadError::TooLarge(message)
+            | UploadError::InvalidLanguage(message)
+            | UploadError::Rejected(message) => {
+                tr!(
+                    "upload-rejected",
+                    service = service().to_string(),
+                    message = message.as_str()
+                )
+            }
+            UploadError::Unexpected(status) => {
+                tr!(
+                    "upload-unexpected",
+                    service = service().to_string(),
+                    status = status.to_string()
+                )
+            }
+            UploadError::Redirected {
+                status,
+                to: Some(to),
+                ..
+            } => tr!(
+                "upload-redirected",
+                service = service().to_string(),
+                status = status.to_string(),
+                to = to.as_str()
+            ),
+            UploadError::Redirected {
+                status, to: None, ..
+            } => tr!(
+                "upload-redirected-nowhere",
+                service = service().to_string(),
+                status = status.to_string()
+            ),
+        };
+        f.write_str(&message)?;
+
+        match self.hint() {
+            Some(hint) => write!(f, "\n{}", tr!("hint", hint = hint)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for UploadError {}
+
+impl From<ApiError> for UploadError {
+    fn from(e: ApiError) -> Self {
+        match e {
+            ApiError::Network(message) => UploadError::Network(message),
+            ApiError::Rejected { status, message } => UploadError::from_api(status, message),
+            ApiError::Unexpected(status) => UploadError::Unexpected(status),
+        }
+    }
+}
+
+/// Explain the likely cause of a redirect.
+///
+/// The usual culprits are a self-hosted instance that only serves HTTPS, or one behind a single
+/// sign-on proxy that sends every unauthenticated request to its login page.
+fn redirect_hint(status: StatusCode, from: &Url, to: &Option<Url>) -> String {
+    const LOGIN_WORDS: &[&str] = &[
+        "login", "signin", "sign_in", "sign-in", "sso", "auth", "saml",
+    ];
+
+    let to = match to {
+        Some(to) => to,
+        None => return tr!("hint-redirect-nowhere"),
+    };
+
+    let target = format!("{}{}", to.host_str().unwrap_or(""), to.path()).to_lowercase();
+
+    if from.scheme() == "http" && to.scheme() == "https" && from.host() == to.host() {
+        tr!("hint-redirect-https")
+    } else if LOGIN_WORDS.iter().any(|word| target.contains(word)) {
+        tr!("hint-redirect-login")
+    } else if status == StatusCode::TEMPORARY_REDIRECT || status == StatusCode::PERMANENT_REDIRECT {
+        tr!("hint-redirect-followable")
+    } else {
+        tr!("hint-redirect-drops-paste")
+    }
+}
+
+/// Some of the files in a batch could not be uploaded.
+#[derive(Debug)]
+pub struct BatchError {
+    pub failed: usize,
+    pub skipped: usize,
+    pub total: usize,
+
+    /// The exit status shared by every failure, or `EXIT_FAILURE` if they differ.
+    pub exit_code: i32,
+}
+
+impl BatchError {
+    /// Summarize a batch from the errors of the uploads that failed.
+    pub fn new<'a, I>(failures: I, skipped: usize, total: usize) -> Self
+    where
+        I: IntoIterator<Item = &'a Error>,
+    {
+        let codes = failures.into_iter().map(exit_code).collect::<Vec<_>>();
+
+        BatchError {
+            failed: codes.len(),
+            skipped,
+            total,
+            exit_code: if codes.windows(2).all(|w| w[0] == w[1]) {
+                codes.first().cloned().unwrap_or(EXIT_FAILURE)
+            } else {
+                EXIT_FAILURE
+            },
+        }
+    }
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self.skipped {
+            0 => tr!("batch-failed", failed = self.failed, total = self.total),
+            skipped => tr!(
+                "batch-failed-skipped",
+                failed = self.failed,
+                total = self.total,
+                skipped = skipped
+            ),
+        };
+
+        f.write_str(&message)
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+/// The exit status for an error.
+pub fn exit_code(e: &Error) -> i32 {
+    if let Some(e) = e.downcast_ref::<UploadError>() {
+        e.exit_code()
+    } else if let Some(e) = e.downcast_ref::<BatchError>() {
+        e.exit_code
+    } else {
+        EXIT_FAILURE
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use failure::err_msg;
+
+    use super::*;
+
+    #[test]
+    fn classify_api_errors() {
+        assert_eq!(
+            UploadError::from_api(StatusCode::OK, "Invalid API key.".into()),
+            UploadError::Unauthorized("Invalid API key.".into())
+        );
+        assert_eq!(
+            UploadError::from_api(StatusCode::UNAUTHORIZED, "Nope".into()),
+            UploadError::Unauthorized("Nope".into())
+        );
+        assert_eq!(
+            UploadError::from_api(StatusCode::BAD_REQUEST, "Paste is too large.".into()),
+            UploadError::TooLarge("Paste is too large.".into())
+        );
+        assert_eq!(
+            UploadError::from_api(StatusCode::BAD_REQUEST, "Unknown language.".into()),
+            UploadError::InvalidLanguage("Unknown language.".into())
+        );
+        assert_eq!(
+            UploadError::from_api(StatusCode::BAD_REQUEST, "Nope".into()),
+            UploadError::Rejected("Nope".into())
+        );
+    }
+
+    #[test]
+    fn explain_redirects() {
+        let from = Url::parse("https://paste.example.com/api/paste/").unwrap();
+        let hint = |status, to: &str| redirect_hint(status, &from, &Url::parse(to).ok());
+
+        assert!(hint(
+            StatusCode::FOUND,
+            "https://sso.example.com/?next=/api/paste/"
+        )
+        .contains("login page"));
+        assert!(hint(
+            StatusCode::FOUND,
+            "https://paste.example.com/accounts/login/"
+        )
+        .contains("login page"));
+        assert!(hint(
+            StatusCode::PERMANENT_REDIRECT,
+            "https://paste.example.com/api/v2/"
+        )
+        .contains("--max-redirects"));
+        assert!(hint(
+            StatusCode::MOVED_PERMANENTLY,
+            "https://paste.example.com/api/v2/"
+        )
+        .contains("without the paste"));
+        assert_eq!(
+            redirect_hint(StatusCode::FOUND, &from, &None),
+            "check the URL of the endpoint"
+        );
+    }
+
+    #[test]
+    fn messages_and_exit_codes() {
+        let e = UploadError::Unauthorized("Invalid API key.".into());
+        assert_eq!(
+            e.to_string(),
+            "Pastery rejected the paste: Invalid API key.\nhint: check the key given with \
+             --api-key or PASTERY_API_KEY; you can find yours at https://www.pastery.net/account/"
+        );
+        assert_eq!(exit_code(&e.into()), EXIT_UNAUTHORIZED);
+
+        let e = UploadError::Rejected("Nope".into());
+        assert_eq!(e.to_string(), "Pastery rejected the paste: Nope");
+        assert_eq!(exit_code(&e.into()), EXIT_REJECTED);
+
+        assert_eq!(
+            exit_code(&UploadError::Network("timed out".into()).into()),
+            EXIT_NETWORK
+        );
+        assert_eq!(exit_code(&err_msg("git failed")), EXIT_FAILURE);
+
+        let e = UploadError::redirected(
+            StatusCode::MOVED_PERMANENTLY,
+            &Url::parse("http://paste.example.com/api/paste/?api_key=foo").unwrap(),
+            Url::parse("https://paste.example.com/api/paste/?api_key=foo").ok(),
+        );
+        assert_eq!(
+            e.to_string(),
+            "Pastery redirected the upload (301 Moved Permanently) to \
+             https://paste.example.com/api/paste/\nhint: the endpoint only accepts HTTPS; use an \
+             https:// URL for it"
+        );
+        assert_eq!(exit_code(&e.into()), EXIT_NETWORK);
+
+        let e = BatchError {
+            failed: 1,
+            skipped: 3,
+            total: 5,
+            exit_code: EXIT_UNAUTHORIZED,
+        };
+        assert_eq!(e.to_string(), "1 of 5 uploads failed and 3 were skipped");
+        assert_eq!(exit_code(&e.into()), EXIT_UNAUTHORIZED);
+    }
+}
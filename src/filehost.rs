@@ -0,0 +1,74 @@
+use std::io::Cursor;
+
+use failure::Error;
+use reqwest::multipart::{Form, Part};
+use reqwest::Url;
+
+use crate::error::UploadError;
+use crate::{describe_request_error, Paste, Uploader};
+
+/// A file to upload to the --file-host, for content that Pastery cannot hold.
+pub struct File {
+    pub name: String,
+    pub mime: &'static str,
+    pub contents: Vec<u8>,
+    /// A short description of the kind of file, such as `png`, which stands in for the
+    /// language when the paste is announced.
+    pub kind: &'static str,
+    pub title: Option<String>,
+}
+
+/// Upload a file to a host that speaks the 0x0.st protocol.
+///
+/// The file is POSTed as the `file` field of a multipart form, and the host responds with the
+/// URL of the file as plain text. The paste's duration is passed on in hours as `expires`,
+/// which the host may shorten according to its own retention policy.
+pub fn upload(uploader: &Uploader, file: File) -> Result<Paste, Error> {
+    let options = uploader.options;
+    let len = file.contents.len() as u64;
+
+    let part = match uploader.limiter {
+        Some(ref limiter) => {
+            Part::reader_with_length(limiter.throttle(Cursor::new(file.contents)), len)
+        }
+        None => Part::bytes(file.contents),
+    };
+    let part = part.file_name(file.name).mime_str(file.mime)?;
+
+    let hours = (options.duration.as_secs() / 3600).max(1);
+    let form = Form::new()
+        .part("file", part)
+        .text("expires", hours.to_string());
+
+    let network = |e: reqwest::Error| UploadError::Network(describe_request_error(&e));
+
+    let request = uploader
+        .client
+        .post(options.file_host.clone())
+        .multipart(form);
+    let mut rsp = uploader.request(request, None).map_err(network)?;
+    let status = rsp.status();
+    let text = rsp.text().map_err(network)?;
+    let text = text.trim();
+
+    if !status.is_success() {
+        return Err(if text.is_empty() {
+            UploadError::Unexpected(status)
+        } else {
+            UploadError::from_api(status, text.into())
+        }
+        .into());
+    }
+
+    match Url::parse(text) {
+        Ok(url) => Ok(Paste {
+            url: url.into_string(),
+            id: None,
+            title: options.title.clone().or(file.title),
+            lang: file.kind,
+            duration: options.duration,
+            max_views: None,
+        }),
+        Err(_) => Err(UploadError::Unexpected(status).into()),
+    }
+}
@@ -0,0 +1,420 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, stdout, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use failure::{err_msg, format_err, Error};
+use reqwest::Url;
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use structopt::StructOpt;
+
+use crate::backend::{self, Kind as BackendKind};
+use crate::error::UploadError;
+use crate::{api_url, describe_request_error, Options, Uploader};
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct Get {
+    /// The IDs or URLs of the pastes, such as abcdef or https://www.pastery.net/abcdef/.
+    #[structopt(raw(required = "true", min_values = "1"))]
+    pastes: Vec<String>,
+
+    /// Write the paste to this file instead of standard output, or to standard output if it is
+    /// `-`.
+    #[structopt(
+        long = "output",
+        short = "o",
+        parse(from_os_str),
+        conflicts_with = "output_dir",
+        conflicts_with = "remote_name"
+    )]
+    output: Option<PathBuf>,
+
+    /// Write each paste to a file in this directory, named after its title or ID.
+    ///
+    /// Files that are already there are never replaced: a paste whose name is taken is written
+    /// with its ID after the name instead.
+    #[structopt(long = "output-dir", parse(from_os_str))]
+    output_dir: Option<PathBuf>,
+
+    /// Write each paste to a file in the current directory, named after its title or ID, as
+    /// --output-dir does.
+    #[structopt(long = "remote-name", short = "O", conflicts_with = "output_dir")]
+    remote_name: bool,
+
+    /// Write pastes to the terminal as they are, without highlighting them or paging them
+    /// through $PAGER.
+    #[structopt(long = "plain")]
+    plain: bool,
+}
+
+/// A paste as the API returns it.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Fetched {
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    #[cfg_attr(not(feature = "render"), allow(dead_code))]
+    pub language: String,
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Response {
+    Error { error_msg: String },
+    Pastes { pastes: Vec<Fetched> },
+}
+
+/// Refuse to run `command` for a --backend other than Pastery, whose pastes it would otherwise
+/// mistake for those on Pastery.
+pub fn check_backend(options: &Options, command: &str) -> Result<(), Error> {
+    match backend::kind(options) {
+        BackendKind::Pastery => Ok(()),
+        kind => Err(format_err!(
+            "{} only works with pastes on Pastery, not {}",
+            command,
+            kind
+        )),
+    }
+}
+
+/// The ID of a paste, given either as the ID itself or as the URL of the paste on `base`, which
+/// is --base-url.
+///
+/// The URL of a paste elsewhere is refused, rather than taken for the paste on Pastery with the
+/// same ID.
+pub fn paste_id(s: &str, base: &Url) -> Result<String, Error> {
+    let id = match Url::parse(s) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {
+            let base = backend::directory(base);
+            let path = url
+                .path()
+                .strip_prefix(base.path())
+                .filter(|_| url.origin() == base.origin())
+                .ok_or_else(|| {
+                    format_err!(
+                        "{} is not the URL of a paste on {}",
+                        s,
+                        base.origin().ascii_serialization()
+                    )
+                })?;
+            path.split('/').next().unwrap_or_default().to_owned()
+        }
+        _ => s.to_owned(),
+    };
+
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format_err!(
+            "{} is not the ID or URL of a paste, such as abcdef or \
+             https://www.pastery.net/abcdef/",
+            s
+        ));
+    }
+
+    Ok(id)
+}
+
+/// Fetch pastes in one request, in the order that their IDs are given.
+pub fn fetch(uploader: &Uploader, ids: &[String]) -> Result<Vec<Fetched>, Error> {
+    let mut url = api_url(uploader.options).join(&format!("{}/", ids.join(",")))?;
+    url.query_pairs_mut().append_pair(
+        "api_key",
+        uploader
+            .options
+            .api_key
+            .as_ref()
+            .map_or("", ExposeSecret::expose_secret),
+    );
+
+    let network = |e: reqwest::Error| UploadError::Network(describe_request_error(&e));
+    let mut rsp = uploader
+        .request(uploader.client.get(url), None)
+        .map_err(network)?;
+    let status = rsp.status();
+    let text = rsp.text().map_err(network)?;
+
+    let mut pastes = match serde_json::from_str(&text) {
+        Ok(Response::Pastes { pastes }) if status.is_success() => pastes,
+        Ok(Response::Error { error_msg }) => {
+            return Err(UploadError::from_api(status, error_msg).into())
+        }
+        Ok(Response::Pastes { .. }) | Err(_) => return Err(UploadError::Unexpected(status).into()),
+    };
+
+    ids.iter()
+        .map(|id| {
+            let i = pastes
+                .iter()
+                .position(|paste| &paste.id == id)
+                .ok_or_else(|| format_err!("Paste {} was not found", id))?;
+            Ok(pastes.swap_remove(i))
+        })
+        .collect()
+}
+
+/// A file name for a paste, from its title if that makes a safe one and from its ID if not.
+fn file_name(paste: &Fetched) -> String {
+    let name = paste
+        .title
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+
+    // A title such as `.bashrc` must not write a hidden file that could be overlooked.
+    let name = name.trim_start_matches('.');
+    if name.is_empty() {
+        paste.id.clone()
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Write `body` to a new file at `path`, without replacing one that is already there.
+fn create(path: &Path, body: &str) -> io::Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?
+        .write_all(body.as_bytes())
+}
+
+/// The body of a paste, highlighted in the language it was pasted in.
+#[cfg(feature = "render")]
+fn highlight(uploader: &Uploader, paste: &Fetched) -> Result<String, Error> {
+    let lang = match paste.language.as_str() {
+        "" => crate::AUTODETECT,
+        lang => lang,
+    };
+    let title = std::path::Path::new(&paste.title);
+
+    crate::render::terminal(&paste.body, lang, Some(title), &uploader.options.theme)
+}
+
+#[cfg(not(feature = "render"))]
+fn highlight(_: &Uploader, paste: &Fetched) -> Result<String, Error> {
+    Ok(paste.body.clone())
+}
+
+/// Show `text` through $PAGER, or `less` if it is not set.
+///
+/// Unless $LESS says otherwise, less is told to quit straight away when the text fits on the
+/// screen and to pass the colours through, so short pastes are printed as if there were no
+/// pager. Setting $PAGER to `cat` or to nothing turns paging off.
+fn page(text: &str) -> Result<(), Error> {
+    let pager = env::var("PAGER").unwrap_or_else(|_| "less".into());
+    let mut words = pager.split_whitespace();
+    let program = match words.next() {
+        Some("cat") | None => return write_stdout(text),
+        Some(program) => program,
+    };
+
+    let mut command = Command::new(program);
+    command.args(words).stdin(Stdio::piped());
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| format_err!("Could not run {}: {}", program, e))?;
+    let written = child.stdin.take().unwrap().write_all(text.as_bytes());
+    child.wait()?;
+
+    quit_early(written)
+}
+
+fn write_stdout(text: &str) -> Result<(), Error> {
+    let stdout = stdout();
+    let mut stdout = stdout.lock();
+    quit_early(
+        stdout
+            .write_all(text.as_bytes())
+            .and_then(|()| stdout.flush()),
+    )
+}
+
+/// A pager or pipe that is quit before the whole paste is read, as `| head` does, is not an
+/// error.
+fn quit_early(result: io::Result<()>) -> Result<(), Error> {
+    match result {
+        Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+impl Get {
+    pub fn run(&self, uploader: &Uploader) -> Result<(), Error> {
+        check_backend(uploader.options, "get")?;
+        let ids = self
+            .pastes
+            .iter()
+            .map(|paste| paste_id(paste, &uploader.options.base_url))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if self.output.is_some() && ids.len() > 1 {
+            return Err(err_msg(
+                "--output takes a single paste; use --output-dir or -O for several",
+            ));
+        }
+
+        let pastes = fetch(uploader, &ids)?;
+
+        let dir = match (&self.output_dir, self.remote_name) {
+            (Some(dir), _) => Some(dir.clone()),
+            (None, true) => Some(PathBuf::from(".")),
+            (None, false) => None,
+        };
+
+        if let Some(dir) = dir {
+            fs::create_dir_all(&dir)
+                .map_err(|e| format_err!("Could not create {}: {}", dir.display(), e))?;
+
+            let mut names = HashSet::new();
+            for paste in &pastes {
+                let mut name = file_name(paste);
+                // Pastes with the same title are told apart by their IDs.
+                if !names.insert(name.clone()) {
+                    name = format!("{}-{}", name, paste.id);
+                    names.insert(name.clone());
+                }
+
+                let mut path = dir.join(&name);
+                let mut written = create(&path, &paste.body);
+                // A file that is already there is kept, and the paste is written next to it.
+                let suffix = format!("-{}", paste.id);
+                if matches!(written, Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists)
+                    && !name.ends_with(&suffix)
+                {
+                    path = dir.join(name + &suffix);
+                    written = create(&path, &paste.body);
+                }
+                written.map_err(|e| format_err!("Could not write {}: {}", path.display(), e))?;
+                eprintln!("{} → {}", paste.id, path.display());
+            }
+
+            return Ok(());
+        }
+
+        if let Some(path) = self.output.as_ref().filter(|path| **path != Path::new("-")) {
+            return fs::write(path, &pastes[0].body)
+                .map_err(|e| format_err!("Could not write {}: {}", path.display(), e));
+        }
+
+        // Pastes are only dressed up for someone reading them in a terminal.
+        let terminal = !self.plain && stdout().is_terminal();
+
+        let mut text = String::new();
+        for (i, paste) in pastes.iter().enumerate() {
+            // Several pastes are told apart like `head` tells files apart.
+            if pastes.len() > 1 {
+                if i > 0 {
+                    text.push('\n');
+                }
+                text.push_str(&format!("==> {} <==\n", file_name(paste)));
+            }
+
+            if terminal {
+                text.push_str(&highlight(uploader, paste)?);
+            } else {
+                text.push_str(&paste.body);
+            }
+            if pastes.len() > 1 && !paste.body.ends_with('\n') {
+                text.push('\n');
+            }
+        }
+
+        if terminal {
+            page(&text)
+        } else {
+            write_stdout(&text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn parse_paste_ids() {
+        let base = Url::parse("https://www.pastery.net/").unwrap();
+        let pastery = |s| paste_id(s, &base);
+
+        assert_eq!(pastery("abcdef").unwrap(), "abcdef");
+        assert_eq!(
+            pastery("https://www.pastery.net/abcdef/").unwrap(),
+            "abcdef"
+        );
+        assert_eq!(pastery("https://www.pastery.net/abcdef").unwrap(), "abcdef");
+        assert!(pastery("https://www.pastery.net/").is_err());
+        assert!(pastery("../etc").is_err());
+        assert!(pastery("").is_err());
+
+        // A paste on another host is not the paste on Pastery with the same ID.
+        assert!(pastery("https://gist.github.com/abcdef").is_err());
+        assert!(pastery("http://www.pastery.net/abcdef/").is_err());
+
+        let base = Url::parse("http://localhost:8000/pastery").unwrap();
+        assert_eq!(
+            paste_id("http://localhost:8000/pastery/abcdef/", &base).unwrap(),
+            "abcdef"
+        );
+        assert!(paste_id("http://localhost:8000/abcdef/", &base).is_err());
+    }
+
+    #[test]
+    fn name_files_after_pastes() {
+        let paste = |title: &str| Fetched {
+            id: "abcdef".into(),
+            title: title.into(),
+            language: "text".into(),
+            body: String::new(),
+        };
+
+        assert_eq!(file_name(&paste("main.rs")), "main.rs");
+        assert_eq!(file_name(&paste("../../.bashrc")), "_.._.bashrc");
+        assert_eq!(file_name(&paste(".bashrc")), "bashrc");
+        assert_eq!(file_name(&paste("  ")), "abcdef");
+    }
+
+    fn is_id(id: &str) -> bool {
+        !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric())
+    }
+
+    proptest! {
+        #[test]
+        fn parse_anything(s in "\\PC*") {
+            let base = Url::parse("https://www.pastery.net/").unwrap();
+            if let Ok(id) = paste_id(&s, &base) {
+                prop_assert!(is_id(&id), "{:?} gave {:?}", s, id);
+            }
+        }
+
+        #[test]
+        fn parse_anything_on_pastery(path in "\\PC*") {
+            let base = Url::parse("https://www.pastery.net/").unwrap();
+            let s = format!("https://www.pastery.net/{}", path);
+            if let Ok(id) = paste_id(&s, &base) {
+                prop_assert!(is_id(&id), "{:?} gave {:?}", s, id);
+            }
+        }
+
+        #[test]
+        fn parse_urls_of_pastes(id in "[a-zA-Z0-9]{1,16}", slash in any::<bool>()) {
+            let base = Url::parse("https://www.pastery.net/").unwrap();
+            let s = format!("https://www.pastery.net/{}{}", id, if slash { "/" } else { "" });
+            prop_assert_eq!(paste_id(&s, &base).unwrap(), id);
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use std::path::Path;
+use std::process::Command;
+
+use failure::{format_err, Error};
+use structopt::StructOpt;
+
+use crate::{Input, Paste, Uploader};
+
+#[derive(Clone, Debug, StructOpt)]
+pub enum Git {
+    /// Paste the output of `git diff` for the current repository.
+    #[structopt(name = "diff")]
+    Diff {
+        /// Paste the staged changes instead of the unstaged ones.
+        #[structopt(long = "staged")]
+        staged: bool,
+    },
+
+    /// Paste the output of `git show` for a revision in the current repository.
+    #[structopt(name = "show")]
+    Show {
+        /// The revision to show.
+        #[structopt(default_value = "HEAD")]
+        rev: String,
+    },
+
+    /// Paste the patch series generated by `git format-patch` for a revision range.
+    #[structopt(name = "format-patch")]
+    FormatPatch {
+        /// The revision range to generate patches for, e.g. `origin/master..`.
+        range: String,
+
+        /// Upload each patch as its own paste, plus an index paste linking to all of them.
+        ///
+        /// The URL of the index paste is printed.
+        #[structopt(long = "split")]
+        split: bool,
+    },
+}
+
+impl Git {
+    /// Run the git command and upload its output.
+    pub fn run(&self, uploader: &Uploader) -> Result<Paste, Error> {
+        let repo = repo_name()?;
+        let branch = git_line(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+
+        let (body, title) = match self {
+            Git::Diff { staged } => {
+                let mut args = vec!["diff", "--no-color", "--no-ext-diff"];
+                if *staged {
+                    args.push("--staged");
+                }
+
+                let what = if *staged { "staged diff" } else { "diff" };
+                (git(&args)?, format!("{}/{}: {}", repo, branch, what))
+            }
+
+            Git::Show { rev } => {
+                let short = git_line(&["rev-parse", "--short", rev])?;
+                let title = format!("{}/{}@{}", repo, branch, short);
+                (git(&["show", "--no-color", "--no-ext-diff", rev])?, title)
+            }
+
+            Git::FormatPatch { range, split } => {
+                let series = git(&["format-patch", "--stdout", "--no-color", range])?;
+                let title = format!("{}/{}: {}", repo, branch, range);
+
+                if *split && !series.is_empty() {
+                    return upload_series(uploader, &series, title);
+                }
+
+                (series, title)
+            }
+        };
+
+        if body.is_empty() {
+            return Err(format_err!("No changes to paste in {}/{}", repo, branch));
+        }
+
+        uploader.upload(diff_input(body, title))
+    }
+}
+
+fn diff_input(body: String, title: String) -> Input {
+    Input {
+        body,
+        title: Some(title),
+        lang: Some(crate::parse_lang("diff")),
+    }
+}
+
+/// Upload each patch in an mbox-formatted series, followed by an index linking to them.
+fn upload_series(uploader: &Uploader, series: &str, title: String) -> Result<Paste, Error> {
+    let patches = split_patches(series);
+    let mut index = format!("{} ({} patches)\n\n", title, patches.len());
+
+    for patch in patches {
+        let subject = patch_subject(patch).unwrap_or("(no subject)").to_owned();
+        let paste = uploader.upload(diff_input(patch.to_owned(), subject.clone()))?;
+
+        index.push_str(&format!("{}\n{}\n\n", subject, paste.url));
+    }
+
+    uploader.upload(Input {
+        body: index,
+        title: Some(title),
+        lang: Some(crate::parse_lang("text")),
+    })
+}
+
+/// Split the output of `git format-patch --stdout` into individual patches.
+fn split_patches(series: &str) -> Vec<&str> {
+    let mut starts = vec![];
+    let mut offset = 0;
+
+    for line in series.split_inclusive('\n') {
+        if is_mbox_separator(line) {
+            starts.push(offset);
+        }
+        offset += line.len();
+    }
+
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+
+    starts
+        .iter()
+        .zip(starts.iter().skip(1).chain(Some(&series.len())))
+        .map(|(&start, &end)| &series[start..end])
+        .collect()
+}
+
+/// Whether the line is the `From <sha> <date>` line that starts each patch in an mbox.
+fn is_mbox_separator(line: &str) -> bool {
+    let mut parts = line.splitn(3, ' ');
+
+    parts.next() == Some("From")
+        && parts
+            .next()
+            .map(|sha| sha.len() == 40 && sha.chars().all(|c| c.is_ascii_hexdigit()))
+            .unwrap_or(false)
+}
+
+/// Find the `Subject:` header of a patch.
+fn patch_subject(patch: &str) -> Option<&str> {
+    patch
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .find(|line| line.starts_with("Subject: "))
+        .map(|line| line["Subject: ".len()..].trim())
+}
+
+fn repo_name() -> Result<String, Error> {
+    let toplevel = git_line(&["rev-parse", "--show-toplevel"])?;
+
+    Ok(Path::new(&toplevel)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or(toplevel))
+}
+
+/// A title for a file in a git repository, from a --repo-title template.
+///
+/// Returns `None` if the file is not in a repository, or the repository has no commits yet.
+pub fn repo_title(path: &Path, template: &str) -> Option<String> {
+    let path = path.canonicalize().ok()?;
+    let dir = path.parent()?.to_str()?;
+    let output = git(&["-C", dir, "rev-parse", "--show-toplevel", "--short", "HEAD"]).ok()?;
+
+    let mut lines = output.lines();
+    let toplevel = Path::new(lines.next()?).canonicalize().ok()?;
+    let rev = lines.next()?;
+    let relative = path.strip_prefix(&toplevel).ok()?;
+
+    Some(render_repo_title(
+        template,
+        &toplevel.file_name()?.to_string_lossy(),
+        relative,
+        rev,
+    ))
+}
+
+/// The abbreviated hash of the last commit that changed a file, if it is in a git repository
+/// and has ever been committed.
+pub fn last_commit(path: &Path) -> Option<String> {
+    let path = path.canonicalize().ok()?;
+    let dir = path.parent()?.to_str()?;
+    let rev = git_line(&["-C", dir, "log", "-1", "--format=%h", "--", path.to_str()?]).ok()?;
+
+    if rev.is_empty() {
+        None
+    } else {
+        Some(rev)
+    }
+}
+
+/// Fill in the placeholders of a --repo-title template. Paths are always written with `/`.
+fn render_repo_title(template: &str, repo: &str, path: &Path, rev: &str) -> String {
+    let path = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let name = path.rsplit('/').next().unwrap_or_default().to_owned();
+
+    template
+        .replace("{repo}", repo)
+        .replace("{path}", &path)
+        .replace("{name}", &name)
+        .replace("{rev}", rev)
+}
+
+/// Run git with the given arguments and capture its output.
+pub fn git(args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| format_err!("Could not run git: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format_err!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Run git with the given arguments and capture its output as a single line.
+pub fn git_line(args: &[&str]) -> Result<String, Error> {
+    Ok(git(args)?.trim().to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn repo_titles() {
+        let path = Path::new("src").join("net").join("mod.rs");
+
+        assert_eq!(
+            render_repo_title(crate::DEFAULT_REPO_TITLE, "patisserie", &path, "d36ee4c"),
+            "patisserie/src/net/mod.rs@d36ee4c"
+        );
+        assert_eq!(
+            render_repo_title("{name} ({repo})", "patisserie", &path, "d36ee4c"),
+            "mod.rs (patisserie)"
+        );
+    }
+
+    const SERIES: &str = "\
+From 0123456789abcdef0123456789abcdef01234567 Mon Sep 17 00:00:00 2001
+From: A U Thor <author@example.com>
+Subject: [PATCH 1/2] Add a thing
+
+---
+ a | 1 +
+From 89abcdef0123456789abcdef0123456789abcdef Mon Sep 17 00:00:00 2001
+From: A U Thor <author@example.com>
+Subject: [PATCH 2/2] Fix the thing
+
+From this line on, the body is not a separator.
+";
+
+    #[test]
+    fn split_series() {
+        let patches = split_patches(SERIES);
+
+        assert_eq!(patches.len(), 2);
+        assert!(patches[0].starts_with("From 0123456789abcdef"));
+        assert!(patches[0].ends_with(" a | 1 +\n"));
+        assert!(patches[1].starts_with("From 89abcdef"));
+        assert!(patches[1].ends_with("separator.\n"));
+        assert_eq!(patches.concat(), SERIES);
+
+        assert_eq!(patch_subject(patches[0]), Some("[PATCH 1/2] Add a thing"));
+        assert_eq!(patch_subject(patches[1]), Some("[PATCH 2/2] Fix the thing"));
+
+        assert_eq!(split_patches("not a patch\n"), vec!["not a patch\n"]);
+        assert_eq!(patch_subject("not a patch\n"), None);
+    }
+}
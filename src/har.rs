@@ -0,0 +1,378 @@
+//! Recording the HTTP requests of an invocation for --har.
+//!
+//! HAR is the format that browsers export their network logs in, so the file can be opened in
+//! their developer tools or attached to a bug report. Bodies are cut off after `MAX_BODY` bytes,
+//! credentials are removed from the headers, and the secrets given on the command line are
+//! removed from everything else.
+
+use std::fs;
+use std::io::{self, Read};
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use failure::{format_err, Error};
+use reqwest::header::{HeaderMap, CONTENT_TYPE, LOCATION};
+use reqwest::{Client, RequestBuilder, Response, Url};
+use secrecy::ExposeSecret;
+use serde_json::{json, Value};
+
+use crate::i18n::tr;
+use crate::{crash, describe_request_error, Options};
+
+/// How much of each body is kept.
+const MAX_BODY: usize = 64 * 1024;
+
+/// Headers whose values are always removed.
+const CREDENTIALS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+    "set-cookie",
+];
+
+const REDACTED: &str = "(redacted)";
+
+/// The requests made so far, which are written out again after each one so that the file is
+/// complete however patisserie exits.
+pub struct Har {
+    path: PathBuf,
+    secrets: Vec<String>,
+    entries: Mutex<Vec<Value>>,
+}
+
+impl Har {
+    /// Start recording to `path`, which is written straight away so that a path that cannot be
+    /// written fails before anything is sent.
+    pub fn new(path: PathBuf, options: &Options) -> Result<Self, Error> {
+        let har = Har {
+            path,
+            secrets: crash::secrets(options)
+                .map(|secret| secret.expose_secret().to_owned())
+                .filter(|secret| !secret.is_empty())
+                .collect(),
+            entries: Mutex::new(vec![]),
+        };
+
+        har.save(&[])
+            .map_err(|e| format_err!("Could not write {}: {}", har.path.display(), e))?;
+        Ok(har)
+    }
+
+    fn redact(&self, text: &str) -> String {
+        self.secrets.iter().fold(text.to_owned(), |text, secret| {
+            text.replace(secret, REDACTED)
+        })
+    }
+
+    fn headers(&self, headers: &HeaderMap) -> Vec<Value> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if CREDENTIALS.contains(&name.as_str()) {
+                    REDACTED.into()
+                } else {
+                    self.redact(&String::from_utf8_lossy(value.as_bytes()))
+                };
+                json!({ "name": name.as_str(), "value": value })
+            })
+            .collect()
+    }
+
+    fn record(&self, entry: Value) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push(entry);
+
+        if let Err(e) = self.save(&entries) {
+            let message = format!("could not write {}: {}", self.path.display(), e);
+            eprintln!("{}", tr!("warning", message = message));
+        }
+    }
+
+    fn save(&self, entries: &[Value]) -> io::Result<()> {
+        let log = json!({
+            "log": {
+                "version": "1.2",
+                "creator": { "name": "patisserie", "version": env!("CARGO_PKG_VERSION") },
+                "entries": entries,
+            }
+        });
+
+        fs::write(&self.path, serde_json::to_string_pretty(&log)?)
+    }
+}
+
+/// The start of a request body, kept as it is sent.
+#[derive(Clone, Default)]
+pub struct Captured(Arc<Mutex<Body>>);
+
+#[derive(Default)]
+struct Body {
+    start: Vec<u8>,
+    len: usize,
+}
+
+impl Captured {
+    /// A body that is already in memory.
+    pub fn bytes(bytes: &[u8]) -> Self {
+        let captured = Captured::default();
+        captured.push(bytes);
+        captured
+    }
+
+    fn push(&self, bytes: &[u8]) {
+        let mut body = self.0.lock().unwrap();
+        let keep = MAX_BODY.saturating_sub(body.start.len()).min(bytes.len());
+        body.start.extend_from_slice(&bytes[..keep]);
+        body.len += bytes.len();
+    }
+}
+
+/// A reader that keeps the start of what is read from it.
+pub struct Tee<R> {
+    reader: R,
+    captured: Captured,
+}
+
+/// Keep the start of a streamed body as it is sent.
+pub fn tee<R: Read>(reader: R) -> (Tee<R>, Captured) {
+    let captured = Captured::default();
+    let tee = Tee {
+        reader,
+        captured: captured.clone(),
+    };
+    (tee, captured)
+}
+
+impl<R: Read> Read for Tee<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.captured.push(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// The HAR `content` or `postData` of a body.
+fn content(har: &Har, mime: &str, text: &str, len: usize) -> Value {
+    let mut end = text.len().min(MAX_BODY);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let mut content = json!({
+        "size": len,
+        "mimeType": mime,
+        "text": har.redact(&text[..end]),
+    });
+    if end < len {
+        content["comment"] = format!("Cut off after {} of {} bytes", end, len).into();
+    }
+
+    content
+}
+
+/// A request that is waiting for its response to be recorded.
+struct Pending<'h> {
+    har: &'h Har,
+    started: DateTime<Utc>,
+    start: Instant,
+    request: Value,
+    body: Option<Captured>,
+}
+
+impl Pending<'_> {
+    /// Record the request with its response, or with the error that it failed with.
+    fn finish(self, response: Result<&Response, &reqwest::Error>, text: Option<&str>) {
+        let har = self.har;
+        let mut request = self.request;
+
+        let mime = request["postData"]["mimeType"].as_str().map(String::from);
+        match (self.body, mime) {
+            (Some(body), mime) => {
+                let body = body.0.lock().unwrap();
+                let mime = mime.unwrap_or_else(|| "application/octet-stream".into());
+                request["postData"] =
+                    content(har, &mime, &String::from_utf8_lossy(&body.start), body.len);
+                request["bodySize"] = body.len.into();
+            }
+            (None, Some(_)) => {
+                request["postData"]["text"] = "".into();
+                request["postData"]["comment"] = "The body was not recorded".into();
+            }
+            (None, None) => {}
+        }
+
+        let response = match response {
+            Ok(response) => {
+                let mime = response
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|mime| mime.to_str().ok())
+                    .unwrap_or_default();
+                let content = match text {
+                    Some(text) => content(har, mime, text, text.len()),
+                    None => json!({ "size": -1, "mimeType": mime }),
+                };
+                let location = response
+                    .headers()
+                    .get(LOCATION)
+                    .and_then(|location| location.to_str().ok())
+                    .unwrap_or_default();
+
+                json!({
+                    "status": response.status().as_u16(),
+                    "statusText": response.status().canonical_reason().unwrap_or_default(),
+                    "httpVersion": format!("{:?}", response.version()),
+                    "cookies": [],
+                    "headers": har.headers(response.headers()),
+                    "content": content,
+                    "redirectURL": har.redact(location),
+                    "headersSize": -1,
+                    "bodySize": -1,
+                })
+            }
+
+            // Browsers record requests that fail without a response with a status of 0.
+            Err(e) => json!({
+                "status": 0,
+                "statusText": "",
+                "httpVersion": "",
+                "cookies": [],
+                "headers": [],
+                "content": { "size": 0, "mimeType": "" },
+                "redirectURL": "",
+                "headersSize": -1,
+                "bodySize": -1,
+                "_error": har.redact(&describe_request_error(e)),
+            }),
+        };
+
+        let time = self.start.elapsed().as_secs_f64() * 1000.0;
+        har.record(json!({
+            "startedDateTime": self.started.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "time": time,
+            "request": request,
+            "response": response,
+            "cache": {},
+            "timings": { "send": 0, "wait": time, "receive": 0 },
+        }));
+    }
+}
+
+/// A response, which is recorded once its body has been read or, if it never is, once it is
+/// dropped.
+pub struct Exchange<'h> {
+    response: Response,
+    pending: Option<Pending<'h>>,
+}
+
+impl Exchange<'_> {
+    /// Read the body of the response, recording it as well.
+    pub fn text(&mut self) -> reqwest::Result<String> {
+        let text = self.response.text();
+        if let Some(pending) = self.pending.take() {
+            pending.finish(Ok(&self.response), text.as_deref().ok());
+        }
+        text
+    }
+}
+
+impl Deref for Exchange<'_> {
+    type Target = Response;
+
+    fn deref(&self) -> &Response {
+        &self.response
+    }
+}
+
+impl DerefMut for Exchange<'_> {
+    fn deref_mut(&mut self) -> &mut Response {
+        &mut self.response
+    }
+}
+
+impl Drop for Exchange<'_> {
+    fn drop(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            pending.finish(Ok(&self.response), None);
+        }
+    }
+}
+
+/// Send `request` with `client`, recording it in `har` if there is one.
+///
+/// `body` is what is kept of the request body. Multipart forms are left out, so their bodies
+/// are marked as not recorded.
+pub fn send<'h>(
+    har: Option<&'h Har>,
+    client: &Client,
+    request: RequestBuilder,
+    body: Option<Captured>,
+) -> reqwest::Result<Exchange<'h>> {
+    let request = request.build()?;
+    let har = match har {
+        Some(har) => har,
+        None => {
+            return client.execute(request).map(|response| Exchange {
+                response,
+                pending: None,
+            })
+        }
+    };
+
+    let mut recorded = json!({
+        "method": request.method().as_str(),
+        "url": har.redact(request.url().as_str()),
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": har.headers(request.headers()),
+        "queryString": query(har, request.url()),
+        "headersSize": -1,
+        "bodySize": -1,
+    });
+    if let Some(mime) = request.headers().get(CONTENT_TYPE) {
+        recorded["postData"] = json!({ "mimeType": har.redact(mime.to_str().unwrap_or_default()) });
+    }
+
+    let pending = Pending {
+        har,
+        started: Utc::now(),
+        start: Instant::now(),
+        request: recorded,
+        body,
+    };
+
+    match client.execute(request) {
+        Ok(response) => Ok(Exchange {
+            response,
+            pending: Some(pending),
+        }),
+        Err(e) => {
+            pending.finish(Err(&e), None);
+            Err(e)
+        }
+    }
+}
+
+fn query(har: &Har, url: &Url) -> Vec<Value> {
+    url.query_pairs()
+        .map(|(name, value)| json!({ "name": name, "value": har.redact(&value) }))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cut_off_bodies() {
+        let (mut reader, captured) = tee(io::repeat(b'x').take(MAX_BODY as u64 + 10));
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+
+        let body = captured.0.lock().unwrap();
+        assert_eq!(body.start.len(), MAX_BODY);
+        assert_eq!(body.len, MAX_BODY + 10);
+    }
+}
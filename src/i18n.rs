@@ -0,0 +1,164 @@
+//! Translations of the messages that patisserie prints for people to read.
+//!
+//! Messages are looked up by ID in the Fluent files under `locales/`, in the language named by
+//! LC_ALL, LC_MESSAGES, or LANG, and fall back to English where there is no translation. Output
+//! for other programs, such as `list --format json`, is never translated.
+
+use std::env;
+use std::sync::OnceLock;
+
+use fluent::concurrent::FluentBundle;
+use fluent::{FluentArgs, FluentResource};
+
+/// The languages that messages are translated into, with their messages. English comes first.
+const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+    ("fr", include_str!("../locales/fr.ftl")),
+];
+
+struct Localizer {
+    /// The messages in the user's language, unless that is English or is not translated.
+    translated: Option<FluentBundle<FluentResource>>,
+    english: FluentBundle<FluentResource>,
+}
+
+static LOCALIZER: OnceLock<Localizer> = OnceLock::new();
+
+/// Format a message with named arguments, such as `tr!("crashed", message = e.to_string())`.
+macro_rules! tr {
+    ($id:expr) => {
+        $crate::i18n::message($id, None)
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = fluent::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::message($id, Some(&args))
+    }};
+}
+
+pub(crate) use tr;
+
+/// The message with the ID `id`, in the user's language if it has been translated.
+///
+/// A message that does not exist at all is a bug, but its ID is shown rather than nothing.
+pub fn message(id: &str, args: Option<&FluentArgs>) -> String {
+    let localizer = LOCALIZER.get_or_init(|| {
+        // Tests check the English messages whatever the language of the machine they run on.
+        let lang = if cfg!(test) { None } else { requested() };
+
+        Localizer {
+            translated: LOCALES[1..]
+                .iter()
+                .find(|(locale, _)| lang.as_deref() == Some(*locale))
+                .map(|(locale, source)| bundle(locale, source)),
+            english: bundle(LOCALES[0].0, LOCALES[0].1),
+        }
+    });
+
+    localizer
+        .translated
+        .iter()
+        .chain(Some(&localizer.english))
+        .find_map(|bundle| {
+            let pattern = bundle.get_message(id)?.value()?;
+            let mut errors = vec![];
+            Some(
+                bundle
+                    .format_pattern(pattern, args, &mut errors)
+                    .into_owned(),
+            )
+        })
+        .unwrap_or_else(|| id.to_owned())
+}
+
+/// Whether `answer` to a question means yes, either in English or in the user's language.
+pub fn is_yes(answer: &str) -> bool {
+    let answer = answer.trim().to_lowercase();
+    matches!(answer.as_str(), "y" | "yes")
+        || message("answer-yes", None)
+            .split(',')
+            .any(|yes| yes.trim() == answer)
+}
+
+fn bundle(locale: &str, source: &str) -> FluentBundle<FluentResource> {
+    let resource = FluentResource::try_new(source.to_owned())
+        .unwrap_or_else(|_| panic!("the {} messages are not valid Fluent", locale));
+
+    let mut bundle = FluentBundle::new_concurrent(vec![locale.parse().unwrap()]);
+    // Isolation marks are for mixing text directions in a GUI; in a terminal they are noise.
+    bundle.set_use_isolating(false);
+    bundle
+        .add_resource(resource)
+        .unwrap_or_else(|_| panic!("the {} messages have duplicate IDs", locale));
+    bundle
+}
+
+/// The language asked for by the environment, in the order that gettext looks, such as `fr`
+/// for `LANG=fr_FR.UTF-8`.
+fn requested() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|locale| language(&locale))
+}
+
+/// The language of a locale such as `de_AT.UTF-8@euro`, unless it is the `C` or `POSIX` locale.
+fn language(locale: &str) -> Option<String> {
+    let lang = locale
+        .split(['_', '-', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    match lang.as_str() {
+        "" | "c" | "posix" => None,
+        _ => Some(lang),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn find_languages() {
+        assert_eq!(language("fr_FR.UTF-8").as_deref(), Some("fr"));
+        assert_eq!(language("de_AT.UTF-8@euro").as_deref(), Some("de"));
+        assert_eq!(language("en-GB").as_deref(), Some("en"));
+        assert_eq!(language("C.UTF-8"), None);
+        assert_eq!(language("POSIX"), None);
+    }
+
+    #[test]
+    fn check_translations() {
+        let english = bundle(LOCALES[0].0, LOCALES[0].1);
+
+        for (locale, source) in &LOCALES[1..] {
+            bundle(locale, source);
+
+            // Each message starts a line with its ID.
+            let ids = source
+                .lines()
+                .filter(|line| line.starts_with(|c: char| c.is_ascii_lowercase()))
+                .filter_map(|line| line.split(" =").next());
+
+            for id in ids {
+                assert!(
+                    english.has_message(id),
+                    "{} translates unknown {}",
+                    locale,
+                    id
+                );
+            }
+        }
+
+        assert_eq!(
+            tr!("batch-failed", failed = 2, total = 3),
+            "2 of 3 uploads failed"
+        );
+        assert!(is_yes(" Y\n"));
+        assert!(!is_yes(""));
+    }
+}
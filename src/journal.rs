@@ -0,0 +1,142 @@
+use std::process::Command;
+
+use failure::{format_err, Error};
+use gethostname::gethostname;
+use structopt::StructOpt;
+
+use crate::capture;
+use crate::{Input, Paste, Uploader};
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct Journal {
+    /// Only show messages from this systemd unit. May be given multiple times.
+    #[structopt(long = "unit", short = "u", number_of_values = 1)]
+    units: Vec<String>,
+
+    /// Only show messages newer than this, in any form journalctl accepts (e.g. `1 hour ago`).
+    #[structopt(long = "since")]
+    since: Option<String>,
+
+    /// Only show messages older than this, in any form journalctl accepts.
+    #[structopt(long = "until")]
+    until: Option<String>,
+
+    /// Only show this many of the most recent messages.
+    #[structopt(
+        long = "lines",
+        short = "n",
+        parse(try_from_str = "crate::parse_line_count")
+    )]
+    lines: Option<u32>,
+
+    /// Read the current user's journal instead of the system journal.
+    #[structopt(long = "user")]
+    user: bool,
+}
+
+impl Journal {
+    /// Collect the journal entries and upload them.
+    pub fn run(&self, uploader: &Uploader) -> Result<Paste, Error> {
+        let (output, status) = capture::run(
+            Command::new("journalctl")
+                .args(self.journalctl_args())
+                .env("SYSTEMD_COLORS", "0"),
+        )?;
+
+        if !status.success() {
+            return Err(format_err!("journalctl failed: {}", output.trim()));
+        }
+
+        uploader.upload(Input {
+            body: output,
+            title: Some(self.title(&gethostname().to_string_lossy())),
+            lang: Some(crate::parse_lang("text")),
+        })
+    }
+
+    fn journalctl_args(&self) -> Vec<String> {
+        let mut args = vec!["--no-pager".to_owned(), "--output=short-iso".to_owned()];
+
+        if self.user {
+            args.push("--user".into());
+        }
+
+        for unit in &self.units {
+            args.push(format!("--unit={}", unit));
+        }
+
+        if let Some(ref since) = self.since {
+            args.push(format!("--since={}", since));
+        }
+
+        if let Some(ref until) = self.until {
+            args.push(format!("--until={}", until));
+        }
+
+        if let Some(lines) = self.lines {
+            args.push(format!("--lines={}", lines));
+        }
+
+        args
+    }
+
+    fn title(&self, host: &str) -> String {
+        let mut title = if self.units.is_empty() {
+            format!("journal on {}", host)
+        } else {
+            format!("journal for {} on {}", self.units.join(", "), host)
+        };
+
+        match (&self.since, &self.until) {
+            (Some(since), Some(until)) => title.push_str(&format!(" ({} to {})", since, until)),
+            (Some(since), None) => title.push_str(&format!(" (since {})", since)),
+            (None, Some(until)) => title.push_str(&format!(" (until {})", until)),
+            (None, None) => {}
+        }
+
+        title
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn args_and_titles() {
+        let journal = Journal::from_iter(&[
+            "journal",
+            "--unit",
+            "nginx",
+            "-u",
+            "php-fpm",
+            "--since",
+            "1 hour ago",
+            "-n",
+            "100",
+        ]);
+
+        assert_eq!(
+            journal.journalctl_args(),
+            vec![
+                "--no-pager",
+                "--output=short-iso",
+                "--unit=nginx",
+                "--unit=php-fpm",
+                "--since=1 hour ago",
+                "--lines=100",
+            ]
+        );
+        assert_eq!(
+            journal.title("web1"),
+            "journal for nginx, php-fpm on web1 (since 1 hour ago)"
+        );
+
+        let journal = Journal::from_iter(&["journal", "--user"]);
+        assert_eq!(
+            journal.journalctl_args(),
+            vec!["--no-pager", "--output=short-iso", "--user"]
+        );
+        assert_eq!(journal.title("web1"), "journal on web1");
+    }
+}
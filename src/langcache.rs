@@ -0,0 +1,132 @@
+//! The languages that Pastery knows, as last fetched with --refresh-languages, so that languages
+//! added since this build can be used without rebuilding it.
+//!
+//! They are kept in `patisserie/languages.json` in the cache directory, and trusted for a month
+//! after they are fetched. The languages built in are always known.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use failure::{format_err, Error};
+use lazy_static::lazy_static;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+
+use crate::duration::ONE_MONTH;
+use crate::error::UploadError;
+use crate::har::{self, Har};
+use crate::{describe_request_error, LANGUAGES};
+
+/// How long fetched languages are trusted for.
+const TTL: Duration = ONE_MONTH;
+
+#[derive(Deserialize, Serialize)]
+struct Cache {
+    /// When the languages were fetched, in seconds since the Unix epoch.
+    fetched: u64,
+    languages: BTreeSet<String>,
+}
+
+lazy_static! {
+    /// The fetched languages, if they are fresh, leaked so that they can be used like those
+    /// built in.
+    static ref CACHED: BTreeSet<&'static str> = read(SystemTime::now())
+        .into_iter()
+        .flat_map(|cache| cache.languages)
+        .map(|lang| &*Box::leak(lang.into_boxed_str()))
+        .collect();
+}
+
+/// The cache file, in $XDG_CACHE_HOME or ~/.cache.
+fn path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+    };
+    Some(dir.join("patisserie/languages.json"))
+}
+
+fn seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// The cache, unless it is missing, unreadable, or older than the TTL at `now`.
+fn read(now: SystemTime) -> Option<Cache> {
+    let cache = serde_json::from_str::<Cache>(&fs::read_to_string(path()?).ok()?).ok()?;
+    Some(cache).filter(|cache| is_fresh(cache.fetched, now))
+}
+
+/// Whether languages fetched at `fetched` can still be trusted at `now`.
+fn is_fresh(fetched: u64, now: SystemTime) -> bool {
+    seconds(now).saturating_sub(fetched) <= TTL.as_secs()
+}
+
+/// The language called `lang`, if it is built in or was fetched.
+pub fn get(lang: &str) -> Option<&'static str> {
+    LANGUAGES
+        .get_key(lang)
+        .or_else(|| CACHED.get(lang))
+        .cloned()
+}
+
+/// What --refresh-languages found.
+pub struct Refreshed {
+    /// How many languages Pastery knows.
+    pub count: usize,
+    /// How many of them are not built in.
+    pub new: usize,
+}
+
+/// Fetch the languages that Pastery knows from `url`, which lists their aliases, and keep them
+/// in the cache.
+pub fn refresh(client: &Client, har: Option<&Har>, url: Url) -> Result<Refreshed, Error> {
+    let network = |e: reqwest::Error| UploadError::Network(describe_request_error(&e));
+    let mut rsp = har::send(har, client, client.get(url), None).map_err(network)?;
+    let status = rsp.status();
+    let text = rsp.text().map_err(network)?;
+
+    let languages = match serde_json::from_str::<BTreeSet<String>>(&text) {
+        Ok(languages) if status.is_success() && !languages.is_empty() => languages,
+        _ => return Err(UploadError::Unexpected(status).into()),
+    };
+    let refreshed = Refreshed {
+        count: languages.len(),
+        new: languages
+            .iter()
+            .filter(|lang| !LANGUAGES.contains(lang.as_str()))
+            .count(),
+    };
+
+    let path = path().ok_or_else(|| format_err!("There is no cache directory to keep them in"))?;
+    let cache = Cache {
+        fetched: seconds(SystemTime::now()),
+        languages,
+    };
+    fs::create_dir_all(path.parent().unwrap())
+        .and_then(|()| fs::write(&path, serde_json::to_string(&cache)?))
+        .map_err(|e| format_err!("Could not write {}: {}", path.display(), e))?;
+
+    Ok(refreshed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::duration::ONE_DAY;
+
+    #[test]
+    fn expire_caches() {
+        let fetched = UNIX_EPOCH + ONE_DAY;
+        let fetched_at = seconds(fetched);
+
+        assert!(is_fresh(fetched_at, fetched));
+        assert!(is_fresh(fetched_at, fetched + TTL));
+        assert!(!is_fresh(fetched_at, fetched + TTL + ONE_DAY));
+        // A clock that has gone backwards still trusts the cache.
+        assert!(is_fresh(fetched_at, UNIX_EPOCH));
+    }
+}
@@ -0,0 +1,146 @@
+//! The languages that Pastery can highlight pastes in.
+
+use std::cmp::Reverse;
+use std::path::Path;
+
+include!(concat!(env!("OUT_DIR"), "/lang.codegen.rs"));
+
+/// The language that asks Pastery to detect the language of a paste.
+pub const AUTODETECT: &str = "autodetect";
+
+/// The language called `lang`, or `AUTODETECT` if Pastery does not know it.
+pub fn parse(lang: &str) -> &'static str {
+    LANGUAGES.get_key(lang).cloned().unwrap_or(AUTODETECT)
+}
+
+/// The language that Pastery knows whose name is closest to `lang`, if one is close enough to
+/// be what was meant, such as `python` for `pyton`.
+pub fn closest(lang: &str) -> Option<&'static str> {
+    let lang = lang.to_lowercase();
+    // Short names are close to too many others to guess from more than one typo.
+    let max = (lang.chars().count() / 3).max(1);
+
+    // Of the names that are as close as each other, the one that starts the same way for longest
+    // is the likeliest, since typos tend to come after the first few characters.
+    let prefix = |known: &str| {
+        lang.chars()
+            .zip(known.chars())
+            .take_while(|(a, b)| a == b)
+            .count()
+    };
+
+    LANGUAGES
+        .iter()
+        .map(|&known| (distance(&lang, known), Reverse(prefix(known)), known))
+        .filter(|&(distance, _, _)| distance <= max)
+        .min()
+        .map(|(_, _, known)| known)
+}
+
+/// The Levenshtein distance between `a` and `b`: how many characters have to be inserted,
+/// removed, or replaced to turn one into the other.
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b) in b.iter().enumerate() {
+            let replaced = diagonal + usize::from(a != b);
+            diagonal = row[j + 1];
+            row[j + 1] = replaced.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// The language of the file at `path`, from its extension, if it is one that Pastery knows.
+pub fn from_extension(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    EXTENSIONS.get(extension.as_str()).cloned()
+}
+
+/// The language of a script, from the interpreter that its shebang line names, such as
+/// `#!/bin/bash` or `#!/usr/bin/env python3`.
+pub fn from_shebang(text: &str) -> Option<&'static str> {
+    let line = text.lines().next()?.strip_prefix("#!")?;
+    let mut words = line.split_whitespace();
+    let mut program = words.next()?;
+    if program.rsplit('/').next() == Some("env") {
+        // Skip env's own options, such as -S.
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+    let program = program.rsplit('/').next()?;
+
+    // Versions are dropped, so that python3.11 is python.
+    match program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.') {
+        "sh" | "bash" | "dash" | "ksh" | "zsh" => Some("bash"),
+        "python" => Some("python"),
+        "perl" => Some("perl"),
+        "ruby" => Some("rb"),
+        "node" | "nodejs" | "deno" => Some("js"),
+        "php" => Some("php"),
+        "lua" => Some("lua"),
+        "tclsh" | "wish" => Some("tcl"),
+        "awk" | "gawk" | "mawk" => Some("awk"),
+        "Rscript" => Some("splus"),
+        "groovy" => Some("groovy"),
+        "pwsh" => Some("powershell"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_langs() {
+        assert!(LANGUAGES.contains(AUTODETECT));
+        assert_eq!(&parse(AUTODETECT), &AUTODETECT);
+        assert_eq!(&parse("rust"), LANGUAGES.get_key("rust").unwrap());
+        assert_eq!(&parse("c"), LANGUAGES.get_key("c").unwrap());
+        assert_eq!(&parse("html"), LANGUAGES.get_key("html").unwrap());
+        assert_eq!(&parse("python"), LANGUAGES.get_key("python").unwrap());
+        assert_eq!(&parse(""), &AUTODETECT);
+        assert_eq!(&parse("asdf"), &AUTODETECT);
+    }
+
+    #[test]
+    fn suggest_langs() {
+        assert_eq!(distance("pyton", "python"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "go"), 2);
+
+        assert_eq!(closest("pyton"), Some("python"));
+        assert_eq!(closest("Rust"), Some("rust"));
+        assert_eq!(closest("haskel"), Some("haskell"));
+        assert_eq!(closest("klingon"), None);
+    }
+
+    #[test]
+    fn langs_from_extensions() {
+        assert_eq!(from_extension(Path::new("src/main.rs")), Some("rust"));
+        assert_eq!(from_extension(Path::new("SETUP.PY")), Some("python"));
+        assert_eq!(from_extension(Path::new("notes.txt")), Some("text"));
+        assert_eq!(from_extension(Path::new("Makefile")), None);
+        assert_eq!(from_extension(Path::new("archive.xyz")), None);
+    }
+
+    #[test]
+    fn langs_from_shebangs() {
+        assert_eq!(from_shebang("#!/bin/bash\necho hi\n"), Some("bash"));
+        assert_eq!(from_shebang("#!/usr/bin/env python3\n"), Some("python"));
+        assert_eq!(from_shebang("#! /usr/bin/python3.11 -u\n"), Some("python"));
+        assert_eq!(
+            from_shebang("#!/usr/bin/env -S node --harmony\n"),
+            Some("js")
+        );
+        assert_eq!(from_shebang("#!/usr/bin/env\n"), None);
+        assert_eq!(from_shebang("#!/usr/bin/unknown\n"), None);
+        assert_eq!(from_shebang("echo '#!/bin/sh'\n"), None);
+        assert_eq!(from_shebang(""), None);
+    }
+}
@@ -0,0 +1,36 @@
+//! Uploading pastes to [Pastery](https://www.pastery.net/) from Rust.
+//!
+//! This is the library behind the `patisserie` command, for tools that want to upload pastes
+//! without running it.
+//!
+//! `client::Client` is async and needs a Tokio runtime, with the `async` feature.
+//! `blocking::Client` has the same API without a runtime, with the `blocking` feature, which is
+//! on by default: it runs `client::Client` on a runtime of its own.
+//!
+//! The command builds its requests with `client::PasteRequest` and reads the responses with
+//! `client::parse_response` as well, but sends them over a connection of its own, which --har,
+//! --pinned-pubkey, and --tor hook into.
+//!
+//! ```no_run
+//! use patisserie::blocking::Client;
+//! use patisserie::client::PasteRequest;
+//! use patisserie::duration::ONE_WEEK;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Client::new("my-api-key");
+//! let request = PasteRequest::new()
+//!     .title("main.rs")
+//!     .language("rust")
+//!     .duration(ONE_WEEK);
+//!
+//! let paste = client.upload(&request, "fn main() {}")?;
+//! println!("{}", paste.url);
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod client;
+pub mod duration;
+pub mod languages;
@@ -0,0 +1,628 @@
+use std::cmp::Reverse;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use failure::{format_err, Error};
+use globset::{Glob, GlobMatcher};
+use reqwest::Url;
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use structopt::StructOpt;
+
+use crate::error::UploadError;
+use crate::table::{self, Style};
+use crate::{
+    api_url, describe_request_error, duration, lang_suggestion, langcache, parse_in_range, Uploader,
+};
+
+/// The most pages of results that are followed, in case the API keeps pointing at more.
+const MAX_PAGES: usize = 1000;
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct List {
+    /// How to order the pastes: created (newest first), expiry (soonest first), or size
+    /// (largest first).
+    #[structopt(long = "sort", default_value = "created", parse(try_from_str))]
+    sort: Sort,
+
+    /// Only list pastes in this language.
+    #[structopt(long = "lang", parse(try_from_str = "parse_lang_filter"))]
+    lang: Option<String>,
+
+    /// Only list pastes whose titles match this glob, such as '*.log'.
+    #[structopt(long = "title-glob", parse(try_from_str = "parse_glob"))]
+    title_glob: Option<GlobMatcher>,
+
+    /// Only list pastes created at or after this time: a date such as 2026-10-01, an RFC 3339
+    /// time, or a duration ago such as 2d.
+    #[structopt(long = "since", parse(try_from_str = "parse_time"))]
+    since: Option<DateTime<Utc>>,
+
+    /// Only list pastes created before this time, given the same way as --since.
+    #[structopt(long = "until", parse(try_from_str = "parse_time"))]
+    until: Option<DateTime<Utc>>,
+
+    /// List at most this many pastes.
+    #[structopt(long = "limit", parse(try_from_str = "parse_limit"))]
+    limit: Option<usize>,
+
+    /// Which page of --limit pastes to list, starting from 1.
+    #[structopt(
+        long = "page",
+        parse(try_from_str = "parse_limit"),
+        raw(requires = "\"limit\"")
+    )]
+    page: Option<usize>,
+
+    /// The columns to list, separated by commas: id, url, title, lang, expires, created, and
+    /// size.
+    #[structopt(
+        long = "columns",
+        default_value = "id,title,lang,expires",
+        parse(try_from_str = "parse_columns")
+    )]
+    columns: Columns,
+
+    /// How to print the pastes: table, json, csv, or ids.
+    ///
+    /// JSON is an array with an object for each paste, holding the columns; expires is in
+    /// minutes and, like size and created, is null when the API does not give one. ids prints
+    /// only the IDs, one per line, whatever the --columns.
+    #[structopt(long = "format", default_value = "table", parse(try_from_str))]
+    format: Format,
+}
+
+/// The order that pastes are listed in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sort {
+    Created,
+    Expiry,
+    Size,
+}
+
+impl FromStr for Sort {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "created" => Ok(Sort::Created),
+            "expiry" => Ok(Sort::Expiry),
+            "size" => Ok(Sort::Size),
+            _ => Err(format_err!(
+                "Unknown sort order {}, expected one of created, expiry, size",
+                s
+            )),
+        }
+    }
+}
+
+/// Something to show about each paste.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Column {
+    Id,
+    Url,
+    Title,
+    Lang,
+    Expires,
+    Created,
+    Size,
+}
+
+impl Column {
+    fn name(self) -> &'static str {
+        match self {
+            Column::Id => "id",
+            Column::Url => "url",
+            Column::Title => "title",
+            Column::Lang => "lang",
+            Column::Expires => "expires",
+            Column::Created => "created",
+            Column::Size => "size",
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "id" => Ok(Column::Id),
+            "url" => Ok(Column::Url),
+            "title" => Ok(Column::Title),
+            "lang" => Ok(Column::Lang),
+            "expires" => Ok(Column::Expires),
+            "created" => Ok(Column::Created),
+            "size" => Ok(Column::Size),
+            _ => Err(format_err!(
+                "Unknown column {}, expected one of id, url, title, lang, expires, created, size",
+                s
+            )),
+        }
+    }
+}
+
+/// The columns given with --columns, in order.
+///
+/// clap takes a `Vec` to mean an option that can be given more than once, so the list is
+/// wrapped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Columns(Vec<Column>);
+
+fn parse_columns(s: &str) -> Result<Columns, Error> {
+    s.split(',')
+        .map(|column| column.trim().parse())
+        .collect::<Result<Vec<_>, _>>()
+        .map(Columns)
+}
+
+/// How the pastes are printed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// Columns padded with spaces, with a header.
+    Table,
+    Json,
+    Csv,
+    /// Nothing but the IDs, for piping into other commands.
+    Ids,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "ids" => Ok(Format::Ids),
+            _ => Err(format_err!(
+                "Unknown format {}, expected one of table, json, csv, ids",
+                s
+            )),
+        }
+    }
+}
+
+/// A paste in the list that the API returns.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Listed {
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    pub url: String,
+    #[serde(default)]
+    pub language: String,
+    /// How many minutes are left until the paste expires, if it ever does.
+    #[serde(default)]
+    pub duration: Option<u64>,
+    /// When the paste was created, as an RFC 3339 time.
+    #[serde(default)]
+    pub created: Option<String>,
+    /// The length of the paste in bytes.
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+impl Listed {
+    /// A column of the paste, as text.
+    fn cell(&self, column: Column) -> String {
+        match column {
+            Column::Id => self.id.clone(),
+            Column::Url => self.url.clone(),
+            Column::Title => self.title.clone(),
+            Column::Lang => self.language.clone(),
+            Column::Expires => self
+                .duration
+                .map(|minutes| duration::format(Duration::from_secs(minutes.saturating_mul(60))))
+                .unwrap_or_else(|| "never".into()),
+            Column::Created => self.created.clone().unwrap_or_default(),
+            Column::Size => self.size.map(|size| size.to_string()).unwrap_or_default(),
+        }
+    }
+
+    /// A column of the paste, as JSON.
+    fn value(&self, column: Column) -> Value {
+        match column {
+            Column::Expires => self.duration.into(),
+            Column::Created => self.created.clone().into(),
+            Column::Size => self.size.into(),
+            column => self.cell(column).into(),
+        }
+    }
+
+    pub fn created(&self) -> Option<DateTime<Utc>> {
+        self.created
+            .as_deref()
+            .and_then(|created| DateTime::parse_from_rfc3339(created).ok())
+            .map(|created| created.with_timezone(&Utc))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Response {
+    Error {
+        error_msg: String,
+    },
+    Pastes {
+        pastes: Vec<Listed>,
+        /// The URL of the next page of pastes, if there is one.
+        #[serde(default)]
+        next: Option<String>,
+    },
+}
+
+fn parse_lang_filter(s: &str) -> Result<String, Error> {
+    if langcache::get(s).is_some() {
+        Ok(s.to_owned())
+    } else {
+        Err(format_err!("Unknown language {}{}", s, lang_suggestion(s)))
+    }
+}
+
+fn parse_glob(s: &str) -> Result<GlobMatcher, Error> {
+    Glob::new(s)
+        .map(|glob| glob.compile_matcher())
+        .map_err(|e| format_err!("Invalid pattern {}: {}", s, e))
+}
+
+fn parse_limit(s: &str) -> Result<usize, Error> {
+    parse_in_range(s, 1, u32::MAX).map(|n| n as usize)
+}
+
+/// Parse a time for --since or --until. Dates are taken to be midnight in the local time zone.
+fn parse_time(s: &str) -> Result<DateTime<Utc>, Error> {
+    if let Ok(ago) = duration::parse(s) {
+        return Ok(Utc::now() - chrono::Duration::from_std(ago)?);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| {
+            Local
+                .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+                .earliest()
+        })
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| {
+            format_err!(
+                "Invalid time {}, expected a date such as 2026-10-01, an RFC 3339 time, or a \
+                 duration such as 2d",
+                s
+            )
+        })
+}
+
+/// Fetch every paste, following the API's pages until there are no more.
+///
+/// Pages are only followed on the API's own host, so that the API key is never sent anywhere
+/// else.
+pub fn fetch(uploader: &Uploader) -> Result<Vec<Listed>, Error> {
+    let api = api_url(uploader.options);
+    let network = |e: reqwest::Error| UploadError::Network(describe_request_error(&e));
+
+    let mut pastes = Vec::new();
+    let mut url = Some(api.clone());
+    for _ in 0..MAX_PAGES {
+        let Some(mut page) = url.take() else {
+            return Ok(pastes);
+        };
+        if !page.query_pairs().any(|(key, _)| key == "api_key") {
+            page.query_pairs_mut().append_pair(
+                "api_key",
+                uploader
+                    .options
+                    .api_key
+                    .as_ref()
+                    .map_or("", ExposeSecret::expose_secret),
+            );
+        }
+
+        let mut rsp = uploader
+            .request(uploader.client.get(page), None)
+            .map_err(network)?;
+        let status = rsp.status();
+        let text = rsp.text().map_err(network)?;
+
+        match serde_json::from_str(&text) {
+            Ok(Response::Pastes {
+                pastes: listed,
+                next,
+            }) if status.is_success() => {
+                pastes.extend(listed);
+                url = next.map(|next| api.join(&next)).transpose()?;
+            }
+            Ok(Response::Error { error_msg }) => {
+                return Err(UploadError::from_api(status, error_msg).into())
+            }
+            Ok(Response::Pastes { .. }) | Err(_) => {
+                return Err(UploadError::Unexpected(status).into())
+            }
+        }
+
+        if let Some(ref next) = url {
+            if !same_origin(next, &api) {
+                return Err(format_err!(
+                    "The API pointed at the next page on another host, {}",
+                    next.host_str().unwrap_or_default()
+                ));
+            }
+        }
+    }
+
+    Err(format_err!(
+        "The API returned more than {} pages of pastes",
+        MAX_PAGES
+    ))
+}
+
+fn same_origin(a: &Url, b: &Url) -> bool {
+    a.scheme() == b.scheme() && a.host_str() == b.host_str() && a.port() == b.port()
+}
+
+impl List {
+    pub fn run(&self, uploader: &Uploader) -> Result<(), Error> {
+        print!("{}", self.format(&self.select(fetch(uploader)?))?);
+        Ok(())
+    }
+
+    fn format(&self, pastes: &[Listed]) -> Result<String, Error> {
+        let Columns(ref columns) = self.columns;
+
+        match self.format {
+            Format::Table => {
+                let header = columns.iter().map(|column| column.name().to_uppercase());
+                let rows = std::iter::once(header.collect())
+                    .chain(pastes.iter().map(|paste| {
+                        columns
+                            .iter()
+                            .map(|&column| paste.cell(column).replace('\n', " "))
+                            .collect()
+                    }))
+                    .collect::<Vec<_>>();
+                Ok(table::layout(&rows, Style::Text))
+            }
+            Format::Json => {
+                let objects = pastes
+                    .iter()
+                    .map(|paste| {
+                        columns
+                            .iter()
+                            .map(|&column| (column.name().to_owned(), paste.value(column)))
+                            .collect::<Map<_, _>>()
+                    })
+                    .collect::<Vec<_>>();
+                Ok(format!("{}\n", serde_json::to_string_pretty(&objects)?))
+            }
+            Format::Csv => {
+                let mut writer = csv::Writer::from_writer(Vec::new());
+                writer.write_record(columns.iter().map(|column| column.name()))?;
+                for paste in pastes {
+                    writer.write_record(columns.iter().map(|&column| paste.cell(column)))?;
+                }
+                Ok(String::from_utf8(writer.into_inner()?)?)
+            }
+            Format::Ids => Ok(pastes
+                .iter()
+                .map(|paste| format!("{}\n", paste.id))
+                .collect()),
+        }
+    }
+
+    /// Filter, sort, and page the pastes.
+    ///
+    /// Pastes that say nothing about what they are sorted by come last, and those that do not
+    /// say when they were created are left out by --since and --until.
+    fn select(&self, mut pastes: Vec<Listed>) -> Vec<Listed> {
+        pastes.retain(|paste| {
+            let created = paste.created();
+            self.lang
+                .as_ref()
+                .is_none_or(|lang| &paste.language == lang)
+                && self
+                    .title_glob
+                    .as_ref()
+                    .is_none_or(|glob| glob.is_match(&paste.title))
+                && self
+                    .since
+                    .is_none_or(|since| created.is_some_and(|created| created >= since))
+                && self
+                    .until
+                    .is_none_or(|until| created.is_some_and(|created| created < until))
+        });
+
+        // Sorting is stable, so pastes that tie stay in the order the API gave them.
+        match self.sort {
+            Sort::Created => pastes.sort_by_key(|paste| {
+                let created = paste.created();
+                (created.is_none(), Reverse(created))
+            }),
+            Sort::Expiry => pastes.sort_by_key(|paste| (paste.duration.is_none(), paste.duration)),
+            Sort::Size => pastes.sort_by_key(|paste| (paste.size.is_none(), Reverse(paste.size))),
+        }
+
+        match self.limit {
+            Some(limit) => pastes
+                .into_iter()
+                .skip((self.page.unwrap_or(1) - 1).saturating_mul(limit))
+                .take(limit)
+                .collect(),
+            None => pastes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn select_pastes() {
+        let paste =
+            |id: &str, title: &str, lang: &str, created: Option<&str>, duration, size| Listed {
+                id: id.into(),
+                title: title.into(),
+                url: format!("https://www.pastery.net/{}/", id),
+                language: lang.into(),
+                duration,
+                created: created.map(String::from),
+                size,
+            };
+        let pastes = vec![
+            paste(
+                "a",
+                "build.log",
+                "text",
+                Some("2026-10-01T12:00:00Z"),
+                Some(60),
+                Some(10),
+            ),
+            paste(
+                "b",
+                "main.rs",
+                "rust",
+                Some("2026-10-03T12:00:00Z"),
+                None,
+                Some(300),
+            ),
+            paste("c", "test.log", "text", None, Some(10), None),
+            paste(
+                "d",
+                "lib.rs",
+                "rust",
+                Some("2026-10-02T12:00:00+02:00"),
+                Some(600),
+                Some(20),
+            ),
+        ];
+        let titles = |list: List| {
+            list.select(pastes.clone())
+                .into_iter()
+                .map(|paste| paste.title)
+                .collect::<Vec<_>>()
+        };
+        let list = || List::from_iter(&["list"]);
+
+        assert_eq!(
+            titles(list()),
+            ["main.rs", "lib.rs", "build.log", "test.log"]
+        );
+        assert_eq!(
+            titles(List {
+                sort: Sort::Expiry,
+                ..list()
+            }),
+            ["test.log", "build.log", "lib.rs", "main.rs"]
+        );
+        assert_eq!(
+            titles(List {
+                sort: Sort::Size,
+                lang: Some("rust".into()),
+                ..list()
+            }),
+            ["main.rs", "lib.rs"]
+        );
+        assert_eq!(
+            titles(List {
+                title_glob: Some(parse_glob("*.log").unwrap()),
+                ..list()
+            }),
+            ["build.log", "test.log"]
+        );
+        assert_eq!(
+            titles(List {
+                since: Some(parse_time("2026-10-02T00:00:00Z").unwrap()),
+                until: Some(parse_time("2026-10-03T00:00:00Z").unwrap()),
+                ..list()
+            }),
+            ["lib.rs"]
+        );
+        assert_eq!(
+            titles(List {
+                limit: Some(3),
+                page: Some(2),
+                ..list()
+            }),
+            ["test.log"]
+        );
+    }
+
+    #[test]
+    fn format_pastes() {
+        let pastes = [
+            Listed {
+                id: "abcdef".into(),
+                title: "build, log".into(),
+                url: "https://www.pastery.net/abcdef/".into(),
+                language: "text".into(),
+                duration: Some(1440),
+                created: None,
+                size: Some(10),
+            },
+            Listed {
+                id: "ghijkl".into(),
+                title: "main.rs".into(),
+                url: "https://www.pastery.net/ghijkl/".into(),
+                language: "rust".into(),
+                duration: None,
+                created: None,
+                size: None,
+            },
+        ];
+        let format = |args: &[&str]| {
+            List::from_iter(["list"].iter().chain(args))
+                .format(&pastes)
+                .unwrap()
+        };
+
+        assert_eq!(
+            format(&[]),
+            "ID      TITLE       LANG  EXPIRES\n\
+             ------  ----------  ----  -------\n\
+             abcdef  build, log  text  1d\n\
+             ghijkl  main.rs     rust  never\n"
+        );
+        assert_eq!(
+            format(&["--format", "csv", "--columns", "url,size"]),
+            "url,size\nhttps://www.pastery.net/abcdef/,10\nhttps://www.pastery.net/ghijkl/,\n"
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&format(&[
+                "--format",
+                "json",
+                "--columns",
+                "id,expires"
+            ]))
+            .unwrap(),
+            serde_json::json!([
+                {"id": "abcdef", "expires": 1440},
+                {"id": "ghijkl", "expires": null},
+            ])
+        );
+        assert_eq!(format(&["--format", "ids"]), "abcdef\nghijkl\n");
+        assert!(parse_columns("id,colour").is_err());
+    }
+
+    #[test]
+    fn parse_times() {
+        assert_eq!(
+            parse_time("2026-10-01T12:00:00+02:00").unwrap(),
+            Utc.with_ymd_and_hms(2026, 10, 1, 10, 0, 0).unwrap()
+        );
+        assert_eq!(
+            parse_time("2026-10-01").unwrap(),
+            Local.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap()
+        );
+
+        let ago = Utc::now() - parse_time("2d").unwrap();
+        assert!((ago - chrono::Duration::days(2)).num_seconds().abs() < 60);
+
+        assert!(parse_time("yesterday").is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use std::process::Command;
+use std::str::FromStr;
+
+use failure::{format_err, Error};
+use structopt::StructOpt;
+
+use crate::capture;
+use crate::{Input, Paste, Uploader};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Runtime {
+    Docker,
+    Podman,
+    Kubectl,
+}
+
+impl FromStr for Runtime {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "docker" => Ok(Runtime::Docker),
+            "podman" => Ok(Runtime::Podman),
+            "kubectl" | "kubernetes" | "k8s" => Ok(Runtime::Kubectl),
+            _ => Err(format_err!(
+                "Unknown runtime {}, expected one of docker, podman, kubectl",
+                s
+            )),
+        }
+    }
+}
+
+impl Runtime {
+    fn program(self) -> &'static str {
+        match self {
+            Runtime::Docker => "docker",
+            Runtime::Podman => "podman",
+            Runtime::Kubectl => "kubectl",
+        }
+    }
+}
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct Logs {
+    /// The container (for docker and podman) or pod (for kubectl) to read logs from.
+    target: String,
+
+    /// The tool used to read the logs: docker, podman, or kubectl.
+    ///
+    /// If not provided, kubectl is used when --namespace or --context is given, and docker
+    /// otherwise.
+    #[structopt(long = "runtime", parse(try_from_str))]
+    runtime: Option<Runtime>,
+
+    /// The Kubernetes namespace of the pod.
+    #[structopt(long = "namespace", short = "n")]
+    namespace: Option<String>,
+
+    /// The kubeconfig context (i.e. cluster) of the pod.
+    #[structopt(long = "context")]
+    context: Option<String>,
+
+    /// The container within the pod, for pods with more than one container.
+    #[structopt(long = "container", short = "c")]
+    container: Option<String>,
+
+    /// Only include this many of the most recent lines.
+    #[structopt(long = "tail", parse(try_from_str = "crate::parse_line_count"))]
+    tail: Option<u32>,
+
+    /// Only include logs newer than this duration (e.g. `10m`) or timestamp.
+    #[structopt(long = "since")]
+    since: Option<String>,
+
+    /// Read the logs of the previous instance of the container, e.g. after a crash.
+    ///
+    /// This is only supported by kubectl.
+    #[structopt(long = "previous", short = "p")]
+    previous: bool,
+}
+
+impl Logs {
+    /// Read the container logs and upload them.
+    pub fn run(&self, uploader: &Uploader) -> Result<Paste, Error> {
+        let runtime = self.runtime();
+
+        if self.previous && runtime != Runtime::Kubectl {
+            return Err(format_err!(
+                "--previous is only supported with kubectl, not {}",
+                runtime.program()
+            ));
+        }
+
+        let (output, status) =
+            capture::run(Command::new(runtime.program()).args(self.logs_args(runtime)))?;
+
+        if !status.success() {
+            return Err(format_err!(
+                "{} logs failed: {}",
+                runtime.program(),
+                output.trim()
+            ));
+        }
+
+        let title = match runtime {
+            Runtime::Kubectl => {
+                let context = match self.context {
+                    Some(ref context) => context.clone(),
+                    None => kubectl_line(&["config", "current-context"])?,
+                };
+
+                let namespace = match self.namespace {
+                    Some(ref namespace) => namespace.clone(),
+                    None => {
+                        let namespace = kubectl_line(&[
+                            "config",
+                            "view",
+                            "--minify",
+                            "--output=jsonpath={..namespace}",
+                        ])?;
+
+                        if namespace.is_empty() {
+                            "default".into()
+                        } else {
+                            namespace
+                        }
+                    }
+                };
+
+                self.kubectl_title(&context, &namespace)
+            }
+
+            _ => format!("{} logs {}", runtime.program(), self.target),
+        };
+
+        uploader.upload(Input {
+            body: capture::clean_terminal_output(&output),
+            title: Some(title),
+            lang: Some(crate::parse_lang("text")),
+        })
+    }
+
+    fn runtime(&self) -> Runtime {
+        match self.runtime {
+            Some(runtime) => runtime,
+            None if self.namespace.is_some() || self.context.is_some() => Runtime::Kubectl,
+            None => Runtime::Docker,
+        }
+    }
+
+    fn logs_args(&self, runtime: Runtime) -> Vec<String> {
+        let mut args = vec!["logs".to_owned()];
+
+        if runtime == Runtime::Kubectl {
+            if let Some(ref context) = self.context {
+                args.push(format!("--context={}", context));
+            }
+
+            if let Some(ref namespace) = self.namespace {
+                args.push(format!("--namespace={}", namespace));
+            }
+
+            if let Some(ref container) = self.container {
+                args.push(format!("--container={}", container));
+            }
+
+            if self.previous {
+                args.push("--previous".into());
+            }
+        }
+
+        if let Some(tail) = self.tail {
+            args.push(format!("--tail={}", tail));
+        }
+
+        if let Some(ref since) = self.since {
+            args.push(format!("--since={}", since));
+        }
+
+        args.push(self.target.clone());
+        args
+    }
+
+    fn kubectl_title(&self, context: &str, namespace: &str) -> String {
+        let mut title = format!("kubectl logs {}/{}/{}", context, namespace, self.target);
+
+        if let Some(ref container) = self.container {
+            title.push_str(&format!(" ({})", container));
+        }
+
+        if self.previous {
+            title.push_str(" (previous)");
+        }
+
+        title
+    }
+}
+
+fn kubectl_line(args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("kubectl").args(args).output()?;
+
+    if !output.status.success() {
+        return Err(format_err!(
+            "kubectl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn choose_runtime() {
+        assert_eq!(Logs::from_iter(&["logs", "web"]).runtime(), Runtime::Docker);
+        assert_eq!(
+            Logs::from_iter(&["logs", "web", "-n", "prod"]).runtime(),
+            Runtime::Kubectl
+        );
+        assert_eq!(
+            Logs::from_iter(&["logs", "web", "--runtime", "podman"]).runtime(),
+            Runtime::Podman
+        );
+        assert!("containerd".parse::<Runtime>().is_err());
+    }
+
+    #[test]
+    fn args_and_titles() {
+        let logs = Logs::from_iter(&["logs", "web", "--tail", "50"]);
+        assert_eq!(
+            logs.logs_args(Runtime::Docker),
+            vec!["logs", "--tail=50", "web"]
+        );
+
+        let logs = Logs::from_iter(&[
+            "logs",
+            "api-7d9f",
+            "--namespace",
+            "prod",
+            "-c",
+            "app",
+            "--previous",
+            "--since",
+            "1h",
+        ]);
+        assert_eq!(
+            logs.logs_args(Runtime::Kubectl),
+            vec![
+                "logs",
+                "--namespace=prod",
+                "--container=app",
+                "--previous",
+                "--since=1h",
+                "api-7d9f",
+            ]
+        );
+        assert_eq!(
+            logs.kubectl_title("eu-west", "prod"),
+            "kubectl logs eu-west/prod/api-7d9f (app) (previous)"
+        );
+    }
+}
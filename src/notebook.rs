@@ -0,0 +1,325 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use failure::{format_err, Error};
+use serde::Deserialize;
+
+use crate::{parse_lang, Converted};
+
+/// At most this many lines of each output are kept when a notebook is converted to Markdown.
+const MAX_OUTPUT_LINES: usize = 20;
+
+/// What a Jupyter notebook is converted to before it is uploaded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// The code cells, with the Markdown cells as comments and the outputs left out.
+    Script,
+    /// The Markdown cells, with the code in fenced blocks followed by a summary of its output.
+    Markdown,
+    /// The notebook as it is.
+    Raw,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "script" => Ok(Format::Script),
+            "markdown" => Ok(Format::Markdown),
+            "raw" => Ok(Format::Raw),
+            _ => Err(format_err!(
+                "Unknown notebook format {}, expected one of script, markdown, raw",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Notebook {
+    cells: Vec<Cell>,
+    #[serde(default)]
+    metadata: Metadata,
+}
+
+#[derive(Default, Deserialize)]
+struct Metadata {
+    language_info: Option<LanguageInfo>,
+    kernelspec: Option<KernelSpec>,
+}
+
+#[derive(Deserialize)]
+struct LanguageInfo {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct KernelSpec {
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Cell {
+    cell_type: String,
+    source: Source,
+    #[serde(default)]
+    outputs: Vec<Output>,
+}
+
+/// Notebooks store text either as a string or as a list of lines.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Source {
+    Text(String),
+    Lines(Vec<String>),
+}
+
+impl Source {
+    fn text(&self) -> String {
+        match self {
+            Source::Text(text) => text.clone(),
+            Source::Lines(lines) => lines.concat(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "output_type", rename_all = "snake_case")]
+enum Output {
+    Stream {
+        text: Source,
+    },
+    ExecuteResult {
+        data: serde_json::Map<String, serde_json::Value>,
+    },
+    DisplayData {
+        data: serde_json::Map<String, serde_json::Value>,
+    },
+    Error {
+        ename: String,
+        evalue: String,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// The format to convert a file to: the one given with --notebook, or Markdown for a file that
+/// is named like a notebook. `None` means the file is uploaded as it is.
+pub fn format_for(format: Option<Format>, path: Option<&Path>) -> Option<Format> {
+    let named_like_notebook = path
+        .and_then(Path::extension)
+        .is_some_and(|extension| extension == "ipynb");
+
+    match format {
+        Some(Format::Raw) => None,
+        Some(format) => Some(format),
+        None if named_like_notebook => Some(Format::Markdown),
+        None => None,
+    }
+}
+
+/// Convert the JSON of a notebook to a script or a Markdown document.
+pub fn convert(text: &str, format: Format) -> Result<Converted, Error> {
+    let notebook: Notebook = serde_json::from_str(text)
+        .map_err(|e| format_err!("This is not a Jupyter notebook: {}", e))?;
+
+    let Metadata {
+        language_info,
+        kernelspec,
+    } = notebook.metadata;
+    let language = language_info
+        .map(|info| info.name)
+        .or_else(|| kernelspec.and_then(|spec| spec.language))
+        .unwrap_or_else(|| "python".into())
+        .to_lowercase();
+
+    Ok(match format {
+        Format::Script => Converted {
+            body: script(&notebook.cells, &language),
+            lang: parse_lang(&language),
+        },
+        Format::Markdown => Converted {
+            body: markdown(&notebook.cells, &language),
+            lang: parse_lang("markdown"),
+        },
+        Format::Raw => Converted {
+            body: text.into(),
+            lang: parse_lang("json"),
+        },
+    })
+}
+
+/// The cells as a script in the percent format understood by Jupytext and most editors.
+fn script(cells: &[Cell], language: &str) -> String {
+    let comment = comment_prefix(language);
+    let mut script = String::new();
+
+    for cell in cells {
+        let source = cell.source.text();
+
+        match cell.cell_type.as_str() {
+            "code" => {
+                script.push_str(&format!("{} %%\n", comment));
+                push_line(&mut script, &source);
+            }
+            _ => {
+                script.push_str(&format!("{} %% [{}]\n", comment, cell.cell_type));
+                for line in source.lines() {
+                    script.push_str(format!("{} {}", comment, line).trim_end());
+                    script.push('\n');
+                }
+            }
+        }
+        script.push('\n');
+    }
+
+    script
+}
+
+fn markdown(cells: &[Cell], language: &str) -> String {
+    let mut document = String::new();
+
+    for cell in cells {
+        let source = cell.source.text();
+
+        match cell.cell_type.as_str() {
+            "code" => {
+                document.push_str(&format!("```{}\n", language));
+                push_line(&mut document, &source);
+                document.push_str("```\n");
+
+                for output in &cell.outputs {
+                    if let Some(summary) = summarize(output) {
+                        document.push('\n');
+                        document.push_str(&summary);
+                    }
+                }
+            }
+            _ => push_line(&mut document, &source),
+        }
+        document.push('\n');
+    }
+
+    document
+}
+
+/// Describe the output of a cell: text is kept, up to a point, and anything else is named.
+fn summarize(output: &Output) -> Option<String> {
+    let text = match output {
+        Output::Stream { text } => text.text(),
+        Output::ExecuteResult { data } | Output::DisplayData { data } => {
+            match data
+                .get("text/plain")
+                .map(|text| serde_json::from_value::<Source>(text.clone()))
+            {
+                Some(Ok(text)) => Source::text(&text),
+                _ => {
+                    let kinds = data.keys().cloned().collect::<Vec<_>>().join(", ");
+                    return Some(format!("*Output: {}*\n", kinds));
+                }
+            }
+        }
+        Output::Error { ename, evalue } => format!("{}: {}", ename, evalue),
+        Output::Unknown => return None,
+    };
+
+    let lines = text.lines().collect::<Vec<_>>();
+    let mut summary = String::from("```text\n");
+    for line in lines.iter().take(MAX_OUTPUT_LINES) {
+        summary.push_str(line);
+        summary.push('\n');
+    }
+    if lines.len() > MAX_OUTPUT_LINES {
+        summary.push_str(&format!(
+            "... {} more lines\n",
+            lines.len() - MAX_OUTPUT_LINES
+        ));
+    }
+    summary.push_str("```\n");
+
+    Some(summary)
+}
+
+/// Append `text`, making sure it ends with a newline.
+fn push_line(out: &mut String, text: &str) {
+    out.push_str(text);
+    if !text.is_empty() && !text.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+fn comment_prefix(language: &str) -> &'static str {
+    match language {
+        "c" | "c++" | "cpp" | "c#" | "csharp" | "go" | "java" | "javascript" | "kotlin"
+        | "rust" | "scala" | "swift" | "typescript" => "//",
+        "haskell" | "lua" | "sql" => "--",
+        "matlab" | "octave" => "%",
+        _ => "#",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NOTEBOOK: &str = r##"{
+        "metadata": {"language_info": {"name": "python"}},
+        "nbformat": 4,
+        "cells": [
+            {"cell_type": "markdown", "metadata": {}, "source": ["# Results\n", "\n", "Some notes"]},
+            {
+                "cell_type": "code",
+                "metadata": {},
+                "source": "import math\nprint(math.pi)",
+                "outputs": [
+                    {"output_type": "stream", "name": "stdout", "text": ["3.141592653589793\n"]},
+                    {"output_type": "display_data", "data": {"image/png": "iVBORw0KGgo="}, "metadata": {}},
+                    {"output_type": "error", "ename": "ValueError", "evalue": "oops", "traceback": []}
+                ]
+            }
+        ]
+    }"##;
+
+    #[test]
+    fn convert_notebooks() {
+        let converted = convert(NOTEBOOK, Format::Script).unwrap();
+        assert_eq!(converted.lang, parse_lang("python"));
+        assert_eq!(
+            converted.body,
+            "# %% [markdown]\n# # Results\n#\n# Some notes\n\n\
+             # %%\nimport math\nprint(math.pi)\n\n"
+        );
+
+        let converted = convert(NOTEBOOK, Format::Markdown).unwrap();
+        assert_eq!(converted.lang, parse_lang("markdown"));
+        assert_eq!(
+            converted.body,
+            "# Results\n\nSome notes\n\n\
+             ```python\nimport math\nprint(math.pi)\n```\n\n\
+             ```text\n3.141592653589793\n```\n\n\
+             *Output: image/png*\n\n\
+             ```text\nValueError: oops\n```\n\n"
+        );
+
+        assert!(convert("not json", Format::Script)
+            .unwrap_err()
+            .to_string()
+            .starts_with("This is not a Jupyter notebook: "));
+    }
+
+    #[test]
+    fn choose_formats() {
+        let notebook = Some(Path::new("analysis.ipynb"));
+        let script = Some(Path::new("analysis.py"));
+
+        assert_eq!(format_for(None, notebook), Some(Format::Markdown));
+        assert_eq!(format_for(None, script), None);
+        assert_eq!(format_for(Some(Format::Raw), notebook), None);
+        assert_eq!(
+            format_for(Some(Format::Script), notebook),
+            Some(Format::Script)
+        );
+        assert_eq!(format_for(Some(Format::Script), None), Some(Format::Script));
+    }
+}
@@ -0,0 +1,495 @@
+//! Certificate pinning for --pinned-pubkey.
+//!
+//! reqwest has no way to look at the certificate of the connection it sends a request over, so
+//! a client with pins sends its https requests through a relay on the loopback interface. The
+//! relay makes each connection to the server itself, checks the server's certificate against the
+//! pins, and only then lets the client's TLS session through, decrypting and re-encrypting it
+//! with a certificate from an authority that exists for as long as the process does and that
+//! only the client trusts. Every request, from the upload to `get`, `list`, `delete` and
+//! --refresh-languages, is sent over a connection whose pin was checked, and a connection that
+//! fails the check is closed before anything is sent on it.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use failure::{err_msg, format_err, Error};
+use lazy_static::lazy_static;
+use native_tls::{Identity, TlsAcceptor, TlsConnector, TlsStream};
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::x509::extension::{
+    BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName,
+};
+use openssl::x509::{X509NameBuilder, X509};
+use reqwest::{Certificate, ClientBuilder, Proxy, Url};
+use sha2::{Digest, Sha256};
+#[cfg(feature = "tor")]
+use socks::Socks5Stream;
+
+use crate::{bind, invocation_token};
+
+const PIN_PREFIX: &str = "sha256//";
+
+/// The most that is read of a request to the relay before its headers end.
+const MAX_HEAD: u64 = 8 * 1024;
+
+/// How long the relay waits for one side of a connection before it looks at the other.
+const POLL: Duration = Duration::from_millis(10);
+
+lazy_static! {
+    /// Why the relay last refused a connection, which reqwest only reports as a failed tunnel.
+    static ref FAILURE: Mutex<Option<String>> = Mutex::new(None);
+}
+
+/// The SHA-256 hash of a certificate's DER-encoded SubjectPublicKeyInfo.
+///
+/// Pins are written in the same `sha256//<base64>` form that curl's `--pinnedpubkey` accepts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pin([u8; 32]);
+
+impl Pin {
+    fn from_spki(spki: &[u8]) -> Self {
+        Pin(Sha256::digest(spki).into())
+    }
+}
+
+impl FromStr for Pin {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let encoded = s
+            .strip_prefix(PIN_PREFIX)
+            .ok_or_else(|| format_err!("Invalid pin {}: expected the form sha256//<base64>", s))?;
+
+        let decoded = BASE64
+            .decode(encoded)
+            .map_err(|e| format_err!("Invalid pin {}: {}", s, e))?;
+
+        if decoded.len() != 32 {
+            return Err(format_err!(
+                "Invalid pin {}: expected a 32 byte SHA-256 hash, got {} bytes",
+                s,
+                decoded.len()
+            ));
+        }
+
+        let mut hash = [0; 32];
+        hash.copy_from_slice(&decoded);
+        Ok(Pin(hash))
+    }
+}
+
+impl fmt::Display for Pin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", PIN_PREFIX, BASE64.encode(self.0))
+    }
+}
+
+/// Check that `tls` is a connection to a server that presented a certificate matching one of
+/// `pins`.
+fn check(host: &str, tls: &TlsStream<TcpStream>, pins: &[Pin]) -> Result<(), Error> {
+    let cert = tls
+        .peer_certificate()?
+        .ok_or_else(|| format_err!("{} did not present a certificate", host))?;
+    let der = cert.to_der()?;
+
+    let (_, parsed) = x509_parser::parse_x509_certificate(&der)
+        .map_err(|e| format_err!("Could not parse the certificate for {}: {}", host, e))?;
+    let actual = Pin::from_spki(parsed.tbs_certificate.subject_pki.raw);
+
+    if pins.contains(&actual) {
+        Ok(())
+    } else {
+        Err(format_err!(
+            "Certificate pin mismatch for {}: the server presented {}, but only {} is pinned. \
+             If the certificate was rotated legitimately, update the pin; otherwise the \
+             connection may be intercepted",
+            host,
+            actual,
+            pins.iter()
+                .map(Pin::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+}
+
+/// Why the relay last refused a connection, if it has since the last call.
+pub fn take_failure() -> Option<String> {
+    FAILURE.lock().unwrap().take()
+}
+
+fn key() -> Result<PKey<Private>, ErrorStack> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    PKey::from_ec_key(EcKey::generate(&group)?)
+}
+
+/// A certificate for `key` that lasts a day, issued by `issuer`, or a certificate authority of
+/// its own if there is none.
+fn issue(
+    host: &str,
+    key: &PKey<Private>,
+    issuer: Option<(&X509, &PKey<Private>)>,
+) -> Result<X509, ErrorStack> {
+    // The names differ so that the certificates issued by the authority are not taken for
+    // self-signed ones.
+    let common_name = match issuer {
+        Some(_) => "patisserie relay",
+        None => "patisserie",
+    };
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_nid(Nid::COMMONNAME, common_name)?;
+    let name = name.build();
+
+    let mut serial = BigNum::new()?;
+    serial.rand(127, MsbOption::MAYBE_ZERO, false)?;
+
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+    builder.set_serial_number(&*serial.to_asn1_integer()?)?;
+    builder.set_subject_name(&name)?;
+    builder.set_pubkey(key)?;
+    builder.set_not_before(&*Asn1Time::days_from_now(0)?)?;
+    builder.set_not_after(&*Asn1Time::days_from_now(1)?)?;
+
+    match issuer {
+        None => {
+            builder.set_issuer_name(&name)?;
+            builder.append_extension(BasicConstraints::new().critical().ca().build()?)?;
+            builder.append_extension(KeyUsage::new().critical().key_cert_sign().build()?)?;
+            builder.sign(key, MessageDigest::sha256())?;
+        }
+        Some((ca, ca_key)) => {
+            builder.set_issuer_name(ca.subject_name())?;
+            builder.append_extension(BasicConstraints::new().build()?)?;
+            builder.append_extension(ExtendedKeyUsage::new().server_auth().build()?)?;
+            let mut san = SubjectAlternativeName::new();
+            if host.parse::<IpAddr>().is_ok() {
+                san.ip(host);
+            } else {
+                san.dns(host);
+            }
+            let san = san.build(&builder.x509v3_context(Some(ca), None))?;
+            builder.append_extension(san)?;
+            builder.sign(ca_key, MessageDigest::sha256())?;
+        }
+    }
+
+    Ok(builder.build())
+}
+
+/// What the relay needs to make and check the connections that it is asked for.
+struct Relay {
+    host: String,
+    pins: Vec<Pin>,
+    socks: Option<SocketAddr>,
+    local: Option<IpAddr>,
+    /// The Proxy-Authorization that the client sends, so that nobody else can use the relay.
+    authorization: String,
+    ca: X509,
+    ca_key: PKey<Private>,
+}
+
+impl Relay {
+    /// Connect to `host`, checking its certificate against the pins if it is the pinned host.
+    ///
+    /// The connection goes through the same SOCKS proxy (if any) that the client would have
+    /// used, so that pinning never leaks traffic around Tor, and from the same --source-ip.
+    fn open(&self, host: &str, port: u16) -> Result<TlsStream<TcpStream>, Error> {
+        let name = host.trim_start_matches('[').trim_end_matches(']');
+        let stream = match self.socks {
+            #[cfg(feature = "tor")]
+            Some(proxy) => Socks5Stream::connect(proxy, (name, port))?.into_inner(),
+
+            // Without the tor feature there is never a proxy to connect through.
+            _ => bind::connect(name, port, self.local)?,
+        };
+
+        let tls = TlsConnector::new()?
+            .connect(name, stream)
+            .map_err(|e| format_err!("TLS handshake with {} failed: {}", host, e))?;
+        if host == self.host {
+            check(host, &tls, &self.pins)?;
+        }
+        Ok(tls)
+    }
+
+    /// The certificate that the relay presents to the client for `host`.
+    fn identity(&self, host: &str) -> Result<Identity, Error> {
+        let name = host.trim_start_matches('[').trim_end_matches(']');
+        let key = key()?;
+        let cert = issue(name, &key, Some((&self.ca, &self.ca_key)))?;
+        Ok(Identity::from_pkcs8(
+            &cert.to_pem()?,
+            &key.private_key_to_pem_pkcs8()?,
+        )?)
+    }
+
+    /// Answer a request for a tunnel from the client.
+    fn serve(&self, client: TcpStream) -> Result<(), Error> {
+        let (target, authorized) = {
+            // reqwest waits for the reply before it starts the TLS handshake, so nothing past
+            // the headers is left in the buffer when it is dropped.
+            let mut reader = BufReader::new((&client).take(MAX_HEAD));
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            let target = match line.split(' ').collect::<Vec<_>>()[..] {
+                ["CONNECT", target, _] => Some(target.to_owned()),
+                _ => None,
+            };
+
+            let mut authorized = false;
+            loop {
+                line.clear();
+                if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    authorized |= name.eq_ignore_ascii_case("proxy-authorization")
+                        && value.trim() == self.authorization;
+                }
+            }
+            (target, authorized)
+        };
+
+        let target = match target {
+            Some(target) if authorized => target,
+            Some(_) => return reply(&client, "407 Proxy Authentication Required"),
+            None => return reply(&client, "405 Method Not Allowed"),
+        };
+        let (host, port) = target
+            .rsplit_once(':')
+            .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+            .ok_or_else(|| format_err!("Invalid tunnel {}", target))?;
+
+        let upstream = match self.open(host, port) {
+            Ok(upstream) => upstream,
+            Err(e) => {
+                *FAILURE.lock().unwrap() = Some(e.to_string());
+                return reply(&client, "502 Bad Gateway");
+            }
+        };
+        reply(&client, "200 Connection established")?;
+
+        let downstream = TlsAcceptor::new(self.identity(host)?)?.accept(client)?;
+        Ok(pipe(downstream, upstream)?)
+    }
+}
+
+fn reply(mut client: &TcpStream, status: &str) -> Result<(), Error> {
+    Ok(write!(client, "HTTP/1.1 {}\r\n\r\n", status)?)
+}
+
+/// Copy what can be read from `from` to `to`, returning how much that was, or None once `from`
+/// is closed.
+fn forward(
+    from: &mut TlsStream<TcpStream>,
+    to: &mut TlsStream<TcpStream>,
+    buf: &mut [u8],
+) -> io::Result<Option<usize>> {
+    match from.read(buf) {
+        Ok(0) => Ok(None),
+        Ok(n) => to.write_all(&buf[..n]).map(|()| Some(n)),
+        Err(ref e)
+            if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut =>
+        {
+            Ok(Some(0))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Pass the decrypted traffic between the client and the server until either closes.
+///
+/// A TLS stream cannot be read from one thread while it is written from another, so both are
+/// read in turn, with a short timeout.
+fn pipe(mut client: TlsStream<TcpStream>, mut server: TlsStream<TcpStream>) -> io::Result<()> {
+    client.get_ref().set_read_timeout(Some(POLL))?;
+    server.get_ref().set_read_timeout(Some(POLL))?;
+
+    let mut buf = [0; 16 * 1024];
+    while forward(&mut client, &mut server, &mut buf)?.is_some()
+        && forward(&mut server, &mut client, &mut buf)?.is_some()
+    {}
+
+    let _ = client.shutdown();
+    let _ = server.shutdown();
+    Ok(())
+}
+
+/// Send the https requests of `builder` through a relay that checks that the server behind
+/// `url` presents a certificate matching one of `pins`.
+///
+/// The relay connects through `socks` and from `local` in the client's place.
+pub fn relay(
+    builder: ClientBuilder,
+    url: &Url,
+    pins: &[Pin],
+    socks: Option<SocketAddr>,
+    local: Option<IpAddr>,
+) -> Result<ClientBuilder, Error> {
+    if url.scheme() != "https" {
+        return Err(format_err!(
+            "Cannot pin the certificate of {}: it is not an https URL",
+            url.origin().ascii_serialization()
+        ));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| err_msg("Cannot pin the certificate of a URL without a host"))?;
+
+    let ca_key = key()?;
+    let ca = issue("", &ca_key, None)?;
+    let password = invocation_token();
+
+    // The client binds its end of the connection to --source-ip, so the relay listens on the
+    // loopback address of the same family.
+    let loopback = match local {
+        Some(IpAddr::V6(_)) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        _ => IpAddr::V4(Ipv4Addr::LOCALHOST),
+    };
+    let listener = TcpListener::bind((loopback, 0))?;
+    let addr = listener.local_addr()?;
+
+    let certificate = Certificate::from_pem(&ca.to_pem()?)?;
+    let relay = Arc::new(Relay {
+        host: host.to_owned(),
+        pins: pins.to_vec(),
+        socks,
+        local,
+        authorization: format!(
+            "Basic {}",
+            BASE64.encode(format!("patisserie:{}", password))
+        ),
+        ca,
+        ca_key,
+    });
+    thread::spawn(move || {
+        for client in listener.incoming().flatten() {
+            let relay = relay.clone();
+            thread::spawn(move || relay.serve(client));
+        }
+    });
+
+    let proxy = Proxy::https(&format!("http://{}", addr))?.basic_auth("patisserie", &password);
+    Ok(builder.proxy(proxy).add_root_certificate(certificate))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_pins() {
+        let pin = "sha256//47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+            .parse::<Pin>()
+            .unwrap();
+
+        assert_eq!(pin, Pin::from_spki(b""));
+        assert_eq!(
+            pin.to_string(),
+            "sha256//47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+        );
+
+        assert!("47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+            .parse::<Pin>()
+            .is_err());
+        assert!("sha256//not base64!".parse::<Pin>().is_err());
+        assert!("sha256//AAAA".parse::<Pin>().is_err());
+    }
+
+    #[test]
+    fn refuse_pins_without_tls() {
+        let url = Url::parse("http://example.com/").unwrap();
+
+        assert!(relay(
+            reqwest::Client::builder(),
+            &url,
+            &[Pin::from_spki(b"")],
+            None,
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn issue_certificates() {
+        let ca_key = key().unwrap();
+        let ca = issue("", &ca_key, None).unwrap();
+        let key = key().unwrap();
+        let cert = issue("www.pastery.net", &key, Some((&ca, &ca_key))).unwrap();
+
+        assert!(cert.verify(&ca_key).unwrap());
+        assert_eq!(
+            cert.subject_alt_names().unwrap()[0].dnsname(),
+            Some("www.pastery.net")
+        );
+    }
+
+    #[test]
+    fn close_connections_that_fail() {
+        // A server that does not speak TLS, so that the handshake with it fails.
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for mut stream in server.incoming().flatten() {
+                let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+            }
+        });
+
+        let ca_key = key().unwrap();
+        let relay = Relay {
+            host: "127.0.0.1".into(),
+            pins: vec![Pin::from_spki(b"")],
+            socks: None,
+            local: None,
+            authorization: "Basic secret".into(),
+            ca: issue("", &ca_key, None).unwrap(),
+            ca_key,
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let tunnel = |request: String| {
+            let mut client = TcpStream::connect(addr).unwrap();
+            client.write_all(request.as_bytes()).unwrap();
+            relay.serve(listener.accept().unwrap().0).unwrap();
+            let mut response = String::new();
+            client.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let connect = format!("CONNECT 127.0.0.1:{} HTTP/1.1\r\n", port);
+        assert_eq!(
+            tunnel(format!("{}\r\n", connect)),
+            "HTTP/1.1 407 Proxy Authentication Required\r\n\r\n"
+        );
+        assert_eq!(
+            tunnel("GET http://127.0.0.1/ HTTP/1.1\r\n\r\n".into()),
+            "HTTP/1.1 405 Method Not Allowed\r\n\r\n"
+        );
+
+        take_failure();
+        assert_eq!(
+            tunnel(format!(
+                "{}Proxy-Authorization: Basic secret\r\n\r\n",
+                connect
+            )),
+            "HTTP/1.1 502 Bad Gateway\r\n\r\n"
+        );
+        assert!(take_failure()
+            .unwrap()
+            .starts_with("TLS handshake with 127.0.0.1 failed"));
+    }
+}
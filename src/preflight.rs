@@ -0,0 +1,193 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io::{stderr, stdin, BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use failure::{err_msg, Error};
+
+use crate::i18n::{self, tr};
+use crate::{duration, AUTODETECT};
+
+/// One of the uploads in a batch, as far as the summary is concerned.
+pub struct Planned<'a> {
+    pub path: &'a Path,
+    pub lang: &'static str,
+    pub duration: Duration,
+    pub max_views: Option<u32>,
+    /// The host that the file is uploaded to.
+    pub host: String,
+}
+
+/// What a batch is about to upload, for the user to look over before anything is published.
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    files: usize,
+    bytes: u64,
+    /// How many files are in each language, with the extensions of the files that Pastery
+    /// has to detect the language of.
+    langs: BTreeMap<&'static str, (usize, BTreeSet<String>)>,
+    hosts: BTreeSet<String>,
+    durations: BTreeSet<Duration>,
+    max_views: BTreeSet<u32>,
+}
+
+impl Summary {
+    pub fn new(planned: &[Planned]) -> Self {
+        let mut langs = BTreeMap::<_, (usize, BTreeSet<String>)>::new();
+        for upload in planned {
+            let (count, extensions) = langs.entry(upload.lang).or_default();
+            *count += 1;
+
+            if upload.lang == AUTODETECT {
+                if let Some(extension) = upload.path.extension() {
+                    extensions.insert(format!(".{}", extension.to_string_lossy()));
+                }
+            }
+        }
+
+        Summary {
+            files: planned.len(),
+            // Files that cannot be read are reported when they are uploaded.
+            bytes: planned
+                .iter()
+                .filter_map(|upload| fs::metadata(upload.path).ok())
+                .map(|metadata| metadata.len())
+                .sum(),
+            langs,
+            hosts: planned.iter().map(|upload| upload.host.clone()).collect(),
+            durations: planned.iter().map(|upload| upload.duration).collect(),
+            max_views: planned
+                .iter()
+                .filter_map(|upload| upload.max_views)
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hosts = self.hosts.iter().cloned().collect::<Vec<_>>();
+        writeln!(
+            f,
+            "About to upload {} files ({}) to {}:",
+            self.files,
+            size(self.bytes),
+            hosts.join(" and ")
+        )?;
+
+        let langs = self
+            .langs
+            .iter()
+            .map(|(lang, (count, extensions))| match extensions.len() {
+                0 => format!("{} ({})", lang, count),
+                _ => format!(
+                    "{} ({}: {})",
+                    lang,
+                    count,
+                    extensions.iter().cloned().collect::<Vec<_>>().join(", ")
+                ),
+            })
+            .collect::<Vec<_>>();
+        writeln!(f, "  languages: {}", langs.join(", "))?;
+
+        let durations = self
+            .durations
+            .iter()
+            .map(|duration| duration::format(*duration))
+            .collect::<Vec<_>>();
+        write!(f, "  expires after: {}", durations.join(", "))?;
+        let max_views = self
+            .max_views
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>();
+        match max_views.as_slice() {
+            [] => Ok(()),
+            [once] if once == "1" => write!(f, ", or once viewed"),
+            views => write!(f, ", or after {} views", views.join(", ")),
+        }
+    }
+}
+
+/// A number of bytes in the largest binary unit that keeps it above 1.
+fn size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB"];
+
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+
+    let mut size = bytes as f64;
+    let mut unit = "bytes";
+    for next in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    format!("{:.1} {}", size, unit)
+}
+
+/// Show the summary and ask whether to go ahead, unless `yes` was given.
+///
+/// Nobody can be asked when standard input or standard error is not a terminal, so scripts go
+/// ahead without asking.
+pub fn confirm(summary: &Summary, yes: bool) -> Result<(), Error> {
+    if yes || !(stdin().is_terminal() && stderr().is_terminal()) {
+        return Ok(());
+    }
+
+    eprint!(
+        "{}\n{} {} ",
+        summary,
+        tr!("preflight-continue"),
+        tr!("prompt-choices")
+    );
+    let _ = stderr().flush();
+
+    let mut answer = String::new();
+    stdin().lock().read_line(&mut answer)?;
+    if i18n::is_yes(&answer) {
+        Ok(())
+    } else {
+        Err(err_msg(tr!("preflight-cancelled")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn summarize_batches() {
+        let planned = |path, lang, duration| Planned {
+            path: Path::new(path),
+            lang,
+            duration,
+            max_views: Some(10),
+            host: "www.pastery.net".into(),
+        };
+        let summary = Summary::new(&[
+            planned("missing/main.rs", "rust", duration::ONE_DAY),
+            planned("missing/README.md", AUTODETECT, duration::ONE_DAY),
+            planned("missing/build.log", AUTODETECT, duration::ONE_WEEK),
+            planned("missing/Makefile", AUTODETECT, duration::ONE_DAY),
+        ]);
+
+        assert_eq!(
+            summary.to_string(),
+            "About to upload 4 files (0 bytes) to www.pastery.net:\n  languages: autodetect (3: \
+             .log, .md), rust (1)\n  expires after: 1d, 1w, or after 10 views"
+        );
+    }
+
+    #[test]
+    fn format_sizes() {
+        assert_eq!(size(512), "512 bytes");
+        assert_eq!(size(1536), "1.5 KiB");
+        assert_eq!(size(5 * 1024 * 1024), "5.0 MiB");
+    }
+}
@@ -0,0 +1,481 @@
+use std::path::Path;
+
+use failure::{format_err, Error};
+use font8x8::{UnicodeFonts, BASIC_FONTS, BLOCK_FONTS, BOX_FONTS, GREEK_FONTS, LATIN_FONTS};
+use pulldown_cmark::{html::push_html, Options, Parser};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, Theme, ThemeSet};
+use syntect::html::{styled_line_to_highlighted_html, IncludeBackground};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
+
+use crate::AUTODETECT;
+
+/// Each pixel of the 8x8 font is drawn as a square this many pixels wide.
+const SCALE: usize = 2;
+const CELL_WIDTH: usize = 8 * SCALE;
+const CELL_HEIGHT: usize = 8 * SCALE;
+const LINE_HEIGHT: usize = CELL_HEIGHT + 6;
+
+/// The space between the edge of the window and the code.
+const MARGIN: usize = 24;
+const CHROME_HEIGHT: usize = 36;
+const TAB_WIDTH: usize = 4;
+
+/// An image is meant for a snippet, so anything larger is refused rather than rendered into an
+/// enormous picture.
+const MAX_LINES: usize = 500;
+const MAX_COLUMNS: usize = 200;
+
+/// The colour behind the window, and the colours of the buttons in its title bar.
+const BACKDROP: Rgb = Rgb(171, 184, 195);
+const BUTTONS: [Rgb; 3] = [Rgb(255, 95, 86), Rgb(255, 189, 46), Rgb(39, 201, 63)];
+
+/// How --as-image draws a paste.
+#[derive(Clone, Debug)]
+pub struct ImageStyle<'a> {
+    pub theme: &'a str,
+    /// The space around the window, in pixels.
+    pub padding: usize,
+    /// Whether to draw a title bar above the code.
+    pub chrome: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Rgb(u8, u8, u8);
+
+impl From<Color> for Rgb {
+    fn from(color: Color) -> Self {
+        Rgb(color.r, color.g, color.b)
+    }
+}
+
+/// An RGB image being drawn.
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize, background: Rgb) -> Self {
+        let mut canvas = Canvas {
+            width,
+            height,
+            pixels: vec![0; width * height * 3],
+        };
+        canvas.fill(0, 0, width, height, background);
+        canvas
+    }
+
+    fn set(&mut self, x: usize, y: usize, color: Rgb) {
+        if x < self.width && y < self.height {
+            let i = (y * self.width + x) * 3;
+            self.pixels[i..i + 3].copy_from_slice(&[color.0, color.1, color.2]);
+        }
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        for y in y..y + height {
+            for x in x..x + width {
+                self.set(x, y, color);
+            }
+        }
+    }
+
+    fn circle(&mut self, cx: usize, cy: usize, radius: usize, color: Rgb) {
+        let r = radius as isize;
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if dx * dx + dy * dy <= r * r {
+                    self.set(
+                        (cx as isize + dx) as usize,
+                        (cy as isize + dy) as usize,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Draw a character with its top left corner at `x`, `y`.
+    fn glyph(&mut self, x: usize, y: usize, c: char, color: Rgb) {
+        let rows = glyph(c);
+
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..8 {
+                // The least significant bit is the leftmost pixel.
+                if bits & (1 << col) != 0 {
+                    self.fill(x + col * SCALE, y + row * SCALE, SCALE, SCALE, color);
+                }
+            }
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, Error> {
+        let mut png = Vec::new();
+
+        let mut encoder = png::Encoder::new(&mut png, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+
+        Ok(png)
+    }
+}
+
+/// The bitmap of a character, or of `?` if the font has no glyph for it.
+fn glyph(c: char) -> [u8; 8] {
+    BASIC_FONTS
+        .get(c)
+        .or_else(|| LATIN_FONTS.get(c))
+        .or_else(|| BOX_FONTS.get(c))
+        .or_else(|| BLOCK_FONTS.get(c))
+        .or_else(|| GREEK_FONTS.get(c))
+        .or_else(|| BASIC_FONTS.get('?'))
+        .unwrap()
+}
+
+/// Choose the syntax to highlight with: the language given with --lang, the file's extension,
+/// or the first line (e.g. a shebang), falling back to plain text.
+fn find_syntax<'a>(
+    syntaxes: &'a SyntaxSet,
+    text: &str,
+    lang: &str,
+    path: Option<&Path>,
+) -> &'a SyntaxReference {
+    let by_lang = || match lang {
+        AUTODETECT => None,
+        lang => syntaxes.find_syntax_by_token(lang),
+    };
+    let by_extension = || {
+        path.and_then(Path::extension)
+            .and_then(|extension| syntaxes.find_syntax_by_extension(&extension.to_string_lossy()))
+    };
+
+    by_lang()
+        .or_else(by_extension)
+        .or_else(|| syntaxes.find_syntax_by_first_line(text))
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text())
+}
+
+fn find_theme<'a>(themes: &'a ThemeSet, name: &str) -> Result<&'a Theme, Error> {
+    themes.themes.get(name).ok_or_else(|| {
+        format_err!(
+            "Unknown theme {}, expected one of {}",
+            name,
+            themes.themes.keys().cloned().collect::<Vec<_>>().join(", ")
+        )
+    })
+}
+
+/// Highlight `text` and split it into lines of coloured characters, with tabs expanded.
+fn highlight(
+    text: &str,
+    syntaxes: &SyntaxSet,
+    syntax: &SyntaxReference,
+    theme: &Theme,
+) -> Result<Vec<Vec<(char, Rgb)>>, Error> {
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let mut lines = Vec::new();
+
+    for line in LinesWithEndings::from(text) {
+        let mut cells = Vec::new();
+
+        for (style, piece) in highlighter.highlight_line(line, syntaxes)? {
+            for c in piece.chars() {
+                match c {
+                    '\t' => {
+                        let spaces = TAB_WIDTH - cells.len() % TAB_WIDTH;
+                        cells.extend((0..spaces).map(|_| (' ', style.foreground.into())));
+                    }
+                    '\r' | '\n' => {}
+                    c => cells.push((c, style.foreground.into())),
+                }
+            }
+        }
+
+        cells.truncate(MAX_COLUMNS);
+        lines.push(cells);
+    }
+
+    Ok(lines)
+}
+
+/// Render `text` as a syntax-highlighted PNG, in a window floating over a plain backdrop.
+pub fn image(
+    text: &str,
+    lang: &str,
+    path: Option<&Path>,
+    style: &ImageStyle,
+) -> Result<Vec<u8>, Error> {
+    let syntaxes = SyntaxSet::load_defaults_newlines();
+    let themes = ThemeSet::load_defaults();
+    let theme = find_theme(&themes, style.theme)?;
+
+    let line_count = text.lines().count();
+    if line_count > MAX_LINES {
+        return Err(format_err!(
+            "--as-image renders at most {} lines, but the paste has {}",
+            MAX_LINES,
+            line_count
+        ));
+    }
+
+    let lines = highlight(
+        text,
+        &syntaxes,
+        find_syntax(&syntaxes, text, lang, path),
+        theme,
+    )?;
+    let columns = lines.iter().map(Vec::len).max().unwrap_or(0).max(1);
+    let rows = lines.len().max(1);
+
+    let chrome = if style.chrome { CHROME_HEIGHT } else { 0 };
+    let window_width = 2 * MARGIN + columns * CELL_WIDTH;
+    let window_height = chrome + 2 * MARGIN + rows * LINE_HEIGHT - (LINE_HEIGHT - CELL_HEIGHT);
+
+    let background = theme
+        .settings
+        .background
+        .map(Rgb::from)
+        .unwrap_or(Rgb(0, 0, 0));
+
+    let mut canvas = Canvas::new(
+        window_width + 2 * style.padding,
+        window_height + 2 * style.padding,
+        BACKDROP,
+    );
+    let (left, top) = (style.padding, style.padding);
+    canvas.fill(left, top, window_width, window_height, background);
+
+    if style.chrome {
+        for (i, &color) in BUTTONS.iter().enumerate() {
+            canvas.circle(left + 20 + i * 20, top + CHROME_HEIGHT / 2, 6, color);
+        }
+    }
+
+    for (row, cells) in lines.iter().enumerate() {
+        let y = top + chrome + MARGIN + row * LINE_HEIGHT;
+
+        for (col, &(c, color)) in cells.iter().enumerate() {
+            if c != ' ' {
+                canvas.glyph(left + MARGIN + col * CELL_WIDTH, y, c, color);
+            }
+        }
+    }
+
+    canvas.encode()
+}
+
+/// Render `text` as a self-contained HTML page, with line numbers and an anchor for each line.
+///
+/// The colours of the theme are written inline, so the page looks the same wherever it is
+/// viewed. Linking to `#L12` highlights the twelfth line.
+pub fn html(
+    text: &str,
+    lang: &str,
+    path: Option<&Path>,
+    theme: &str,
+    title: Option<&str>,
+) -> Result<String, Error> {
+    let syntaxes = SyntaxSet::load_defaults_newlines();
+    let themes = ThemeSet::load_defaults();
+    let theme = find_theme(&themes, theme)?;
+    let syntax = find_syntax(&syntaxes, text, lang, path);
+
+    let settings = &theme.settings;
+    let background = settings.background.unwrap_or(Color::BLACK);
+    let foreground = settings.foreground.unwrap_or(Color::WHITE);
+    let gutter = settings.gutter_foreground.unwrap_or(foreground);
+    let highlight = settings.line_highlight.unwrap_or(background);
+
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>\n\
+         body {{ margin: 0; background: {}; color: {}; }}\n\
+         table {{ border-collapse: collapse; font-family: monospace; }}\n\
+         td {{ padding: 0 1em; vertical-align: top; }}\n\
+         td.n {{ text-align: right; user-select: none; }}\n\
+         td.n a {{ color: {}; text-decoration: none; }}\n\
+         pre {{ margin: 0; white-space: pre; }}\n\
+         tr:target {{ background: {}; }}\n\
+         </style>\n</head>\n<body>\n<table>\n",
+        escape(title.unwrap_or(&syntax.name)),
+        css(background),
+        css(foreground),
+        css(gutter),
+        css(highlight),
+    );
+
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    for (i, line) in LinesWithEndings::from(text).enumerate() {
+        let regions = highlighter.highlight_line(line, &syntaxes)?;
+        let code = styled_line_to_highlighted_html(&regions, IncludeBackground::No)?;
+
+        page.push_str(&format!(
+            "<tr id=\"L{n}\"><td class=\"n\"><a href=\"#L{n}\">{n}</a></td><td><pre>{}</pre></td></tr>\n",
+            code.trim_end_matches(&['\r', '\n'][..]),
+            n = i + 1
+        ));
+    }
+
+    page.push_str("</table>\n</body>\n</html>\n");
+    Ok(page)
+}
+
+/// Highlight `text` with 24-bit colour escapes, for reading a paste in a terminal.
+pub fn terminal(text: &str, lang: &str, path: Option<&Path>, theme: &str) -> Result<String, Error> {
+    let syntaxes = SyntaxSet::load_defaults_newlines();
+    let themes = ThemeSet::load_defaults();
+    let theme = find_theme(&themes, theme)?;
+    let syntax = find_syntax(&syntaxes, text, lang, path);
+
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let mut out = String::new();
+    for line in LinesWithEndings::from(text) {
+        let regions = highlighter.highlight_line(line, &syntaxes)?;
+        out.push_str(&as_24_bit_terminal_escaped(&regions, false));
+    }
+
+    // Leave the terminal in its own colours afterwards.
+    out.push_str("\x1b[0m");
+    Ok(out)
+}
+
+/// Convert Markdown to a self-contained HTML document, with a little styling for readability.
+pub fn markdown(text: &str, title: Option<&str>) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>\n\
+         body {{ max-width: 46em; margin: 2em auto; padding: 0 1em; line-height: 1.5; \
+         font-family: sans-serif; color: #24292e; }}\n\
+         pre, code {{ background: #f6f8fa; font-family: monospace; }}\n\
+         pre {{ padding: 1em; overflow: auto; }}\n\
+         table {{ border-collapse: collapse; }}\n\
+         th, td {{ border: 1px solid #dfe2e5; padding: 0.3em 0.8em; }}\n\
+         blockquote {{ margin: 0; padding: 0 1em; color: #6a737d; \
+         border-left: 0.25em solid #dfe2e5; }}\n\
+         </style>\n</head>\n<body>\n",
+        escape(title.unwrap_or("Markdown"))
+    );
+    push_html(&mut page, Parser::new_ext(text, options));
+    page.push_str("</body>\n</html>\n");
+
+    page
+}
+
+fn css(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn choose_syntaxes() {
+        let syntaxes = SyntaxSet::load_defaults_newlines();
+        let name = |text, lang, path: Option<&str>| {
+            find_syntax(&syntaxes, text, lang, path.map(Path::new))
+                .name
+                .clone()
+        };
+
+        assert_eq!(name("", "rust", None), "Rust");
+        assert_eq!(name("", AUTODETECT, Some("script.py")), "Python");
+        assert_eq!(
+            name("#!/bin/bash\necho hi\n", AUTODETECT, None),
+            "Bourne Again Shell (bash)"
+        );
+        assert_eq!(name("just words", AUTODETECT, None), "Plain Text");
+    }
+
+    #[test]
+    fn render_images() {
+        let style = ImageStyle {
+            theme: "base16-ocean.dark",
+            padding: 10,
+            chrome: true,
+        };
+        let png = image(
+            "fn main() {\n\tprintln!(\"hi\");\n}\n",
+            "rust",
+            None,
+            &style,
+        )
+        .unwrap();
+
+        let decoder = png::Decoder::new(std::io::Cursor::new(png));
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+
+        // The widest line is the second: a tab expands to four columns, then 15 characters.
+        assert_eq!(info.width as usize, 2 * 10 + 2 * MARGIN + 19 * CELL_WIDTH);
+        assert_eq!(
+            info.height as usize,
+            2 * 10 + CHROME_HEIGHT + 2 * MARGIN + 3 * LINE_HEIGHT - 6
+        );
+
+        let style = ImageStyle {
+            theme: "no-such-theme",
+            ..style
+        };
+        assert!(image("x", "rust", None, &style)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Unknown theme no-such-theme, expected one of "));
+    }
+
+    #[test]
+    fn render_html() {
+        let page = html(
+            "fn main() {\n    println!(\"<hi>\");\n}\n",
+            "rust",
+            None,
+            "InspiredGitHub",
+            Some("Tom & Jerry"),
+        )
+        .unwrap();
+
+        assert!(page.starts_with("<!DOCTYPE html>\n"));
+        assert!(page.contains("<title>Tom &amp; Jerry</title>"));
+        assert!(page.contains("body { margin: 0; background: #ffffff;"));
+        assert!(page.contains("<tr id=\"L2\"><td class=\"n\"><a href=\"#L2\">2</a></td><td><pre>"));
+        assert!(page.contains("&lt;hi&gt;"));
+        assert!(!page.contains("L4"));
+        assert!(!page.contains("<hi>"));
+    }
+
+    #[test]
+    fn highlight_for_terminals() {
+        let text = terminal("fn main() {}\n", "rust", None, "base16-ocean.dark").unwrap();
+
+        assert!(text.starts_with("\x1b[38;2;"));
+        assert!(text.contains("main"));
+        assert!(text.ends_with("\n\x1b[0m"));
+    }
+
+    #[test]
+    fn render_markdown() {
+        let page = markdown("# Design\n\n| a | b |\n|---|---|\n| 1 | 2 |\n", None);
+
+        assert!(page.starts_with("<!DOCTYPE html>\n"));
+        assert!(page.contains("<title>Markdown</title>"));
+        assert!(page.contains("<h1>Design</h1>"));
+        assert!(page.contains("<td>1</td>"));
+        assert!(page.ends_with("</body>\n</html>\n"));
+    }
+}
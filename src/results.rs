@@ -0,0 +1,210 @@
+//! The pastes made from files, kept until every upload has finished, for scripts and for people
+//! to take the URLs from.
+//!
+//! --results-file writes them as JSON:
+//!
+//! ```json
+//! {
+//!   "build.log": {
+//!     "url": "https://www.pastery.net/abcdef/",
+//!     "id": "abcdef",
+//!     "expires": "2026-10-21T12:00:00Z"
+//!   }
+//! }
+//! ```
+//!
+//! The files are in the order they were given. `expires` is null for pastes that never expire,
+//! and `id` for hosts that do not report one.
+//!
+//! --summary prints them as markdown instead, as a list or a table of links, for a pull request
+//! or an issue comment.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use failure::{format_err, Error};
+use serde::{Serialize, Serializer};
+
+use crate::duration::{self, ONE_HUNDRED_YEARS};
+use crate::{embed, Paste};
+
+/// The markdown that --summary prints.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Summary {
+    /// A bullet list of links.
+    List,
+    /// A table of links, with the language and lifetime of each paste.
+    Table,
+}
+
+impl FromStr for Summary {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "markdown" | "md" => Ok(Summary::List),
+            "markdown-table" => Ok(Summary::Table),
+            _ => Err(format_err!(
+                "Unknown summary format {}, expected one of markdown, markdown-table",
+                s
+            )),
+        }
+    }
+}
+
+/// What --results-file keeps of a paste.
+#[derive(Serialize)]
+struct Created<'a> {
+    url: &'a str,
+    id: Option<&'a str>,
+    expires: Option<String>,
+}
+
+/// The pastes published so far, with the files they were made from and when.
+#[derive(Default)]
+pub struct Results(Vec<(PathBuf, Paste, DateTime<Utc>)>);
+
+/// Whether a paste lasts for as long as its host keeps it.
+fn never_expires(paste: &Paste) -> bool {
+    paste.duration >= ONE_HUNDRED_YEARS
+}
+
+impl Results {
+    /// Keep the paste made from `path`, which expires counting from `now`.
+    pub fn add(&mut self, path: &Path, paste: &Paste, now: DateTime<Utc>) {
+        self.0.push((path.to_owned(), paste.clone(), now));
+    }
+
+    /// Write the pastes to `path`, or print them if it is `-`.
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self)?;
+        if path == Path::new("-") {
+            println!("{}", json);
+            return Ok(());
+        }
+
+        fs::write(path, json + "\n")
+            .map_err(|e| format_err!("Could not write {}: {}", path.display(), e))
+    }
+
+    /// The pastes as markdown, each linked from the path of its file.
+    pub fn markdown(&self, summary: Summary) -> String {
+        let mut markdown = String::new();
+        if summary == Summary::Table && !self.0.is_empty() {
+            markdown.push_str("| File | Language | Expires |\n| --- | --- | --- |\n");
+        }
+
+        for (path, paste, _) in &self.0 {
+            let link = format!(
+                "[{}]({})",
+                embed::markdown(&path.to_string_lossy()),
+                paste.url
+            );
+            match summary {
+                Summary::List => writeln!(markdown, "- {}", link),
+                Summary::Table => writeln!(
+                    markdown,
+                    "| {} | {} | {} |",
+                    link.replace('|', "\\|"),
+                    paste.lang,
+                    if never_expires(paste) {
+                        "never".into()
+                    } else {
+                        duration::format(paste.duration)
+                    }
+                ),
+            }
+            .unwrap();
+        }
+
+        markdown
+    }
+}
+
+impl Serialize for Results {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(path, paste, published)| {
+            let expires = if never_expires(paste) {
+                None
+            } else {
+                chrono::Duration::from_std(paste.duration)
+                    .ok()
+                    .and_then(|duration| published.checked_add_signed(duration))
+                    .map(|expires| expires.to_rfc3339_opts(SecondsFormat::Secs, true))
+            };
+
+            let created = Created {
+                url: &paste.url,
+                id: paste.id.as_deref(),
+                expires,
+            };
+            (path.to_string_lossy(), created)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::duration::ONE_DAY;
+    use chrono::TimeZone;
+
+    fn results() -> Results {
+        let now = Utc.with_ymd_and_hms(2026, 10, 14, 12, 0, 0).unwrap();
+        let paste = |url: &str, lang, duration| Paste {
+            url: url.into(),
+            id: None,
+            title: None,
+            lang,
+            duration,
+            max_views: None,
+        };
+
+        let mut results = Results::default();
+        results.add(
+            Path::new("src/main.rs"),
+            &Paste {
+                id: Some("abcdef".into()),
+                ..paste("https://www.pastery.net/abcdef/", "rust", ONE_DAY)
+            },
+            now,
+        );
+        results.add(
+            Path::new("logs/[x86]|build.log"),
+            &paste(
+                "https://haste.example.com/ghijkl",
+                "text",
+                ONE_HUNDRED_YEARS,
+            ),
+            now,
+        );
+        results
+    }
+
+    #[test]
+    fn serialize_results() {
+        assert_eq!(
+            serde_json::to_string(&results()).unwrap(),
+            r#"{"src/main.rs":{"url":"https://www.pastery.net/abcdef/","id":"abcdef","expires":"2026-10-15T12:00:00Z"},"logs/[x86]|build.log":{"url":"https://haste.example.com/ghijkl","id":null,"expires":null}}"#
+        );
+    }
+
+    #[test]
+    fn summarize_results() {
+        assert_eq!(
+            results().markdown(Summary::List),
+            "- [src/main.rs](https://www.pastery.net/abcdef/)\n\
+             - [logs/\\[x86\\]|build.log](https://haste.example.com/ghijkl)\n"
+        );
+        assert_eq!(
+            results().markdown(Summary::Table),
+            "| File | Language | Expires |\n| --- | --- | --- |\n\
+             | [src/main.rs](https://www.pastery.net/abcdef/) | rust | 1d |\n\
+             | [logs/\\[x86\\]\\|build.log](https://haste.example.com/ghijkl) | text | never |\n"
+        );
+        assert_eq!(Results::default().markdown(Summary::Table), "");
+    }
+}
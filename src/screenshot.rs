@@ -0,0 +1,187 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use chrono::Local;
+use failure::{err_msg, format_err, Error};
+use structopt::StructOpt;
+
+use crate::filehost::{self, File};
+use crate::{create_private_dir, temp_path, Paste, Uploader};
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct Screenshot {
+    /// Select the region of the screen to capture.
+    #[structopt(long = "region")]
+    region: bool,
+
+    /// Capture a single window instead of the whole screen.
+    ///
+    /// On X11 this is the active window, and on macOS the window that is clicked.
+    #[structopt(long = "window", conflicts_with = "region")]
+    window: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Area {
+    Screen,
+    Region,
+    Window,
+}
+
+/// The screenshot tools that patisserie knows how to drive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Tool {
+    /// grim, with slurp to select a region, on Wayland.
+    Grim,
+    /// maim, with xdotool to find the active window, on X11.
+    Maim,
+    /// screencapture on macOS.
+    Screencapture,
+}
+
+impl Screenshot {
+    /// Take a screenshot and upload it to the --file-host.
+    pub fn run(&self, uploader: &Uploader) -> Result<Paste, Error> {
+        let area = match (self.region, self.window) {
+            (true, _) => Area::Region,
+            (_, true) => Area::Window,
+            _ => Area::Screen,
+        };
+
+        // The screenshot is written by another program, so it goes in a directory that only
+        // the user can reach rather than at a path that someone else could have taken first.
+        let dir = temp_path("patisserie-screenshot-", "");
+        create_private_dir(&dir)
+            .map_err(|e| format_err!("Could not create {}: {}", dir.display(), e))?;
+        let path = dir.join("screenshot.png");
+        let captured = capture(Tool::detect()?, area, &path);
+        let png = captured
+            .and_then(|()| fs::read(&path).map_err(|_| err_msg("The screenshot was cancelled")));
+        let _ = fs::remove_dir_all(&dir);
+
+        filehost::upload(
+            uploader,
+            File {
+                name: "screenshot.png".into(),
+                mime: "image/png",
+                contents: png?,
+                kind: "png",
+                title: Some(format!(
+                    "Screenshot {}",
+                    Local::now().format("%Y-%m-%d %H:%M")
+                )),
+            },
+        )
+    }
+}
+
+impl Tool {
+    fn detect() -> Result<Self, Error> {
+        if cfg!(target_os = "macos") {
+            Ok(Tool::Screencapture)
+        } else if cfg!(unix) && env::var_os("WAYLAND_DISPLAY").is_some() {
+            Ok(Tool::Grim)
+        } else if cfg!(unix) && env::var_os("DISPLAY").is_some() {
+            Ok(Tool::Maim)
+        } else {
+            Err(err_msg(
+                "Screenshots are only supported on Wayland (with grim and slurp), X11 (with maim \
+                 and xdotool), and macOS",
+            ))
+        }
+    }
+
+    /// The arguments that capture `area` to `path`.
+    ///
+    /// `selection` is the region chosen with slurp for grim, or the active window for maim.
+    fn args(self, area: Area, selection: Option<&str>, path: &Path) -> Vec<OsString> {
+        let mut args: Vec<OsString> = match (self, area, selection) {
+            (Tool::Grim, Area::Region, Some(geometry)) => vec!["-g".into(), geometry.into()],
+            (Tool::Maim, Area::Region, _) => vec!["--select".into()],
+            (Tool::Maim, Area::Window, Some(window)) => vec!["--window".into(), window.into()],
+            (Tool::Screencapture, Area::Screen, _) => vec!["-x".into()],
+            (Tool::Screencapture, Area::Region, _) => vec!["-x".into(), "-i".into(), "-s".into()],
+            (Tool::Screencapture, Area::Window, _) => vec!["-x".into(), "-i".into(), "-w".into()],
+            _ => vec![],
+        };
+
+        args.push(path.into());
+        args
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Tool::Grim => "grim",
+            Tool::Maim => "maim",
+            Tool::Screencapture => "screencapture",
+        }
+    }
+}
+
+fn capture(tool: Tool, area: Area, path: &Path) -> Result<(), Error> {
+    let selection = match (tool, area) {
+        (Tool::Grim, Area::Region) => Some(output(&mut Command::new("slurp"))?),
+        (Tool::Grim, Area::Window) => {
+            return Err(err_msg(
+                "--window is not supported on Wayland; use --region and select the window",
+            ));
+        }
+        (Tool::Maim, Area::Window) => Some(output(Command::new("xdotool").arg("getactivewindow"))?),
+        _ => None,
+    };
+
+    let mut command = Command::new(tool.program());
+    command.args(tool.args(area, selection.as_deref(), path));
+    output(&mut command).map(|_| ())
+}
+
+/// Run a command and return its trimmed standard output.
+fn output(command: &mut Command) -> Result<String, Error> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .output()
+        .map_err(|e| format_err!("Could not run {}: {}", program, e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.trim() {
+            "" => format_err!("{} failed ({})", program, output.status),
+            stderr => format_err!("{} failed: {}", program, stderr),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tool_args() {
+        let path = Path::new("/tmp/shot.png");
+        let args = |tool: Tool, area, selection| {
+            tool.args(area, selection, path)
+                .into_iter()
+                .map(|arg| arg.into_string().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(args(Tool::Grim, Area::Screen, None), ["/tmp/shot.png"]);
+        assert_eq!(
+            args(Tool::Grim, Area::Region, Some("10,20 300x200")),
+            ["-g", "10,20 300x200", "/tmp/shot.png"]
+        );
+        assert_eq!(
+            args(Tool::Maim, Area::Window, Some("41943047")),
+            ["--window", "41943047", "/tmp/shot.png"]
+        );
+        assert_eq!(
+            args(Tool::Screencapture, Area::Region, None),
+            ["-x", "-i", "-s", "/tmp/shot.png"]
+        );
+    }
+}
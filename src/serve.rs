@@ -0,0 +1,550 @@
+//! An HTTP gateway that uploads the bodies POSTed to it as pastes.
+//!
+//! `POST /` with the contents as the body creates a paste with the gateway's own options and
+//! API key, and responds with `201 Created` and the URL of the paste as plain text. The title
+//! and language can be given as the `title` and `lang` query parameters. When the gateway has a
+//! token, requests have to carry it as `Authorization: Bearer <token>`.
+//!
+//! Without a token, the gateway only listens on the loopback address. Browsers are refused, since
+//! a page could otherwise POST to the gateway from anywhere: requests with an `Origin` header are
+//! rejected, and without a token so are those whose `Host` is not the loopback address, which a
+//! DNS rebinding attack would give itself away with.
+//!
+//! Each connection carries one request, so there is no keep-alive to get wrong.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::io::FromRawFd;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use failure::{format_err, Error};
+use secrecy::{ExposeSecret, SecretString};
+use structopt::StructOpt;
+use url::form_urlencoded;
+
+use crate::i18n::tr;
+use crate::systemd::Idle;
+use crate::{parse_lang, parse_timeout, Input, Uploader};
+
+/// The largest body that is accepted, in bytes.
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+/// The longest line of the request line and headers that is accepted, in bytes.
+const MAX_LINE: usize = 8 * 1024;
+
+/// The most headers that are accepted in a request.
+const MAX_HEADERS: usize = 100;
+
+/// The longest a client can take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The most connections that are served at once, each on a thread of its own. Any more are
+/// turned away until one finishes.
+const MAX_CONNECTIONS: usize = 32;
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct Serve {
+    /// The address and port to listen on.
+    ///
+    /// Pastes are uploaded with your API key, so the gateway refuses to listen on an address
+    /// other than the loopback address without a --token. Ignored when systemd passes the socket
+    /// to the gateway, which is held to the same rule.
+    #[structopt(long = "listen", default_value = "127.0.0.1:8899")]
+    listen: SocketAddr,
+
+    /// Exit once no pastes have been POSTed for this many seconds.
+    #[structopt(long = "idle-timeout", parse(try_from_str = "parse_timeout"))]
+    idle_timeout: Option<Duration>,
+
+    /// A token that requests have to send as `Authorization: Bearer <token>`.
+    #[structopt(
+        long = "token",
+        env = "PATISSERIE_SERVE_TOKEN",
+        parse(from_str),
+        hide_env_values = true
+    )]
+    token: Option<SecretString>,
+}
+
+/// A request, as far as the gateway cares about it.
+struct Request {
+    method: String,
+    target: String,
+    host: Option<String>,
+    origin: Option<String>,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+/// A response: the status, extra headers, and a plain text body.
+struct Response {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+impl Response {
+    fn new(status: u16, body: impl Into<String>) -> Self {
+        Response {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+impl Serve {
+    /// Listen for pastes until killed or idle, uploading each with the gateway's own options.
+    pub fn run(&self, uploader: &Uploader) -> Result<(), Error> {
+        let listener = self.listener()?;
+        let addr = listener.local_addr()?;
+        if self.token.is_none() && !addr.ip().is_loopback() {
+            return Err(format_err!(
+                "Anyone who can reach {} could paste with your API key: give a --token to listen \
+                 on it",
+                addr
+            ));
+        }
+        eprintln!("{}", tr!("listening", address = format!("http://{}", addr)));
+
+        let idle = self.idle_timeout.map(Idle::new);
+        let connections = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            if let Some(ref idle) = idle {
+                scope.spawn(move || idle.exit_when_idle());
+            }
+
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let connection = match Connection::open(&connections) {
+                            Some(connection) => connection,
+                            None => {
+                                let _ = turn_away(&stream);
+                                continue;
+                            }
+                        };
+                        let busy = idle.as_ref().map(Idle::busy);
+                        scope.spawn(move || {
+                            let _busy = busy;
+                            let _connection = connection;
+                            if let Err(e) = self.serve(uploader, stream) {
+                                eprintln!("{}", tr!("error", message = e.to_string()));
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        let message = tr!("not-accepted", error = e.to_string());
+                        eprintln!("{}", tr!("error", message = message));
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn listener(&self) -> Result<TcpListener, Error> {
+        #[cfg(unix)]
+        if let Some(fd) = crate::systemd::listen_fd()? {
+            // systemd created the socket, and keeps it for the next gateway once this one exits.
+            return Ok(unsafe { TcpListener::from_raw_fd(fd) });
+        }
+
+        TcpListener::bind(self.listen)
+            .map_err(|e| format_err!("Could not listen on {}: {}", self.listen, e))
+    }
+
+    fn serve(&self, uploader: &Uploader, stream: TcpStream) -> Result<(), Error> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        let response = match read_request(&mut BufReader::new(&stream)) {
+            Ok(request) => self.respond(uploader, request),
+            Err(response) => response,
+        };
+
+        write_response(&stream, &response)
+    }
+
+    fn respond(&self, uploader: &Uploader, request: Request) -> Response {
+        let (path, query) = match request.target.split_once('?') {
+            Some((path, query)) => (path, query),
+            None => (request.target.as_str(), ""),
+        };
+
+        if path != "/" {
+            return Response::new(404, "Not found; POST pastes to /\n");
+        }
+        if request.method != "POST" {
+            return Response::new(405, "Only POST is supported\n").header("Allow", "POST");
+        }
+        if request.origin.is_some() {
+            return Response::new(403, "Requests from web pages are not accepted\n");
+        }
+        if self.token.is_none() && !request.host.as_deref().is_some_and(is_loopback) {
+            return Response::new(
+                403,
+                "Without a --token, requests have to be addressed to localhost\n",
+            );
+        }
+        if let Some(ref token) = self.token {
+            let bearer = request
+                .authorization
+                .as_deref()
+                .and_then(|value| value.strip_prefix("Bearer "));
+
+            if !bearer.is_some_and(|bearer| same(bearer.trim(), token.expose_secret())) {
+                return Response::new(401, "A valid token is required\n")
+                    .header("WWW-Authenticate", "Bearer");
+            }
+        }
+
+        let body = match String::from_utf8(request.body) {
+            Ok(body) => body,
+            Err(_) => return Response::new(400, "The paste is not valid UTF-8\n"),
+        };
+
+        let mut input = Input {
+            body,
+            title: None,
+            lang: None,
+        };
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            match &*key {
+                "title" if !value.is_empty() => input.title = Some(value.into_owned()),
+                "lang" => input.lang = Some(parse_lang(&value)),
+                _ => {}
+            }
+        }
+
+        match uploader.upload(input) {
+            Ok(paste) => {
+                Response::new(201, format!("{}\n", paste.url)).header("Location", paste.url)
+            }
+            Err(e) => {
+                eprintln!("{}", tr!("error", message = e.to_string()));
+                Response::new(502, format!("{}\n", e))
+            }
+        }
+    }
+}
+
+/// A connection being served, counted until it is dropped.
+struct Connection<'a>(&'a AtomicUsize);
+
+impl<'a> Connection<'a> {
+    /// Count a connection, unless `MAX_CONNECTIONS` are already being served.
+    fn open(connections: &'a AtomicUsize) -> Option<Self> {
+        connections
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+                Some(open + 1).filter(|&open| open <= MAX_CONNECTIONS)
+            })
+            .ok()
+            .map(|_| Connection(connections))
+    }
+}
+
+impl Drop for Connection<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Tell a client that there are too many connections to serve it, without waiting on it for
+/// longer than it takes to write the response.
+fn turn_away(stream: &TcpStream) -> Result<(), Error> {
+    stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+    write_response(
+        stream,
+        &Response::new(503, "Too many requests at once; try again\n").header("Retry-After", "1"),
+    )
+}
+
+fn write_response(mut stream: &TcpStream, response: &Response) -> Result<(), Error> {
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\n\
+         Connection: close\r\n",
+        response.status,
+        reason(response.status),
+        response.body.len()
+    )?;
+    for (name, value) in &response.headers {
+        write!(stream, "{}: {}\r\n", name, value)?;
+    }
+    write!(stream, "\r\n{}", response.body)?;
+    stream.flush()?;
+
+    Ok(())
+}
+
+/// Whether the `Host` of a request names the loopback address, such as `localhost:8899` or
+/// `[::1]:8899`.
+fn is_loopback(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => host,
+    };
+    let name = name
+        .strip_prefix('[')
+        .and_then(|name| name.strip_suffix(']'))
+        .unwrap_or(name);
+
+    name.eq_ignore_ascii_case("localhost")
+        || name.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Compare a token without giving away how much of it was right in how long it took.
+fn same(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Read a line of a request of at most `MAX_LINE` bytes, or `None` if it is any longer.
+fn read_line<R: BufRead>(reader: &mut R) -> std::io::Result<Option<String>> {
+    let mut line = String::new();
+    reader
+        .by_ref()
+        .take(MAX_LINE as u64 + 1)
+        .read_line(&mut line)?;
+
+    Ok(Some(line).filter(|line| line.len() <= MAX_LINE))
+}
+
+/// Read a request, or the response that explains why it could not be read.
+fn read_request<R: BufRead>(reader: &mut R) -> Result<Request, Response> {
+    let bad = |message: &str| Response::new(400, format!("{}\n", message));
+    let too_long = || Response::new(431, "The request headers are too large\n");
+
+    let line = read_line(reader)
+        .map_err(|_| bad("Could not read the request"))?
+        .ok_or_else(too_long)?;
+
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_owned(), target.to_owned()),
+        _ => return Err(bad("Invalid request line")),
+    };
+
+    let mut host = None;
+    let mut origin = None;
+    let mut authorization = None;
+    let mut length = None;
+    let mut chunked = false;
+    for headers in 0.. {
+        let line = read_line(reader)
+            .map_err(|_| bad("Could not read the request headers"))?
+            .ok_or_else(too_long)?;
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if headers == MAX_HEADERS {
+            return Err(too_long());
+        }
+
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| bad("Invalid request header"))?;
+        let value = value.trim();
+        match &*name.to_lowercase() {
+            "host" => host = Some(value.to_owned()),
+            "origin" => origin = Some(value.to_owned()),
+            "authorization" => authorization = Some(value.to_owned()),
+            "content-length" => {
+                length = Some(
+                    value
+                        .parse::<usize>()
+                        .map_err(|_| bad("Invalid Content-Length"))?,
+                )
+            }
+            "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+            _ => {}
+        }
+    }
+
+    let too_large = || {
+        Response::new(
+            413,
+            format!("The paste is larger than {} bytes\n", MAX_BODY),
+        )
+    };
+
+    let mut body = Vec::new();
+    if chunked {
+        loop {
+            let size = read_line(reader)
+                .map_err(|_| bad("Could not read the body"))?
+                .ok_or_else(|| bad("Invalid chunk size"))?;
+            // Chunk extensions after a `;` are allowed and meaningless here.
+            let size = size.split(';').next().unwrap_or("").trim();
+            let size = usize::from_str_radix(size, 16).map_err(|_| bad("Invalid chunk size"))?;
+
+            // The size is the client's to choose, so adding it could overflow.
+            if body
+                .len()
+                .checked_add(size)
+                .filter(|&n| n <= MAX_BODY)
+                .is_none()
+            {
+                return Err(too_large());
+            }
+
+            let mut chunk = vec![0; size + 2];
+            reader
+                .read_exact(&mut chunk)
+                .map_err(|_| bad("Could not read the body"))?;
+
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    } else if let Some(length) = length {
+        if length > MAX_BODY {
+            return Err(too_large());
+        }
+        body.resize(length, 0);
+        reader
+            .read_exact(&mut body)
+            .map_err(|_| bad("Could not read the body"))?;
+    } else if method == "POST" {
+        return Err(Response::new(411, "Content-Length is required\n"));
+    }
+
+    Ok(Request {
+        method,
+        target,
+        host,
+        origin,
+        authorization,
+        body,
+    })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read(request: &str) -> Result<Request, Response> {
+        read_request(&mut request.as_bytes())
+    }
+
+    #[test]
+    fn read_requests() {
+        let request = read(
+            "POST /?title=x HTTP/1.1\r\nHost: localhost:8899\r\nAuthorization: Bearer s3cret\r\n\
+             Content-Length: 5\r\n\r\nhello",
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.target, "/?title=x");
+        assert_eq!(request.host.as_deref(), Some("localhost:8899"));
+        assert_eq!(request.origin, None);
+        assert_eq!(request.authorization.as_deref(), Some("Bearer s3cret"));
+        assert_eq!(request.body, b"hello");
+
+        let request = read(
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nhel\r\n2;x=y\r\nlo\r\n\
+             0\r\n\r\n",
+        )
+        .ok()
+        .unwrap();
+        assert_eq!(request.body, b"hello");
+
+        let status = |request| read(request).err().unwrap().status;
+        assert_eq!(status("POST / HTTP/1.1\r\n\r\n"), 411);
+        assert_eq!(
+            status("POST / HTTP/1.1\r\nContent-Length: 999999999\r\n\r\n"),
+            413
+        );
+        assert_eq!(status("nonsense\r\n\r\n"), 400);
+        assert_eq!(
+            status(
+                "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n1\r\na\r\n\
+                 ffffffffffffffff\r\n"
+            ),
+            413
+        );
+
+        let long = format!("POST / HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(MAX_LINE));
+        assert_eq!(status(&long), 431);
+        let many = format!(
+            "POST / HTTP/1.1\r\n{}\r\n",
+            "X: a\r\n".repeat(MAX_HEADERS + 1)
+        );
+        assert_eq!(status(&many), 431);
+    }
+
+    #[test]
+    fn recognise_loopback_hosts() {
+        for host in [
+            "localhost",
+            "LOCALHOST:8899",
+            "127.0.0.1:8899",
+            "127.1.2.3",
+            "[::1]:8899",
+        ] {
+            assert!(is_loopback(host), "{}", host);
+        }
+        for host in [
+            "attacker.example.com",
+            "localhost.example.com:8899",
+            "10.0.0.1",
+            "::1",
+        ] {
+            assert!(!is_loopback(host), "{}", host);
+        }
+    }
+
+    #[test]
+    fn limit_connections() {
+        let connections = AtomicUsize::new(0);
+        let open = (0..MAX_CONNECTIONS)
+            .map(|_| Connection::open(&connections).unwrap())
+            .collect::<Vec<_>>();
+        assert!(Connection::open(&connections).is_none());
+
+        drop(open);
+        assert_eq!(connections.load(Ordering::SeqCst), 0);
+        assert!(Connection::open(&connections).is_some());
+    }
+
+    #[test]
+    fn compare_tokens() {
+        assert!(same("s3cret", "s3cret"));
+        assert!(!same("s3cres", "s3cret"));
+        assert!(!same("s3cre", "s3cret"));
+    }
+}
@@ -0,0 +1,183 @@
+use std::str::FromStr;
+
+use failure::{format_err, Error};
+use structopt::StructOpt;
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct Init {
+    /// The shell to print the integration for: bash, zsh, or fish.
+    #[structopt(parse(try_from_str))]
+    shell: Shell,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(format_err!(
+                "Unknown shell {}, expected one of bash, zsh, fish",
+                s
+            )),
+        }
+    }
+}
+
+impl Shell {
+    fn script(self) -> &'static str {
+        match self {
+            Shell::Bash => BASH,
+            Shell::Zsh => ZSH,
+            Shell::Fish => FISH,
+        }
+    }
+}
+
+const BASH: &str = r#"# patisserie integration for bash. Add this to ~/.bashrc:
+#
+#     eval "$(patisserie init bash)"
+#
+# patisserie-last runs the previous command again and pastes what it prints, titled with the
+# command. Ctrl-X L does the same from the prompt, and Ctrl-X P pastes the command line that is
+# being edited instead of running it.
+
+_patisserie_paste_history() {
+    local cmd
+    cmd=$(HISTTIMEFORMAT= fc -ln "$1" "$1") || return
+    cmd=${cmd#"${cmd%%[![:space:]]*}"}
+    printf 'Pasting the output of: %s\n' "$cmd" >&2
+    eval "$cmd" 2>&1 | patisserie --title "$cmd"
+}
+
+# The newest history entry is the call to patisserie-last itself.
+patisserie-last() {
+    _patisserie_paste_history -2
+}
+
+_patisserie_paste_line() {
+    [[ -n $READLINE_LINE ]] || return
+    printf '%s\n' "$READLINE_LINE" | patisserie --lang bash --title 'Command line'
+}
+
+if [[ $- == *i* ]]; then
+    bind -x '"\C-xl": _patisserie_paste_history -1'
+    bind -x '"\C-xp": _patisserie_paste_line'
+fi
+"#;
+
+const ZSH: &str = r#"# patisserie integration for zsh. Add this to ~/.zshrc:
+#
+#     eval "$(patisserie init zsh)"
+#
+# patisserie-last runs the previous command again and pastes what it prints, titled with the
+# command. Ctrl-X L does the same from the prompt, and Ctrl-X P pastes the command line that is
+# being edited instead of running it.
+
+_patisserie_paste_history() {
+    local cmd
+    cmd=$(fc -ln "$1" "$1") || return
+    print -r -- "Pasting the output of: $cmd" >&2
+    eval "$cmd" 2>&1 | patisserie --title "$cmd"
+}
+
+# The newest history entry is the call to patisserie-last itself.
+patisserie-last() {
+    _patisserie_paste_history -2
+}
+
+_patisserie-last-widget() {
+    zle -I
+    _patisserie_paste_history -1
+}
+
+_patisserie-line-widget() {
+    [[ -n $BUFFER ]] || return
+    zle -I
+    print -r -- "$BUFFER" | patisserie --lang bash --title 'Command line'
+}
+
+zle -N _patisserie-last-widget
+zle -N _patisserie-line-widget
+bindkey '^Xl' _patisserie-last-widget
+bindkey '^Xp' _patisserie-line-widget
+"#;
+
+const FISH: &str = r#"# patisserie integration for fish. Add this to ~/.config/fish/config.fish:
+#
+#     patisserie init fish | source
+#
+# patisserie-last runs the previous command again and pastes what it prints, titled with the
+# command. Ctrl-X L does the same from the prompt, and Ctrl-X P pastes the command line that is
+# being edited instead of running it.
+
+function patisserie-last --description 'Run the previous command again and paste its output'
+    set -l cmd $history[1]
+    or return
+    printf 'Pasting the output of: %s\n' $cmd >&2
+    eval $cmd 2>&1 | patisserie --title $cmd
+end
+
+function __patisserie_paste_line
+    set -l line (commandline)
+    test -n "$line"
+    or return
+    echo
+    printf '%s\n' $line | patisserie --title 'Command line'
+    commandline -f repaint
+end
+
+bind \cxl 'echo; patisserie-last; commandline -f repaint'
+bind \cxp __patisserie_paste_line
+"#;
+
+impl Init {
+    pub fn run(&self) -> Result<(), Error> {
+        print!("{}", self.shell.script());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::process::Command;
+
+    use super::*;
+
+    #[test]
+    fn check_scripts() {
+        for (shell, program) in [
+            (Shell::Bash, "bash"),
+            (Shell::Zsh, "zsh"),
+            (Shell::Fish, "fish"),
+        ] {
+            let script = shell.script();
+            assert!(script.contains(&format!("patisserie init {}", program)));
+            assert!(script.contains("patisserie-last"));
+
+            // Parse the script with the shell itself, where it is installed.
+            if let Ok(output) = Command::new(program)
+                .arg("-n")
+                .arg("-c")
+                .arg(script)
+                .output()
+            {
+                assert!(
+                    output.status.success(),
+                    "{} -n failed: {}",
+                    program,
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::str::FromStr;
+
+use failure::{format_err, Error};
+use reqwest::{Client, Url};
+use url::form_urlencoded;
+
+use crate::describe_request_error;
+use crate::har::{self, Captured, Har};
+
+/// The fields that JSON responses from shorteners commonly put the short link in.
+const JSON_FIELDS: &[&str] = &["short_url", "shorturl", "shortUrl", "link", "url"];
+
+/// A service that turns the URL of a paste into a short link, for --shorten.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shortener {
+    IsGd,
+    VGd,
+    TinyUrl,
+    /// A URL to GET, with `{url}` standing for the URL of the paste.
+    Get(String),
+    /// A URL to POST the URL of the paste to, as the `url` field of a form.
+    Post(String),
+}
+
+impl FromStr for Shortener {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let template = |template: &str| {
+            Url::parse(&template.replace("{url}", "x"))
+                .map(|_| template.to_owned())
+                .map_err(|e| format_err!("Invalid shortener URL {}: {}", template, e))
+        };
+
+        match s {
+            "is.gd" => Ok(Shortener::IsGd),
+            "v.gd" => Ok(Shortener::VGd),
+            "tinyurl" => Ok(Shortener::TinyUrl),
+            s => match s.strip_prefix("POST ") {
+                Some(url) => template(url.trim()).map(Shortener::Post),
+                None if s.contains("{url}") => template(s).map(Shortener::Get),
+                None => Err(format_err!(
+                    "Unknown shortener {}, expected is.gd, v.gd, tinyurl, a URL containing \
+                     {{url}}, or POST followed by a URL",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl Shortener {
+    /// Shorten the URL of a paste.
+    ///
+    /// The fragment of the URL, which holds the key of a paste that is encrypted in the client,
+    /// as on PrivateBin, is never sent to the shortener. It is put on the short link instead,
+    /// since browsers keep it when the shortener redirects them.
+    pub fn shorten(&self, client: &Client, har: Option<&Har>, url: &str) -> Result<String, Error> {
+        let (url, fragment) = url.split_at(url.find('#').unwrap_or(url.len()));
+
+        let template = match self {
+            Shortener::IsGd => "https://is.gd/create.php?format=simple&url={url}",
+            Shortener::VGd => "https://v.gd/create.php?format=simple&url={url}",
+            Shortener::TinyUrl => "https://tinyurl.com/api-create.php?url={url}",
+            Shortener::Get(template) | Shortener::Post(template) => template,
+        };
+        let encoded = form_urlencoded::byte_serialize(url.as_bytes()).collect::<String>();
+        let target = Url::parse(&template.replace("{url}", &encoded))?;
+
+        let (request, body) = match self {
+            // The form is encoded the same way as the URL in the template.
+            Shortener::Post(_) => (
+                client.post(target).form(&[("url", url)]),
+                Some(format!("url={}", encoded)),
+            ),
+            _ => (client.get(target), None),
+        };
+
+        let body = body
+            .filter(|_| har.is_some())
+            .map(|body| Captured::bytes(body.as_bytes()));
+        let mut rsp = har::send(har, client, request, body)
+            .map_err(|e| format_err!("{}", describe_request_error(&e)))?;
+        let text = rsp
+            .text()
+            .map_err(|e| format_err!("{}", describe_request_error(&e)))?;
+
+        if !rsp.status().is_success() {
+            return Err(format_err!("the shortener responded with {}", rsp.status()));
+        }
+
+        short_link(&text)
+            .map(|link| link + fragment)
+            .ok_or_else(|| format_err!("the shortener responded with {}", text.trim()))
+    }
+}
+
+/// Find the short link in a response, which is either the link itself or a JSON object.
+fn short_link(text: &str) -> Option<String> {
+    let link = |text: &str| {
+        Url::parse(text)
+            .ok()
+            .filter(|url| url.scheme() == "http" || url.scheme() == "https")
+            .map(Url::into_string)
+    };
+
+    let text = text.trim();
+    if let Some(link) = link(text) {
+        return Some(link);
+    }
+
+    let object = serde_json::from_str::<serde_json::Value>(text).ok()?;
+    JSON_FIELDS
+        .iter()
+        .filter_map(|field| object.get(field)?.as_str())
+        .find_map(link)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_shorteners() {
+        assert_eq!("is.gd".parse::<Shortener>().unwrap(), Shortener::IsGd);
+        assert_eq!(
+            "https://s.example/new?u={url}"
+                .parse::<Shortener>()
+                .unwrap(),
+            Shortener::Get("https://s.example/new?u={url}".into())
+        );
+        assert_eq!(
+            "POST https://s.example/new".parse::<Shortener>().unwrap(),
+            Shortener::Post("https://s.example/new".into())
+        );
+        assert!("https://s.example/new".parse::<Shortener>().is_err());
+        assert!("POST not a url".parse::<Shortener>().is_err());
+    }
+
+    #[test]
+    fn find_short_links() {
+        assert_eq!(
+            short_link("https://is.gd/abc\n").as_deref(),
+            Some("https://is.gd/abc")
+        );
+        assert_eq!(
+            short_link(r#"{"status": "ok", "short_url": "https://s.example/x"}"#).as_deref(),
+            Some("https://s.example/x")
+        );
+        assert_eq!(short_link("Error: Please enter a valid URL"), None);
+    }
+}
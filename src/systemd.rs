@@ -0,0 +1,193 @@
+//! Socket activation and idle shutdown, so that systemd can start the daemon and the HTTP
+//! gateway when the first request arrives and they can exit again once unused.
+
+use std::env;
+#[cfg(all(unix, feature = "daemon"))]
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+#[cfg(all(unix, feature = "daemon"))]
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use failure::{err_msg, format_err, Error};
+
+#[cfg(all(unix, feature = "daemon"))]
+use crate::config;
+
+/// The first file descriptor that systemd passes, as defined by sd_listen_fds(3).
+#[cfg(unix)]
+const LISTEN_FDS_START: RawFd = 3;
+
+/// The socket that systemd passed to this process, if it was socket activated.
+///
+/// Like sd_listen_fds(3), this unsets the variables that say so, so that they are not inherited
+/// by the processes that patisserie runs.
+#[cfg(unix)]
+pub fn listen_fd() -> Result<Option<RawFd>, Error> {
+    let pid = env::var("LISTEN_PID").ok();
+    let fds = env::var("LISTEN_FDS").ok();
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    // The variables may have been meant for a parent process.
+    if pid.and_then(|pid| pid.parse::<u32>().ok()) != Some(std::process::id()) {
+        return Ok(None);
+    }
+
+    match fds.as_deref().map(str::parse::<u32>) {
+        None | Some(Ok(0)) => Ok(None),
+        Some(Ok(1)) => Ok(Some(LISTEN_FDS_START)),
+        Some(Ok(n)) => Err(format_err!(
+            "systemd passed {} sockets, but patisserie listens on one",
+            n
+        )),
+        Some(Err(_)) => Err(err_msg("systemd passed an invalid LISTEN_FDS")),
+    }
+}
+
+/// Tracks the requests in flight, so that a server can exit once it has been idle for a while.
+pub struct Idle {
+    timeout: Duration,
+    /// The requests in flight, and when the last one finished.
+    state: Mutex<(usize, Instant)>,
+}
+
+/// A request in flight, which keeps the server from exiting until it is dropped.
+pub struct Busy<'a>(&'a Idle);
+
+impl Idle {
+    pub fn new(timeout: Duration) -> Self {
+        Idle {
+            timeout,
+            state: Mutex::new((0, Instant::now())),
+        }
+    }
+
+    pub fn busy(&self) -> Busy<'_> {
+        self.state.lock().unwrap().0 += 1;
+        Busy(self)
+    }
+
+    /// Exit the process once nothing has been in flight for the timeout.
+    ///
+    /// When the server was socket activated, connections that arrive while it exits wait on the
+    /// socket, where they make systemd start it again.
+    pub fn exit_when_idle(&self) -> ! {
+        loop {
+            let wait = {
+                let state = self.state.lock().unwrap();
+                match *state {
+                    (0, since) if since.elapsed() >= self.timeout => exit(0),
+                    (0, since) => self.timeout - since.elapsed(),
+                    _ => self.timeout,
+                }
+            };
+            thread::sleep(wait);
+        }
+    }
+}
+
+impl Drop for Busy<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap();
+        *state = (state.0 - 1, Instant::now());
+    }
+}
+
+/// Where the user's systemd units go: $XDG_CONFIG_HOME/systemd/user.
+#[cfg(all(unix, feature = "daemon"))]
+pub fn user_unit_dir() -> Result<PathBuf, Error> {
+    let config =
+        config::dir().ok_or_else(|| err_msg("Could not find your configuration directory"))?;
+
+    Ok(config.join("systemd/user"))
+}
+
+/// The units that start the daemon when something connects to its socket.
+///
+/// The daemon reads the API key from patisserie/daemon.env in the configuration directory, as
+/// `PASTERY_API_KEY=...`, so that it does not end up in the unit.
+#[cfg(all(unix, feature = "daemon"))]
+pub fn daemon_units(exe: &Path, idle_timeout: Duration) -> [(&'static str, String); 2] {
+    let socket = "\
+[Unit]
+Description=patisserie paste daemon socket
+
+[Socket]
+ListenStream=%t/patisserie.sock
+SocketMode=0600
+
+[Install]
+WantedBy=sockets.target
+"
+    .to_owned();
+
+    let service = format!(
+        "\
+[Unit]
+Description=patisserie paste daemon
+Requires=patisserie.socket
+
+[Service]
+# Options such as --duration go before `daemon`.
+ExecStart={} daemon --idle-timeout {}
+EnvironmentFile=-%E/patisserie/daemon.env
+",
+        exe.display(),
+        idle_timeout.as_secs()
+    );
+
+    [
+        ("patisserie.socket", socket),
+        ("patisserie.service", service),
+    ]
+}
+
+/// Write the daemon's units into `dir`.
+#[cfg(all(unix, feature = "daemon"))]
+pub fn install(dir: &Path, exe: &Path, idle_timeout: Duration) -> Result<Vec<PathBuf>, Error> {
+    fs::create_dir_all(dir)
+        .map_err(|e| format_err!("Could not create {}: {}", dir.display(), e))?;
+
+    daemon_units(exe, idle_timeout)
+        .iter()
+        .map(|(name, contents)| {
+            let path = dir.join(name);
+            fs::write(&path, contents)
+                .map_err(|e| format_err!("Could not write {}: {}", path.display(), e))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exit_only_when_idle() {
+        let idle = Idle::new(Duration::from_secs(60));
+        {
+            let _first = idle.busy();
+            let _second = idle.busy();
+            assert_eq!(idle.state.lock().unwrap().0, 2);
+        }
+        assert_eq!(idle.state.lock().unwrap().0, 0);
+    }
+
+    #[cfg(all(unix, feature = "daemon"))]
+    #[test]
+    fn write_units() {
+        let [(socket_name, socket), (service_name, service)] =
+            daemon_units(Path::new("/usr/bin/patisserie"), Duration::from_secs(600));
+        assert_eq!(socket_name, "patisserie.socket");
+        assert!(socket.contains("ListenStream=%t/patisserie.sock\n"));
+        assert_eq!(service_name, "patisserie.service");
+        assert!(service.contains("ExecStart=/usr/bin/patisserie daemon --idle-timeout 600\n"));
+    }
+}
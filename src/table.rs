@@ -0,0 +1,193 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use failure::{format_err, Error};
+
+use crate::{parse_lang, Converted};
+
+/// How --table lays out CSV or TSV.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+    /// Columns padded with spaces, with a rule under the header.
+    Text,
+    /// A Markdown table.
+    Markdown,
+}
+
+impl FromStr for Style {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "text" => Ok(Style::Text),
+            "markdown" => Ok(Style::Markdown),
+            _ => Err(format_err!(
+                "Unknown table style {}, expected one of text, markdown",
+                s
+            )),
+        }
+    }
+}
+
+/// The delimiter of the table: a tab for a .tsv file, a comma for a .csv file, and otherwise
+/// whichever of the two appears more often in the first line.
+fn delimiter(text: &str, path: Option<&Path>) -> u8 {
+    let extension = path
+        .and_then(Path::extension)
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+
+    match extension.as_deref() {
+        Some("tsv") | Some("tab") => b'\t',
+        Some("csv") => b',',
+        _ => {
+            let first = text.lines().next().unwrap_or("");
+            if first.matches('\t').count() > first.matches(',').count() {
+                b'\t'
+            } else {
+                b','
+            }
+        }
+    }
+}
+
+/// Convert CSV or TSV to an aligned table. The first row is taken to be the header, and at most
+/// `max_rows` rows follow it.
+pub fn convert(
+    text: &str,
+    style: Style,
+    path: Option<&Path>,
+    max_rows: Option<usize>,
+) -> Result<Converted, Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter(text, path))
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format_err!("This is not a valid table: {}", e))?;
+        rows.push(record.iter().map(|cell| cell_text(cell, style)).collect());
+    }
+
+    let body_rows = rows.len().saturating_sub(1);
+    let omitted = match max_rows {
+        Some(max) if body_rows > max => {
+            rows.truncate(max + 1);
+            body_rows - max
+        }
+        _ => 0,
+    };
+
+    let mut table = layout(&rows, style);
+    if omitted > 0 {
+        let rows = if omitted == 1 { "row" } else { "rows" };
+        table.push_str(&format!("\n… {} more {}\n", omitted, rows));
+    }
+
+    Ok(Converted {
+        body: table,
+        lang: parse_lang(match style {
+            Style::Text => "text",
+            Style::Markdown => "markdown",
+        }),
+    })
+}
+
+/// A cell on a single line, with pipes escaped for Markdown.
+fn cell_text(cell: &str, style: Style) -> String {
+    let cell = cell.trim().replace("\r\n", " ").replace('\n', " ");
+
+    match style {
+        Style::Text => cell,
+        Style::Markdown => cell.replace('|', "\\|"),
+    }
+}
+
+/// Lay out rows of cells as a table, with the first row as the header.
+pub fn layout(rows: &[Vec<String>], style: Style) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let mut widths = vec![0; columns];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    if style == Style::Markdown {
+        // A Markdown rule needs at least three dashes.
+        for width in &mut widths {
+            *width = (*width).max(3);
+        }
+    }
+
+    let line = |cells: Vec<&str>| {
+        let padded = widths
+            .iter()
+            .enumerate()
+            .map(|(i, &width)| {
+                let cell = cells.get(i).copied().unwrap_or("");
+                format!("{}{}", cell, " ".repeat(width - cell.chars().count()))
+            })
+            .collect::<Vec<_>>();
+
+        match style {
+            Style::Text => format!("{}\n", padded.join("  ").trim_end()),
+            Style::Markdown => format!("| {} |\n", padded.join(" | ")),
+        }
+    };
+
+    let mut table = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        table.push_str(&line(row.iter().map(String::as_str).collect()));
+
+        if i == 0 {
+            let rules = widths
+                .iter()
+                .map(|&width| "-".repeat(width))
+                .collect::<Vec<_>>();
+            let rule = line(rules.iter().map(String::as_str).collect());
+            table.push_str(&rule);
+        }
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn convert_tables() {
+        let csv = "name,count\nwidgets,3\n\"gadgets, large\",12\nthings|stuff,1\n";
+
+        assert_eq!(
+            convert(csv, Style::Text, None, None).unwrap().body,
+            "name            count\n\
+             --------------  -----\n\
+             widgets         3\n\
+             gadgets, large  12\n\
+             things|stuff    1\n"
+        );
+        assert_eq!(
+            convert(csv, Style::Markdown, None, Some(2)).unwrap().body,
+            "| name           | count |\n\
+             | -------------- | ----- |\n\
+             | widgets        | 3     |\n\
+             | gadgets, large | 12    |\n\
+             \n… 1 more row\n"
+        );
+
+        let tsv = "a\tb,c\td\n1\t2\t3\n";
+        assert_eq!(
+            convert(tsv, Style::Text, None, None).unwrap().body,
+            "a  b,c  d\n-  ---  -\n1  2    3\n"
+        );
+        assert_eq!(
+            convert("x,y\n1,2\n", Style::Text, Some(Path::new("data.tsv")), None)
+                .unwrap()
+                .body,
+            "x,y\n---\n1,2\n"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::io::{self, Read};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use failure::{format_err, Error};
+
+/// A transfer rate in bytes per second, e.g. `500k` or `2M`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rate(u64);
+
+impl FromStr for Rate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let invalid = || {
+            format_err!(
+                "Invalid rate {}, expected a number of bytes per second optionally followed by k, \
+                 M, or G",
+                s
+            )
+        };
+
+        let (amount, multiplier) = match s.char_indices().last() {
+            Some((i, 'k')) | Some((i, 'K')) => (&s[..i], 1 << 10),
+            Some((i, 'm')) | Some((i, 'M')) => (&s[..i], 1 << 20),
+            Some((i, 'g')) | Some((i, 'G')) => (&s[..i], 1 << 30),
+            _ => (s, 1),
+        };
+
+        match amount
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(multiplier))
+        {
+            Some(0) | None => Err(invalid()),
+            Some(rate) => Ok(Rate(rate)),
+        }
+    }
+}
+
+/// Limits the combined rate of every reader that shares it.
+#[derive(Debug)]
+pub struct Limiter {
+    rate: u64,
+    /// When the first byte was read, and how many bytes have been read since.
+    state: Mutex<(Option<Instant>, u64)>,
+}
+
+impl Limiter {
+    pub fn new(rate: Rate) -> Arc<Self> {
+        Arc::new(Limiter {
+            rate: rate.0,
+            state: Mutex::new((None, 0)),
+        })
+    }
+
+    /// Wrap a reader so that it is read no faster than the limit allows.
+    pub fn throttle<R: Read>(self: &Arc<Self>, inner: R) -> Throttled<R> {
+        Throttled {
+            inner,
+            limiter: self.clone(),
+        }
+    }
+
+    /// How long to wait until the bytes read so far are within the limit.
+    fn delay(&self) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let start = *state.0.get_or_insert_with(Instant::now);
+        let due = Duration::from_secs_f64(state.1 as f64 / self.rate as f64);
+
+        due.saturating_sub(start.elapsed())
+    }
+
+    fn record(&self, n: usize) {
+        self.state.lock().unwrap().1 += n as u64;
+    }
+}
+
+/// A reader throttled by a `Limiter`.
+pub struct Throttled<R> {
+    inner: R,
+    limiter: Arc<Limiter>,
+}
+
+impl<R: Read> Read for Throttled<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        thread::sleep(self.limiter.delay());
+
+        // Read at most a tenth of a second's worth at a time so that the rate stays smooth.
+        let len = buf.len().min((self.limiter.rate / 10).max(1) as usize);
+        let n = self.inner.read(&mut buf[..len])?;
+        self.limiter.record(n);
+
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_rates() {
+        assert_eq!("500".parse::<Rate>().unwrap(), Rate(500));
+        assert_eq!("500k".parse::<Rate>().unwrap(), Rate(500 * 1024));
+        assert_eq!("2M".parse::<Rate>().unwrap(), Rate(2 * 1024 * 1024));
+        assert_eq!("1g".parse::<Rate>().unwrap(), Rate(1024 * 1024 * 1024));
+
+        assert!("".parse::<Rate>().is_err());
+        assert!("0k".parse::<Rate>().is_err());
+        assert!("k".parse::<Rate>().is_err());
+        assert!("1.5M".parse::<Rate>().is_err());
+        assert!("10kb".parse::<Rate>().is_err());
+        assert!("99999999999999999999G".parse::<Rate>().is_err());
+    }
+
+    #[test]
+    fn throttle_readers() {
+        let limiter = Limiter::new(Rate(1000));
+        let input = vec![b'x'; 250];
+        let start = Instant::now();
+
+        let mut output = Vec::new();
+        limiter
+            .throttle(&input[..])
+            .read_to_end(&mut output)
+            .unwrap();
+
+        assert_eq!(output, input);
+        // The first 100 bytes are read immediately, and each 100 after that is spaced out.
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,178 @@
+//! Titles for pastes that have no other, and what can be added to the titles of files.
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{DateTime, Local};
+use failure::{format_err, Error};
+
+use crate::git;
+
+/// Inferred titles are cut off after this many characters.
+const MAX_LEN: usize = 80;
+
+/// How many lines are looked at for a heading or comment.
+const MAX_LINES: usize = 50;
+
+/// Markers that start a line comment, in the languages that pastes are most often in.
+const COMMENTS: &[&str] = &["///", "//!", "//", "--", ";;", "#", "/*", "<!--", "*"];
+
+/// A title for `text`: its first Markdown heading, its first comment, or its first line.
+///
+/// Shebangs and comments made only of punctuation, such as a row of `=`, are skipped over.
+pub fn from_content(text: &str) -> Option<String> {
+    let lines = || {
+        text.lines()
+            .take(MAX_LINES)
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("#!"))
+    };
+
+    let heading = || {
+        lines().find_map(|line| {
+            let rest = line.trim_start_matches('#');
+            let level = line.len() - rest.len();
+            let heading = rest.trim_end_matches('#');
+            if (1..=6).contains(&level)
+                && rest.starts_with(char::is_whitespace)
+                && heading.chars().any(char::is_alphanumeric)
+            {
+                Some(heading)
+            } else {
+                None
+            }
+        })
+    };
+    let comment = || lines().find_map(comment_text);
+
+    heading()
+        .or_else(comment)
+        .or_else(|| lines().next())
+        .map(clean)
+        .filter(|title| title.chars().any(char::is_alphanumeric))
+}
+
+/// The text of a comment line, without its markers.
+fn comment_text(line: &str) -> Option<&str> {
+    let marker = COMMENTS.iter().find(|marker| line.starts_with(*marker))?;
+    let rest = &line[marker.len()..];
+
+    // `#include`, `#[derive]`, `**bold**`, and `-->` are not comments.
+    if ["#", "*", "--", ";;"].contains(marker) && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let rest = rest.trim_end_matches("*/").trim_end_matches("-->").trim();
+    if rest.chars().any(char::is_alphanumeric) {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+/// Something about a file that --title-meta adds to its title.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Meta {
+    /// When the file was last modified.
+    Mtime,
+    /// The last commit that changed the file, if it is in a git repository.
+    Commit,
+}
+
+impl FromStr for Meta {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "mtime" => Ok(Meta::Mtime),
+            "commit" => Ok(Meta::Commit),
+            _ => Err(format_err!(
+                "Unknown title metadata {}, expected one of mtime, commit",
+                s
+            )),
+        }
+    }
+}
+
+/// `title` with the --title-meta of the file at `path` after it in parentheses, such as
+/// `nginx.conf (modified 2026-10-14 09:30, commit 1a2b3c4)`.
+///
+/// Metadata that the file does not have, such as the commit of a file outside a repository,
+/// is left out.
+pub fn with_meta(title: &str, path: &Path, meta: &[Meta]) -> String {
+    let parts = meta
+        .iter()
+        .filter_map(|meta| match meta {
+            Meta::Mtime => {
+                let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+                let modified = DateTime::<Local>::from(modified.ok()?);
+                Some(format!("modified {}", modified.format("%Y-%m-%d %H:%M")))
+            }
+            Meta::Commit => git::last_commit(path).map(|rev| format!("commit {}", rev)),
+        })
+        .collect::<Vec<_>>();
+
+    if parts.is_empty() {
+        title.to_owned()
+    } else {
+        format!("{} ({})", title, parts.join(", "))
+    }
+}
+
+/// Collapse runs of whitespace and cut the title down to `MAX_LEN` characters.
+fn clean(title: &str) -> String {
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if title.chars().count() <= MAX_LEN {
+        return title;
+    }
+
+    let cut = title.chars().take(MAX_LEN - 1).collect::<String>();
+    format!("{}…", cut.trim_end())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn infer_titles() {
+        assert_eq!(
+            from_content("Some notes\n\n## Design  ##\n\nText").as_deref(),
+            Some("Design")
+        );
+        assert_eq!(
+            from_content("#!/bin/sh\n# ======\n# Deploy the   site\nset -e\n").as_deref(),
+            Some("Deploy the site")
+        );
+        assert_eq!(
+            from_content("#include <stdio.h>\n/* Print a greeting. */\nint main;").as_deref(),
+            Some("Print a greeting.")
+        );
+        assert_eq!(
+            from_content("\n\nerror[E0308]: mismatched types\n  --> src/main.rs").as_deref(),
+            Some("error[E0308]: mismatched types")
+        );
+        assert_eq!(
+            from_content(&"x".repeat(200)).unwrap(),
+            format!("{}…", "x".repeat(MAX_LEN - 1))
+        );
+        assert_eq!(from_content("\n  \n"), None);
+        assert_eq!(from_content("----\n"), None);
+    }
+
+    #[test]
+    fn add_metadata() {
+        let path = std::env::temp_dir().join(format!("patisserie-meta-{}", std::process::id()));
+        fs::write(&path, "").unwrap();
+        let today = Local::now().format("%Y-%m-%d ").to_string();
+
+        let title = with_meta("app.conf", &path, &[Meta::Mtime, Meta::Commit]);
+        assert!(title.starts_with(&format!("app.conf (modified {}", today)));
+        assert!(!title.contains("commit"));
+        assert_eq!(with_meta("app.conf", &path, &[Meta::Commit]), "app.conf");
+
+        fs::remove_file(path).unwrap();
+    }
+}
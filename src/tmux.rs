@@ -0,0 +1,86 @@
+use std::process::Command;
+
+use failure::{format_err, Error};
+use structopt::StructOpt;
+
+use crate::capture;
+use crate::{Input, Paste, Uploader};
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct Tmux {
+    /// The pane to capture, in any form tmux accepts as a target (e.g. `%3` or `work:1.0`).
+    ///
+    /// If not provided, the current pane is captured.
+    #[structopt(long = "pane")]
+    pane: Option<String>,
+
+    /// Include this many lines of scrollback history above the visible contents.
+    #[structopt(long = "history")]
+    history: Option<u32>,
+}
+
+impl Tmux {
+    /// Capture the pane and upload its contents.
+    pub fn run(&self, uploader: &Uploader) -> Result<Paste, Error> {
+        let mut args = vec!["capture-pane".to_owned(), "-p".to_owned(), "-J".to_owned()];
+        if let Some(history) = self.history {
+            args.push("-S".into());
+            args.push(format!("-{}", history));
+        }
+        args.extend(self.target_args());
+
+        let contents = tmux(&args)?;
+
+        let mut describe = vec!["display-message".to_owned(), "-p".to_owned()];
+        describe.extend(self.target_args());
+        describe.push("#S:#I.#P #{pane_current_command}".into());
+
+        let title = format!("tmux {}", tmux(&describe)?.trim());
+
+        uploader.upload(Input {
+            body: trim_trailing_blank_lines(&capture::clean_terminal_output(&contents)),
+            title: Some(title),
+            lang: Some(crate::parse_lang("text")),
+        })
+    }
+
+    fn target_args(&self) -> Vec<String> {
+        match self.pane {
+            Some(ref pane) => vec!["-t".into(), pane.clone()],
+            None => vec![],
+        }
+    }
+}
+
+fn tmux(args: &[String]) -> Result<String, Error> {
+    let (output, status) = capture::run(Command::new("tmux").args(args))?;
+
+    if !status.success() {
+        return Err(format_err!("tmux {} failed: {}", args[0], output.trim()));
+    }
+
+    Ok(output)
+}
+
+/// Remove the empty lines that pad out the bottom of a pane that isn't full.
+fn trim_trailing_blank_lines(text: &str) -> String {
+    let mut trimmed = text.trim_end().to_owned();
+    if !trimmed.is_empty() {
+        trimmed.push('\n');
+    }
+    trimmed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn trim_padding() {
+        assert_eq!(
+            trim_trailing_blank_lines("$ ls\nfoo bar\n$ \n\n\n\n"),
+            "$ ls\nfoo bar\n$\n"
+        );
+        assert_eq!(trim_trailing_blank_lines("\n\n\n"), "");
+    }
+}
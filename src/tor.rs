@@ -0,0 +1,53 @@
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+use failure::{err_msg, Error};
+use reqwest::Proxy;
+
+/// The ports used by the Tor daemon and the Tor Browser bundle, in order of preference.
+const TOR_PORTS: &[u16] = &[9050, 9150];
+
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Find a local Tor SOCKS port that is accepting connections.
+pub fn detect_socks_addr() -> Option<SocketAddr> {
+    TOR_PORTS
+        .iter()
+        .map(|port| SocketAddr::from(([127, 0, 0, 1], *port)))
+        .find(|addr| TcpStream::connect_timeout(addr, CONNECT_TIMEOUT).is_ok())
+}
+
+/// Find the local Tor SOCKS proxy, refusing to continue if there isn't one.
+pub fn socks_addr() -> Result<SocketAddr, Error> {
+    detect_socks_addr().ok_or_else(|| {
+        err_msg(
+            "Could not find a Tor SOCKS proxy on port 9050 or 9150; \
+             refusing to upload without Tor",
+        )
+    })
+}
+
+/// Build a proxy that sends all traffic, including DNS resolution, through Tor.
+///
+/// Resolving hostnames on the Tor side is required for `.onion` addresses and keeps lookups
+/// from leaking to the local resolver.
+pub fn proxy(addr: SocketAddr) -> Result<Proxy, Error> {
+    Ok(Proxy::all(&socks_url(addr))?)
+}
+
+fn socks_url(addr: SocketAddr) -> String {
+    format!("socks5h://{}", addr)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn socks_urls() {
+        assert_eq!(
+            socks_url(SocketAddr::from(([127, 0, 0, 1], 9050))),
+            "socks5h://127.0.0.1:9050"
+        );
+    }
+}
@@ -0,0 +1,394 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use failure::{format_err, Error};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+
+use crate::denylist::Denylist;
+use crate::i18n::tr;
+
+/// The file that lists what to leave out of directories, in the same syntax as .gitignore.
+pub const IGNORE_FILE: &str = ".patisserieignore";
+
+/// Build artifacts and dependencies, which are left out of directories even when no ignore file
+/// mentions them.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    ".git/",
+    ".hg/",
+    ".svn/",
+    "target/",
+    "node_modules/",
+    "__pycache__/",
+    "build/",
+    "dist/",
+    "*.o",
+    "*.obj",
+    "*.a",
+    "*.lib",
+    "*.so",
+    "*.dylib",
+    "*.dll",
+    "*.exe",
+    "*.class",
+    "*.jar",
+    "*.pyc",
+];
+
+/// How many bytes are looked at to decide whether a file is binary, as git does.
+const BINARY_SNIFF_LEN: u64 = 8000;
+
+/// What to leave out when walking a directory.
+pub struct Filter<'a> {
+    /// Globs that files have to match, if any are given.
+    pub include: &'a [String],
+    /// Globs for files and directories to leave out, on top of the defaults.
+    pub exclude: &'a [String],
+    /// Files to leave out with a warning, unless --force was given.
+    pub denylist: Option<&'a Denylist>,
+    /// Whether to walk into hidden files and directories.
+    pub hidden: bool,
+    /// Whether to take files that .gitignore, .ignore, and .patisserieignore files leave out.
+    pub no_ignore: bool,
+    /// Whether to follow symbolic links, which are otherwise skipped.
+    pub follow_symlinks: bool,
+    /// How deep to walk, with 1 being the files directly in the directory.
+    pub max_depth: Option<usize>,
+}
+
+impl Filter<'_> {
+    /// The files in a directory, sorted by name.
+    ///
+    /// Directories are walked the way git would: hidden files and anything matched by a
+    /// .gitignore, .ignore, or .patisserieignore file are left out, and so are build artifacts.
+    /// So are files matched by the denylist, with a warning, since a directory can hold
+    /// secrets that nobody meant to share. Symbolic links that lead back to a directory that
+    /// is being walked are skipped with a warning. With `no_ignore`, ignore files are not read,
+    /// but build artifacts are still left out.
+    pub fn files(&self, dir: &Path) -> Result<Vec<PathBuf>, Error> {
+        let include = self.include()?;
+
+        // Overrides turn gitignore syntax around, so excludes are given with a `!`. Includes are
+        // not overrides, since a whitelisted file would be taken however it is ignored.
+        let mut overrides = OverrideBuilder::new(dir);
+        let excludes = DEFAULT_EXCLUDES
+            .iter()
+            .cloned()
+            .chain(self.exclude.iter().map(String::as_str));
+        for glob in excludes {
+            overrides
+                .add(&format!("!{}", glob))
+                .map_err(|e| format_err!("Invalid pattern {}: {}", glob, e))?;
+        }
+
+        let mut walk = WalkBuilder::new(dir);
+        walk.hidden(!self.hidden)
+            .follow_links(self.follow_symlinks)
+            .max_depth(self.max_depth)
+            .require_git(false)
+            .ignore(!self.no_ignore)
+            .git_ignore(!self.no_ignore)
+            .git_global(!self.no_ignore)
+            .git_exclude(!self.no_ignore)
+            .parents(!self.no_ignore)
+            .overrides(overrides.build()?)
+            .sort_by_file_name(|a, b| a.cmp(b));
+        if !self.no_ignore {
+            walk.add_custom_ignore_filename(IGNORE_FILE);
+        }
+        let walk = walk.build();
+
+        let mut files = Vec::new();
+        for entry in walk {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) if is_loop(&e) => {
+                    eprintln!("{}", tr!("warning", message = e.to_string()));
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if !entry.file_type().is_some_and(|kind| kind.is_file()) {
+                continue;
+            }
+
+            let path = entry.into_path();
+            if !include.is_match(&path, dir) {
+                continue;
+            }
+            if let Some(pattern) = self
+                .denylist
+                .and_then(|denylist| denylist.matching_pattern(&path))
+            {
+                let message = tr!(
+                    "sensitive-skipped",
+                    path = path.display().to_string(),
+                    pattern = pattern
+                );
+                eprintln!("{}", tr!("warning", message = message));
+                continue;
+            }
+
+            files.push(path);
+        }
+
+        Ok(files)
+    }
+
+    fn include(&self) -> Result<Include, Error> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for glob in self.include {
+            let invalid = |e| format_err!("Invalid pattern {}: {}", glob, e);
+            if glob.contains('/') {
+                // As in .gitignore, a glob with a slash is relative to the directory, and `*`
+                // stops at slashes.
+                paths.add(
+                    GlobBuilder::new(glob.trim_start_matches('/'))
+                        .literal_separator(true)
+                        .build()
+                        .map_err(invalid)?,
+                );
+            } else {
+                names.add(Glob::new(glob).map_err(invalid)?);
+            }
+        }
+
+        Ok(Include {
+            any: self.include.is_empty(),
+            names: names.build()?,
+            paths: paths.build()?,
+        })
+    }
+}
+
+/// The --include globs, which match either the name of a file or its path in the directory.
+struct Include {
+    /// Whether there are no globs, so that every file is taken.
+    any: bool,
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl Include {
+    fn is_match(&self, path: &Path, dir: &Path) -> bool {
+        self.any
+            || path
+                .file_name()
+                .is_some_and(|name| self.names.is_match(name))
+            || path
+                .strip_prefix(dir)
+                .is_ok_and(|relative| self.paths.is_match(relative))
+    }
+}
+
+fn is_loop(e: &ignore::Error) -> bool {
+    match e {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::WithPath { err, .. } | ignore::Error::WithDepth { err, .. } => is_loop(err),
+        _ => false,
+    }
+}
+
+/// Whether a file looks binary, because it has a NUL byte near the start.
+pub fn is_binary(path: &Path) -> Result<bool, Error> {
+    let mut start = Vec::new();
+    fs::File::open(path)
+        .and_then(|file| file.take(BINARY_SNIFF_LEN).read_to_end(&mut start))
+        .map_err(|e| format_err!("Could not read {}: {}", path.display(), e))?;
+
+    Ok(start.contains(&0))
+}
+
+/// Replace the directories among `paths` with the text files in them, for --recursive.
+pub fn expand(paths: &[PathBuf], filter: &Filter) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+
+        for file in filter.files(path)? {
+            if is_binary(&file)? {
+                let message = tr!("binary-skipped", path = file.display().to_string());
+                eprintln!("{}", tr!("warning", message = message));
+            } else {
+                files.push(file);
+            }
+        }
+    }
+
+    if files.is_empty() {
+        return Err(format_err!(
+            "There is nothing to upload: every file was ignored"
+        ));
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn expand_directories() {
+        let dir = env::temp_dir().join(format!("patisserie-walk-{}", std::process::id()));
+        let project = dir.join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::create_dir_all(project.join("target/debug")).unwrap();
+        fs::create_dir_all(project.join("docs")).unwrap();
+        fs::write(project.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(project.join("src/lib.rs"), "").unwrap();
+        fs::write(project.join("target/debug/app"), "built").unwrap();
+        fs::write(project.join("docs/notes.md"), "notes").unwrap();
+        fs::write(project.join("docs/draft.md"), "draft").unwrap();
+        fs::write(project.join("logo.png"), b"\x89PNG\r\n\x1a\n\0\0").unwrap();
+        fs::write(project.join(".env"), "SECRET=1").unwrap();
+        fs::write(project.join("server.key"), "secret").unwrap();
+        fs::write(project.join(IGNORE_FILE), "draft.md\n").unwrap();
+
+        let denylist = Denylist::new(&[]).unwrap();
+        let paths = [project.clone()];
+        let relative = |files: Vec<PathBuf>| {
+            files
+                .iter()
+                .map(|file| file.strip_prefix(&project).unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+        let filter = Filter {
+            include: &[],
+            exclude: &[],
+            denylist: Some(&denylist),
+            hidden: false,
+            no_ignore: false,
+            follow_symlinks: false,
+            max_depth: None,
+        };
+        assert_eq!(
+            relative(expand(&paths, &filter).unwrap()),
+            [
+                Path::new("docs/notes.md"),
+                Path::new("src/lib.rs"),
+                Path::new("src/main.rs")
+            ]
+        );
+
+        let filter = Filter {
+            include: &["*.rs".into()],
+            exclude: &["lib.rs".into()],
+            denylist: None,
+            hidden: false,
+            no_ignore: false,
+            follow_symlinks: false,
+            max_depth: None,
+        };
+        assert_eq!(
+            relative(expand(&paths, &filter).unwrap()),
+            [Path::new("src/main.rs")]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn include_only_what_would_be_walked() {
+        let dir = env::temp_dir().join(format!("patisserie-include-{}", std::process::id()));
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        fs::write(dir.join(".gitignore"), "secret.txt\n").unwrap();
+        fs::write(dir.join("secret.txt"), "secret").unwrap();
+        fs::write(dir.join(".hidden.txt"), "hidden").unwrap();
+        fs::write(dir.join("readme.txt"), "readme").unwrap();
+        fs::write(dir.join("notes/todo.txt"), "todo").unwrap();
+        fs::write(dir.join("notes/todo.md"), "todo").unwrap();
+
+        let files = |include: &[String]| {
+            let filter = Filter {
+                include,
+                exclude: &[],
+                denylist: None,
+                hidden: false,
+                no_ignore: false,
+                follow_symlinks: false,
+                max_depth: None,
+            };
+            filter
+                .files(&dir)
+                .unwrap()
+                .iter()
+                .map(|file| file.strip_prefix(&dir).unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            files(&["*.txt".into()]),
+            [Path::new("notes/todo.txt"), Path::new("readme.txt")]
+        );
+        assert_eq!(
+            files(&["notes/*".into()]),
+            [Path::new("notes/todo.md"), Path::new("notes/todo.txt")]
+        );
+        assert_eq!(files(&["/*.txt".into()]), [Path::new("readme.txt")]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_hidden_files_and_symbolic_links() {
+        let dir = env::temp_dir().join(format!("patisserie-links-{}", std::process::id()));
+        let logs = dir.join("logs");
+        fs::create_dir_all(logs.join("old")).unwrap();
+        fs::create_dir_all(dir.join("shared")).unwrap();
+        fs::write(logs.join("app.log"), "app").unwrap();
+        fs::write(logs.join(".session.log"), "session").unwrap();
+        fs::write(logs.join("old/app.log"), "old").unwrap();
+        fs::write(dir.join("shared/db.log"), "db").unwrap();
+        std::os::unix::fs::symlink(dir.join("shared"), logs.join("shared")).unwrap();
+        std::os::unix::fs::symlink(&logs, logs.join("old/loop")).unwrap();
+
+        let files = |hidden, follow_symlinks, max_depth| {
+            let filter = Filter {
+                include: &[],
+                exclude: &[],
+                denylist: None,
+                hidden,
+                no_ignore: false,
+                follow_symlinks,
+                max_depth,
+            };
+            filter
+                .files(&logs)
+                .unwrap()
+                .iter()
+                .map(|file| file.strip_prefix(&logs).unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            files(false, false, None),
+            [Path::new("app.log"), Path::new("old/app.log")]
+        );
+        assert_eq!(
+            files(true, false, Some(1)),
+            [Path::new(".session.log"), Path::new("app.log")]
+        );
+        // The link back to logs/ is skipped rather than walked forever.
+        assert_eq!(
+            files(false, true, None),
+            [
+                Path::new("app.log"),
+                Path::new("old/app.log"),
+                Path::new("shared/db.log")
+            ]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use failure::{format_err, Error};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use structopt::StructOpt;
+
+use crate::denylist::Denylist;
+use crate::i18n::tr;
+use crate::{parse_in_range, Paste, Uploader};
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct WatchDir {
+    /// The directory to watch.
+    #[structopt(parse(from_os_str))]
+    dir: PathBuf,
+
+    /// Only upload files whose names match this glob, such as '*.log'.
+    ///
+    /// Can be given more than once. Hidden files are never uploaded.
+    #[structopt(long = "pattern", number_of_values = 1)]
+    patterns: Vec<String>,
+
+    /// How long a file has to stay unchanged before it is uploaded, in milliseconds.
+    ///
+    /// Files are uploaded again once they have been modified and have settled again.
+    #[structopt(
+        long = "settle",
+        default_value = "2000",
+        parse(try_from_str = "parse_millis")
+    )]
+    settle: Duration,
+
+    /// How often to look for new files, in milliseconds.
+    #[structopt(
+        long = "interval",
+        default_value = "500",
+        parse(try_from_str = "parse_millis")
+    )]
+    interval: Duration,
+
+    /// Also upload the files that are already in the directory.
+    #[structopt(long = "existing")]
+    existing: bool,
+}
+
+fn parse_millis(s: &str) -> Result<Duration, Error> {
+    parse_in_range(s, 50, 600_000).map(|ms| Duration::from_millis(ms.into()))
+}
+
+/// The size and modification time of a file, which change when it is written to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Stamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+struct Seen {
+    stamp: Stamp,
+    /// When the stamp last changed.
+    changed: Instant,
+    /// Whether this version of the file has been dealt with.
+    done: bool,
+}
+
+/// Decides which files have settled and are due to be uploaded.
+#[derive(Default)]
+struct Tracker {
+    files: HashMap<PathBuf, Seen>,
+}
+
+impl Tracker {
+    /// Mark files as already dealt with, so that only later changes to them are uploaded.
+    fn skip(&mut self, files: Vec<(PathBuf, Stamp)>, now: Instant) {
+        for (path, stamp) in files {
+            self.files.insert(
+                path,
+                Seen {
+                    stamp,
+                    changed: now,
+                    done: true,
+                },
+            );
+        }
+    }
+
+    /// Record the files that are in the directory now, and return those that have stayed
+    /// unchanged for `settle` since they appeared or were last modified.
+    fn update(
+        &mut self,
+        files: Vec<(PathBuf, Stamp)>,
+        now: Instant,
+        settle: Duration,
+    ) -> Vec<PathBuf> {
+        self.files
+            .retain(|path, _| files.iter().any(|(file, _)| file == path));
+
+        let mut due = Vec::new();
+        for (path, stamp) in files {
+            let seen = self.files.entry(path.clone()).or_insert(Seen {
+                stamp,
+                changed: now,
+                done: false,
+            });
+
+            if seen.stamp != stamp {
+                *seen = Seen {
+                    stamp,
+                    changed: now,
+                    done: false,
+                };
+            }
+
+            if !seen.done && now.duration_since(seen.changed) >= settle {
+                seen.done = true;
+                due.push(path);
+            }
+        }
+
+        due.sort();
+        due
+    }
+}
+
+impl WatchDir {
+    /// Watch the directory and upload each matching file once it has settled, until killed.
+    ///
+    /// Failed uploads are reported and watching carries on; the file is tried again once it
+    /// changes.
+    pub fn run<F>(&self, uploader: &Uploader, mut publish: F) -> Result<(), Error>
+    where
+        F: FnMut(&Path, &Paste) -> Result<(), Error>,
+    {
+        let patterns = self.patterns()?;
+        let denylist = if uploader.options.force {
+            None
+        } else {
+            Some(Denylist::new(&uploader.options.deny)?)
+        };
+
+        let mut tracker = Tracker::default();
+        if !self.existing {
+            tracker.skip(self.scan(&patterns)?, Instant::now());
+        }
+        eprintln!(
+            "{}",
+            tr!("directory-watching", dir = self.dir.display().to_string())
+        );
+
+        loop {
+            for path in tracker.update(self.scan(&patterns)?, Instant::now(), self.settle) {
+                if let Some(pattern) = denylist
+                    .as_ref()
+                    .and_then(|denylist| denylist.matching_pattern(&path))
+                {
+                    let message = tr!(
+                        "sensitive-not-uploaded",
+                        path = path.display().to_string(),
+                        pattern = pattern
+                    );
+                    eprintln!("{}", tr!("warning", message = message));
+                    continue;
+                }
+
+                match uploader.upload_file(&path) {
+                    Ok(paste) => publish(&path, &paste)?,
+                    Err(e) => eprintln!(
+                        "{}",
+                        tr!(
+                            "error-in",
+                            path = path.display().to_string(),
+                            message = e.to_string()
+                        )
+                    ),
+                }
+            }
+
+            thread::sleep(self.interval);
+        }
+    }
+
+    fn patterns(&self) -> Result<Option<GlobSet>, Error> {
+        if self.patterns.is_empty() {
+            return Ok(None);
+        }
+
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &self.patterns {
+            builder.add(
+                Glob::new(pattern)
+                    .map_err(|e| format_err!("Invalid pattern {}: {}", pattern, e))?,
+            );
+        }
+        Ok(Some(builder.build()?))
+    }
+
+    /// The matching files in the directory, which is not descended into.
+    fn scan(&self, patterns: &Option<GlobSet>) -> Result<Vec<(PathBuf, Stamp)>, Error> {
+        let entries = fs::read_dir(&self.dir)
+            .map_err(|e| format_err!("Could not read {}: {}", self.dir.display(), e))?;
+
+        let mut files = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            if name.to_string_lossy().starts_with('.')
+                || !patterns.as_ref().is_none_or(|set| set.is_match(&name))
+            {
+                continue;
+            }
+
+            // Files can disappear between being listed and being looked at.
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_file() {
+                let stamp = Stamp {
+                    len: metadata.len(),
+                    modified: metadata.modified().ok(),
+                };
+                files.push((entry.path(), stamp));
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn upload_files_once_settled() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let settle = Duration::from_millis(1000);
+        let stamp = |len| Stamp {
+            len,
+            modified: None,
+        };
+        let old = PathBuf::from("old.log");
+        let crash = PathBuf::from("crash.log");
+
+        let mut tracker = Tracker::default();
+        tracker.skip(vec![(old.clone(), stamp(10))], at(0));
+
+        // A file that is still being written is held back until it stops changing.
+        let mut files = vec![(old.clone(), stamp(10)), (crash.clone(), stamp(1))];
+        assert!(tracker.update(files.clone(), at(500), settle).is_empty());
+        files[1].1 = stamp(2);
+        assert!(tracker.update(files.clone(), at(1000), settle).is_empty());
+        assert_eq!(
+            tracker.update(files.clone(), at(2000), settle),
+            vec![crash.clone()]
+        );
+        assert!(tracker.update(files.clone(), at(5000), settle).is_empty());
+
+        // Modified files are uploaded again, and so are files that are skipped and then change.
+        files[0].1 = stamp(11);
+        files[1].1 = stamp(3);
+        assert!(tracker.update(files.clone(), at(6000), settle).is_empty());
+        assert_eq!(
+            tracker.update(files.clone(), at(7000), settle),
+            [crash.clone(), old]
+        );
+
+        // A file that is removed and recreated is new again.
+        tracker.update(vec![], at(8000), settle);
+        assert!(tracker
+            .update(vec![(crash.clone(), stamp(3))], at(8000), settle)
+            .is_empty());
+        assert_eq!(
+            tracker.update(vec![(crash.clone(), stamp(3))], at(9000), settle),
+            [crash]
+        );
+    }
+}
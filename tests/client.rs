@@ -0,0 +1,60 @@
+//! Tests of the library clients against a mock of the Pastery API.
+
+#![cfg(any(feature = "async", feature = "blocking"))]
+
+mod common;
+
+use patisserie::client::PasteRequest;
+use reqwest::Url;
+
+use crate::common::{MockServer, Response};
+
+#[cfg(feature = "blocking")]
+#[test]
+fn upload_blocking() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+    let client = patisserie::blocking::Client::new("foo").api_url(Url::parse(&server.url).unwrap());
+
+    let created = client
+        .upload(&PasteRequest::new().language("rust"), "fn main() {}")
+        .unwrap();
+    assert_eq!(created.url, "https://www.pastery.net/abcdef/");
+
+    let uploads = server.uploads();
+    assert_eq!(uploads[0].param("api_key").as_deref(), Some("foo"));
+    assert_eq!(uploads[0].param("language").as_deref(), Some("rust"));
+    assert_eq!(uploads[0].text(), "fn main() {}");
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn upload_async() {
+    use patisserie::client::ApiError;
+    use reqwest::StatusCode;
+
+    let server = MockServer::start(|request| match request.param("api_key").as_deref() {
+        Some("foo") => Response::paste("https://www.pastery.net/abcdef/"),
+        _ => Response::json(401, r#"{"error_msg": "Invalid API key."}"#),
+    });
+    let api_url = Url::parse(&server.url).unwrap();
+
+    let client = patisserie::client::Client::new("foo").api_url(api_url.clone());
+    let created = client
+        .upload(&PasteRequest::new().title("main.rs"), "fn main() {}")
+        .await
+        .unwrap();
+    assert_eq!(created.url, "https://www.pastery.net/abcdef/");
+    assert_eq!(
+        server.uploads()[0].param("title").as_deref(),
+        Some("main.rs")
+    );
+
+    let client = patisserie::client::Client::new("bar").api_url(api_url);
+    assert_eq!(
+        client.upload(&PasteRequest::new(), "").await,
+        Err(ApiError::Rejected {
+            status: StatusCode::UNAUTHORIZED,
+            message: "Invalid API key.".into(),
+        })
+    );
+}
@@ -0,0 +1,246 @@
+//! A minimal HTTP/1.1 server that stands in for the Pastery API.
+
+// Each test crate only uses some of it.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use url::Url;
+
+/// A request received by the server.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub target: String,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// The decoded value of a query parameter.
+    pub fn param(&self, name: &str) -> Option<String> {
+        Url::parse(&format!("http://localhost{}", self.target))
+            .unwrap()
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    }
+
+    pub fn text(&self) -> &str {
+        std::str::from_utf8(&self.body).unwrap()
+    }
+}
+
+/// A response for the server to send.
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+    /// Close the connection instead of responding, as if the response was lost.
+    pub hang_up: bool,
+}
+
+impl Response {
+    pub fn json(status: u16, body: &str) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: body.into(),
+            hang_up: false,
+        }
+    }
+
+    pub fn text(status: u16, body: &str) -> Self {
+        Response {
+            content_type: "text/plain",
+            ..Response::json(status, body)
+        }
+    }
+
+    pub fn html(status: u16, body: &str) -> Self {
+        Response {
+            content_type: "text/html",
+            ..Response::json(status, body)
+        }
+    }
+
+    pub fn redirect(status: u16, location: &str) -> Self {
+        Response {
+            headers: vec![("Location", location.into())],
+            ..Response::html(status, "")
+        }
+    }
+
+    /// No response at all: the connection is closed once the request has been read.
+    pub fn hang_up() -> Self {
+        Response {
+            hang_up: true,
+            ..Response::json(200, "")
+        }
+    }
+
+    /// A successful upload.
+    pub fn paste(url: &str) -> Self {
+        Response::json(200, &format!(r#"{{"url": "{}"}}"#, url))
+    }
+}
+
+type Handler = dyn Fn(&Request) -> Response + Send + Sync;
+
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockServer {
+    /// Start a server that answers every upload with `handler`.
+    ///
+    /// HEAD requests, which patisserie sends to open a connection early, are answered with an
+    /// empty response and are not recorded.
+    pub fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api/paste/", listener.local_addr().unwrap());
+        let handler: Arc<Handler> = Arc::new(handler);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        {
+            let requests = requests.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let handler = handler.clone();
+                    let requests = requests.clone();
+                    thread::spawn(move || serve(stream, &*handler, &requests));
+                }
+            });
+        }
+
+        MockServer { url, requests }
+    }
+
+    /// The uploads received so far.
+    pub fn uploads(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn serve(stream: TcpStream, handler: &Handler, requests: &Mutex<Vec<Request>>) {
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+
+    while let Some(request) = read_request(&mut reader) {
+        let response = if request.method == "HEAD" {
+            Response::json(200, "")
+        } else {
+            let response = handler(&request);
+            requests.lock().unwrap().push(request.clone());
+            response
+        };
+
+        if response.hang_up {
+            return;
+        }
+
+        let body = if request.method == "HEAD" {
+            ""
+        } else {
+            &response.body
+        };
+
+        let headers = response
+            .headers
+            .iter()
+            .map(|(name, value)| format!("{}: {}\r\n", name, value))
+            .collect::<String>();
+
+        let written = write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}\r\n{}",
+            response.status,
+            reason(response.status),
+            response.content_type,
+            body.len(),
+            headers,
+            body
+        );
+
+        if written.is_err() {
+            return;
+        }
+    }
+}
+
+fn read_request(reader: &mut BufReader<TcpStream>) -> Option<Request> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).ok()? == 0 {
+        return None;
+    }
+
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_owned();
+    let target = parts.next()?.to_owned();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        let (name, value) = line.split_once(':')?;
+        headers.insert(name.to_lowercase(), value.trim().to_owned());
+    }
+
+    let mut body = Vec::new();
+    if let Some(len) = headers.get("content-length") {
+        body.resize(len.parse().ok()?, 0);
+        reader.read_exact(&mut body).ok()?;
+    } else if headers.get("transfer-encoding").map(String::as_str) == Some("chunked") {
+        loop {
+            let mut size = String::new();
+            reader.read_line(&mut size).ok()?;
+            let size = usize::from_str_radix(size.trim(), 16).ok()?;
+
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk).ok()?;
+
+            if size == 0 {
+                break;
+            }
+
+            body.extend_from_slice(&chunk[..size]);
+        }
+    }
+
+    Some(Request {
+        method,
+        target,
+        headers,
+        body,
+    })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        301 => "Moved Permanently",
+        302 => "Found",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        429 => "Too Many Requests",
+        _ => "Unknown",
+    }
+}
@@ -0,0 +1,2167 @@
+//! End-to-end tests that run the patisserie binary against a mock Pastery API.
+
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{self, Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+mod common;
+
+use crate::common::{MockServer, Response};
+
+fn patisserie(url: &str) -> Command {
+    let mut command = without_api_key(url);
+    command.args(["--api-key", "test-key"]);
+    command
+}
+
+fn without_api_key(url: &str) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_patisserie"));
+    command
+        .args(["--hermetic", url])
+        // No configuration file is read unless a test writes one.
+        .env(
+            "XDG_CONFIG_HOME",
+            env::temp_dir().join("patisserie-no-config"),
+        )
+        .env(
+            "XDG_CACHE_HOME",
+            env::temp_dir().join("patisserie-no-cache"),
+        )
+        .env(
+            "XDG_STATE_HOME",
+            env::temp_dir().join("patisserie-no-state"),
+        )
+        .env_remove("PASTERY_API_KEY")
+        .env_remove("PASTERY_API_KEY_CMD")
+        .env_remove("PATISSERIE_BACKEND")
+        // Messages are checked in English, whatever the language of the machine.
+        .env_remove("LC_ALL")
+        .env_remove("LC_MESSAGES")
+        .env("LANG", "C");
+    command
+}
+
+fn run(command: &mut Command, stdin: &str) -> Output {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // patisserie can exit before it reads its input, for example on a bad argument.
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+
+    child.wait_with_output().unwrap()
+}
+
+/// Write a file in a directory of its own, so that tests running in parallel do not collide.
+fn temp_file(test: &str, name: &str, contents: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("patisserie-{}-{}", test, process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn upload_a_file() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+    let path = temp_file("upload_a_file", "main.rs", "fn main() {}\n");
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["--lang", "rust", "--max-views", "burn"])
+            .arg(&path),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://www.pastery.net/abcdef/\n");
+
+    let uploads = server.uploads();
+    assert_eq!(uploads.len(), 1);
+    assert_eq!(uploads[0].method, "POST");
+    assert_eq!(uploads[0].param("api_key").as_deref(), Some("test-key"));
+    assert_eq!(uploads[0].param("title").as_deref(), Some("main.rs"));
+    assert_eq!(uploads[0].param("language").as_deref(), Some("rust"));
+    assert_eq!(uploads[0].param("max_views").as_deref(), Some("1"));
+    assert_eq!(uploads[0].text(), "fn main() {}\n");
+}
+
+#[test]
+fn take_the_lang_from_the_extension() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+    let script = temp_file("take_the_lang_from_the_extension", "setup.py", "print(1)\n");
+    let notes = temp_file("take_the_lang_from_the_extension", "NOTES", "notes\n");
+
+    for path in [&script, &notes] {
+        let output = run(patisserie(&server.url).arg(path), "");
+        assert!(output.status.success(), "{}", stderr(&output));
+    }
+    // --lang takes precedence.
+    let output = run(
+        patisserie(&server.url)
+            .args(["--lang", "text"])
+            .arg(&script),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let uploads = server.uploads();
+    assert_eq!(uploads[0].param("language").as_deref(), Some("python"));
+    assert_eq!(uploads[1].param("language").as_deref(), Some("autodetect"));
+    assert_eq!(uploads[2].param("language").as_deref(), Some("text"));
+}
+
+#[test]
+fn give_each_file_a_lang() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+    let log = temp_file("give_each_file_a_lang", "build.log", "ok\n");
+    let script = temp_file("give_each_file_a_lang", "main.py", "print(1)\n");
+    let odd = temp_file("give_each_file_a_lang", "a=rust", "fn main() {}\n");
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["--lang", "c"])
+            .arg(format!("{}=text", log.display()))
+            .arg(&script)
+            .arg(&odd)
+            .arg("--file-lang")
+            .arg(format!("{}=python", script.display())),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let mut langs = server
+        .uploads()
+        .iter()
+        .map(|upload| (upload.param("title"), upload.param("language")))
+        .collect::<Vec<_>>();
+    langs.sort();
+    assert_eq!(
+        langs,
+        [
+            (Some("a=rust".into()), Some("c".into())),
+            (Some("build.log".into()), Some("text".into())),
+            (Some("main.py".into()), Some("python".into())),
+        ]
+    );
+}
+
+#[test]
+fn refuse_unknown_langs() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+
+    let output = run(
+        patisserie(&server.url).args(["--lang", "pyton"]),
+        "print(1)\n",
+    );
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Unknown language pyton, did you mean python?"));
+
+    let output = run(patisserie(&server.url).args(["--lang", "klingon"]), "text");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Unknown language klingon"));
+    assert!(!stderr(&output).contains("did you mean"));
+
+    assert!(server.uploads().is_empty());
+}
+
+#[test]
+fn refresh_languages() {
+    let server = MockServer::start(|request| match request.target.as_str() {
+        "/api/languages/" => Response::json(200, r#"["klingon", "rust"]"#),
+        _ => Response::paste("https://www.pastery.net/abcdef/"),
+    });
+    let cache_home = env::temp_dir().join(format!("patisserie-refresh-{}", process::id()));
+    let cached = || {
+        let mut command = patisserie(&server.url);
+        command.env("XDG_CACHE_HOME", &cache_home);
+        command
+    };
+
+    let output = run(cached().args(["--lang", "klingon"]), "text");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("run patisserie --refresh-languages"));
+
+    let output = run(cached().arg("--refresh-languages"), "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(
+        stderr(&output).contains("Pastery knows 2 languages, 1 of which is new since this build.")
+    );
+    assert!(cache_home.join("patisserie/languages.json").exists());
+
+    let output = run(cached().args(["--lang", "klingon"]), "text");
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let requests = server.uploads();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[1].param("language").as_deref(), Some("klingon"));
+}
+
+#[test]
+fn take_the_lang_from_the_shebang() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+    let script = temp_file(
+        "take_the_lang_from_the_shebang",
+        "deploy",
+        "#!/usr/bin/env python3\nprint(1)\n",
+    );
+
+    let output = run(patisserie(&server.url).arg(&script), "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = run(&mut patisserie(&server.url), "#!/bin/sh\necho hi\n");
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let uploads = server.uploads();
+    assert_eq!(uploads[0].param("language").as_deref(), Some("python"));
+    assert_eq!(uploads[1].param("language").as_deref(), Some("bash"));
+}
+
+#[cfg(feature = "detect")]
+#[test]
+fn guess_the_lang_from_the_content() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+
+    let output = run(
+        &mut patisserie(&server.url),
+        "package main\n\nfunc main() {\n\tfmt.Println(\"hi\")\n}\n",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let output = run(&mut patisserie(&server.url), "hello\n");
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let uploads = server.uploads();
+    assert_eq!(uploads[0].param("language").as_deref(), Some("go"));
+    assert_eq!(uploads[1].param("language").as_deref(), Some("autodetect"));
+}
+
+#[test]
+fn stream_stdin() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/stdin/"));
+
+    let output = run(&mut patisserie(&server.url), "hello from a pipe\n");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://www.pastery.net/stdin/\n");
+
+    let uploads = server.uploads();
+    assert_eq!(
+        uploads[0]
+            .headers
+            .get("transfer-encoding")
+            .map(String::as_str),
+        Some("chunked")
+    );
+    assert_eq!(uploads[0].text(), "hello from a pipe\n");
+    assert_eq!(
+        uploads[0].param("title").as_deref(),
+        Some("hello from a pipe")
+    );
+
+    let output = run(
+        patisserie(&server.url).args(["--stdin-title", "stdin {user}"]),
+        "x",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(server.uploads()[1]
+        .param("title")
+        .unwrap()
+        .starts_with("stdin "));
+}
+
+#[test]
+fn report_api_errors() {
+    let server = MockServer::start(|_| Response::json(200, r#"{"error_msg": "Invalid API key."}"#));
+
+    let output = run(&mut patisserie(&server.url), "text");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).contains("Pastery rejected the paste: Invalid API key."));
+    assert!(stderr(&output).contains("hint: check the key given with --api-key"));
+}
+
+#[test]
+fn report_recent_errors_in_bug_reports() {
+    let server = MockServer::start(|request| match request.param("title") {
+        Some(title) if title.ends_with(" bug report") => {
+            Response::paste("https://www.pastery.net/abcdef/")
+        }
+        _ => Response::json(200, r#"{"error_msg": "Invalid API key."}"#),
+    });
+    let state = temp_file("bug_reports", "patisserie/errors.log", "");
+    let state_home = state.parent().unwrap().parent().unwrap();
+
+    let output = run(
+        patisserie(&server.url).env("XDG_STATE_HOME", state_home),
+        "text",
+    );
+    assert!(!output.status.success());
+
+    let output = run(
+        patisserie(&server.url)
+            .env("XDG_STATE_HOME", state_home)
+            .arg("report"),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let uploads = server.uploads();
+    let report = uploads[1].text();
+    assert!(report.contains("\nRecent errors:\n  "));
+    assert!(report.contains(" Pastery rejected the paste: Invalid API key. hint: "));
+    assert!(!report.contains("test-key"));
+}
+
+#[test]
+fn translate_messages() {
+    let server = MockServer::start(|_| Response::json(200, r#"{"error_msg": "Invalid API key."}"#));
+
+    let output = run(patisserie(&server.url).env("LANG", "fr_FR.UTF-8"), "text");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).contains("erreur : Pastery a refusé le paste : Invalid API key."));
+    assert!(stderr(&output).contains("conseil : vérifiez la clé"));
+
+    // Languages without a translation are shown in English.
+    let output = run(patisserie(&server.url).env("LC_ALL", "sv_SE.UTF-8"), "text");
+    assert!(stderr(&output).contains("error: Pastery rejected the paste"));
+}
+
+#[test]
+fn report_unexpected_responses() {
+    let server = MockServer::start(|_| Response::html(429, "<h1>Slow down</h1>"));
+
+    let output = run(&mut patisserie(&server.url), "text");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("429 Too Many Requests"));
+}
+
+#[test]
+fn report_redirects_instead_of_dropping_the_paste() {
+    let server = MockServer::start(|request| {
+        let redirect = format!("https://127.0.0.1{}", request.target);
+        Response::redirect(301, &redirect)
+    });
+
+    let output = run(&mut patisserie(&server.url), "text");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("redirected the upload (301 Moved Permanently)"));
+    assert!(stderr(&output).contains("use an https:// URL"));
+    assert!(!stderr(&output).contains("test-key"));
+    assert_eq!(server.uploads().len(), 1);
+}
+
+#[test]
+fn follow_redirects_that_keep_the_paste() {
+    let server = MockServer::start(|request| {
+        if request.target.starts_with("/api/paste/") {
+            Response::redirect(
+                307,
+                &request.target.replace("/api/paste/", "/api/v2/paste/"),
+            )
+        } else {
+            Response::paste("https://www.pastery.net/moved/")
+        }
+    });
+
+    // Scrubbed input is held in memory, so it can be sent again.
+    let output = run(patisserie(&server.url).args(["--scrub", "email"]), "moved");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://www.pastery.net/moved/\n");
+    assert_eq!(server.uploads()[1].text(), "moved");
+    assert!(!server.uploads()[1].headers.contains_key("referer"));
+
+    let output = run(
+        patisserie(&server.url).args(["--scrub", "email", "--no-follow-redirects"]),
+        "moved",
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("--max-redirects"));
+}
+
+#[cfg(all(unix, feature = "screenshot"))]
+#[test]
+fn upload_screenshots_to_the_file_host() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let server = MockServer::start(|_| Response::text(200, "https://0x0.st/abc.png\n"));
+
+    // A stand-in for maim that writes a "screenshot" to the path it is given.
+    let maim = temp_file(
+        "screenshot",
+        "maim",
+        "#!/bin/sh\nfor path; do :; done\nprintf 'PNG' > \"$path\"\n",
+    );
+    fs::set_permissions(&maim, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut command = patisserie(&server.url);
+    command
+        .args(["--file-host", &server.url, "screenshot"])
+        .env("PATH", maim.parent().unwrap())
+        .env("DISPLAY", ":0")
+        .env_remove("WAYLAND_DISPLAY");
+
+    let output = run(&mut command, "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://0x0.st/abc.png\n");
+
+    let upload = &server.uploads()[0];
+    assert!(upload.headers["content-type"].starts_with("multipart/form-data"));
+    assert!(upload.text().contains("filename=\"screenshot.png\""));
+    assert!(upload.text().contains("PNG"));
+    assert!(upload.text().contains("name=\"expires\"\r\n\r\n24\r\n"));
+}
+
+#[cfg(all(unix, feature = "clipwatch"))]
+#[test]
+fn replace_copied_text_with_its_url() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+
+    // Stand-ins for wl-clipboard: the clipboard starts empty, then holds the copied text until
+    // wl-copy replaces it.
+    let clip = temp_file("clipwatch", "clip", "copied text");
+    let dir = clip.parent().unwrap();
+    let wl_paste = temp_file(
+        "clipwatch",
+        "wl-paste",
+        "#!/bin/sh\ncd \"$(dirname \"$0\")\"\n\
+         if [ -f started ]; then cat clip; else touch started; fi\n",
+    );
+    let wl_copy = temp_file(
+        "clipwatch",
+        "wl-copy",
+        "#!/bin/sh\ncat > \"$(dirname \"$0\")/clip\"\n",
+    );
+    for script in [&wl_paste, &wl_copy] {
+        fs::set_permissions(script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let mut command = patisserie(&server.url);
+    command
+        .args([
+            "clipwatch",
+            "--trigger",
+            "size:5",
+            "--interval",
+            "50",
+            "--once",
+        ])
+        .env("PATH", format!("{}:/bin:/usr/bin", dir.display()))
+        .env("WAYLAND_DISPLAY", "wayland-0");
+
+    let output = run(&mut command, "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://www.pastery.net/abcdef/\n");
+    assert_eq!(server.uploads()[0].text(), "copied text");
+    assert_eq!(
+        fs::read_to_string(&clip).unwrap(),
+        "https://www.pastery.net/abcdef/"
+    );
+}
+
+#[cfg(all(unix, feature = "daemon"))]
+#[test]
+fn upload_through_the_daemon() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+    let socket = temp_file("daemon", "unused", "").with_file_name("patisserie.sock");
+
+    let mut daemon = patisserie(&server.url)
+        .args(["--duration", "1h", "daemon", "--socket"])
+        .arg(&socket)
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    while !socket.exists() {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let output = run(
+        patisserie("http://127.0.0.1:1/api/paste/")
+            .args(["--via-daemon", "--lang", "rust", "--daemon-socket"])
+            .arg(&socket),
+        "fn main() {}",
+    );
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://www.pastery.net/abcdef/\n");
+
+    let upload = &server.uploads()[0];
+    assert_eq!(upload.text(), "fn main() {}");
+    assert_eq!(upload.param("language").as_deref(), Some("rust"));
+    assert_eq!(upload.param("duration").as_deref(), Some("60"));
+}
+
+#[cfg(all(unix, feature = "daemon"))]
+#[test]
+fn keep_the_daemon_socket_in_a_private_directory() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let tmp = temp_file("daemon_dir", "unused", "");
+    let uid = fs::metadata(&tmp).unwrap().uid();
+    let tmp = tmp.parent().unwrap();
+    let dir = tmp.join(format!("patisserie-{}", uid));
+    let daemon = || {
+        let mut command = patisserie("http://127.0.0.1:1/api/paste/");
+        command
+            .env("TMPDIR", tmp)
+            .env_remove("XDG_RUNTIME_DIR")
+            .arg("daemon");
+        command
+    };
+
+    // A directory that others can reach could have been made by any of them.
+    fs::create_dir_all(&dir).unwrap();
+    fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).unwrap();
+    let output = run(&mut daemon(), "");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("is not a directory that only you can reach"));
+
+    fs::remove_dir(&dir).unwrap();
+    let mut daemon = daemon().stderr(Stdio::null()).spawn().unwrap();
+    let socket = dir.join("patisserie.sock");
+    while !socket.exists() {
+        thread::sleep(Duration::from_millis(10));
+    }
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+
+    assert_eq!(fs::metadata(&dir).unwrap().mode() & 0o777, 0o700);
+    assert_eq!(fs::metadata(&socket).unwrap().mode() & 0o777, 0o600);
+}
+
+#[cfg(feature = "serve")]
+#[test]
+fn upload_pastes_posted_to_the_gateway() {
+    use std::io::Read;
+    use std::net::TcpStream;
+
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+
+    let mut gateway = patisserie(&server.url)
+        .args(["serve", "--listen", "127.0.0.1:0", "--token", "s3cret"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut line = String::new();
+    BufReader::new(gateway.stderr.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let addr = line
+        .trim()
+        .strip_prefix("Listening on http://")
+        .unwrap()
+        .to_owned();
+
+    let post = |token: &str, headers: &str| {
+        let body = "fn main() {}";
+        let mut stream = TcpStream::connect(&addr).unwrap();
+        write!(
+            stream,
+            "POST /?title=Gateway&lang=rust HTTP/1.1\r\nHost: {}\r\nAuthorization: Bearer {}\r\n\
+             {}Content-Length: {}\r\n\r\n{}",
+            addr,
+            token,
+            headers,
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+
+    let refused = post("wrong", "");
+    // A page in a browser that knows the token still cannot use it.
+    let from_page = post("s3cret", "Origin: https://attacker.example.com\r\n");
+    let created = post("s3cret", "");
+    gateway.kill().unwrap();
+    gateway.wait().unwrap();
+
+    assert!(refused.starts_with("HTTP/1.1 401 "), "{}", refused);
+    assert!(from_page.starts_with("HTTP/1.1 403 "), "{}", from_page);
+    assert!(created.starts_with("HTTP/1.1 201 "), "{}", created);
+    assert!(created.ends_with("\r\n\r\nhttps://www.pastery.net/abcdef/\n"));
+
+    let uploads = server.uploads();
+    assert_eq!(uploads.len(), 1);
+    assert_eq!(uploads[0].text(), "fn main() {}");
+    assert_eq!(uploads[0].param("title").as_deref(), Some("Gateway"));
+    assert_eq!(uploads[0].param("language").as_deref(), Some("rust"));
+}
+
+#[cfg(feature = "serve")]
+#[test]
+fn exit_the_gateway_when_idle() {
+    let mut gateway = patisserie("http://127.0.0.1:1/api/paste/")
+        .args(["serve", "--listen", "127.0.0.1:0", "--idle-timeout", "1"])
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    for _ in 0..100 {
+        if let Some(status) = gateway.try_wait().unwrap() {
+            assert!(status.success());
+            return;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    gateway.kill().unwrap();
+    panic!("the gateway was still running after 5s");
+}
+
+#[cfg(feature = "serve")]
+#[test]
+fn refuse_to_serve_others_without_a_token() {
+    let output = run(
+        patisserie("http://127.0.0.1:1/api/paste/").args(["serve", "--listen", "0.0.0.0:0"]),
+        "",
+    );
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("give a --token"));
+}
+
+#[test]
+fn upload_files_added_to_a_watched_directory() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+    let old = temp_file("watch_dir", "old.log", "old");
+    let dir = old.parent().unwrap().to_owned();
+
+    let mut watcher = patisserie(&server.url)
+        .args([
+            "watch-dir",
+            "--pattern",
+            "*.log",
+            "--settle",
+            "100",
+            "--interval",
+            "50",
+        ])
+        .arg(&dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Files are only looked for once the watcher has said that it is watching.
+    let mut line = String::new();
+    BufReader::new(watcher.stderr.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+
+    fs::write(dir.join("notes.txt"), "ignored").unwrap();
+    fs::write(dir.join("crash.log"), "segfault").unwrap();
+
+    let mut line = String::new();
+    BufReader::new(watcher.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    watcher.kill().unwrap();
+    watcher.wait().unwrap();
+
+    assert_eq!(
+        line,
+        format!(
+            "{} → https://www.pastery.net/abcdef/\n",
+            dir.join("crash.log").display()
+        )
+    );
+    let uploads = server.uploads();
+    assert_eq!(uploads.len(), 1);
+    assert_eq!(uploads[0].text(), "segfault");
+}
+
+#[test]
+fn upload_batches_from_a_manifest() {
+    let server = MockServer::start(|request| match request.param("title").as_deref() {
+        Some("Build log") => Response::paste("https://www.pastery.net/abcdef/"),
+        _ => Response::paste("https://www.pastery.net/ghijkl/"),
+    });
+    let manifest = temp_file(
+        "batch",
+        "manifest.toml",
+        "# Attached to the release.\n[[paste]]\npath = \"build.log\"\ntitle = \"Build log\"\n\
+         duration = \"1h\"\n\n[[paste]]\npath = \"src/main.rs\"\nlang = \"rust\"\n",
+    );
+    let dir = manifest.parent().unwrap();
+    fs::write(dir.join("build.log"), "ok").unwrap();
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["batch", "--write"])
+            .arg(&manifest),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "{} → https://www.pastery.net/abcdef/\n{} → https://www.pastery.net/ghijkl/\n",
+            dir.join("build.log").display(),
+            dir.join("src/main.rs").display()
+        )
+    );
+
+    // The files are uploaded side by side, so the uploads can arrive in either order.
+    let uploads = server.uploads();
+    let titled = |title| {
+        uploads
+            .iter()
+            .find(|upload| upload.param("title").as_deref() == Some(title))
+            .unwrap()
+    };
+    assert_eq!(titled("Build log").param("duration").as_deref(), Some("60"));
+    assert_eq!(titled("main.rs").param("language").as_deref(), Some("rust"));
+
+    let written = fs::read_to_string(&manifest).unwrap();
+    assert!(written.starts_with("# Attached to the release.\n"));
+    assert!(written.contains("url = \"https://www.pastery.net/abcdef/\""));
+    assert!(written.contains("url = \"https://www.pastery.net/ghijkl/\""));
+}
+
+#[test]
+fn upload_directories_recursively() {
+    let server = MockServer::start(|request| {
+        Response::paste(&format!(
+            "https://www.pastery.net/{}/",
+            request.param("title").unwrap().replace('.', "-")
+        ))
+    });
+    let main = temp_file("recursive", "project/src/main.rs", "fn main() {}");
+    let project = main.parent().unwrap().parent().unwrap();
+    fs::write(project.join("README.md"), "# Project").unwrap();
+    fs::write(project.join("notes.txt"), "private").unwrap();
+    fs::write(project.join(".patisserieignore"), "*.txt\n").unwrap();
+    fs::create_dir_all(project.join("target")).unwrap();
+    fs::write(project.join("target/app"), "built").unwrap();
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["--recursive", "--exclude", "README.md"])
+            .arg(project),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://www.pastery.net/main-rs/\n");
+    assert_eq!(server.uploads().len(), 1);
+
+    fs::write(project.join(".gitignore"), "src/\n").unwrap();
+    let output = run(
+        patisserie(&server.url)
+            .args(["--recursive", "--no-ignore", "--exclude", "README.md"])
+            .arg(project),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("https://www.pastery.net/notes-txt/"));
+    assert!(stdout(&output).contains("https://www.pastery.net/main-rs/"));
+    assert!(!stdout(&output).contains("app"));
+    assert_eq!(server.uploads().len(), 3);
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["--include", "*.rs"])
+            .arg(project),
+        "",
+    );
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("is a directory; use --recursive"));
+    assert_eq!(server.uploads().len(), 3);
+}
+
+#[cfg(feature = "render")]
+#[test]
+fn upload_snippets_as_images() {
+    let server = MockServer::start(|_| Response::text(200, "https://0x0.st/snippet.png"));
+    let path = temp_file("as_image", "main.rs", "fn main() {}\n");
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["--file-host", &server.url, "--as-image"])
+            .arg(&path),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://0x0.st/snippet.png\n");
+
+    let body = &server.uploads()[0].body;
+    assert!(body.windows(8).any(|bytes| bytes == b"\x89PNG\r\n\x1a\n"));
+}
+
+#[cfg(feature = "render")]
+#[test]
+fn upload_snippets_as_html() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+
+    let output = run(
+        patisserie(&server.url).args(["--lang", "python", "--as-html"]),
+        "print('<hi>')\n",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let upload = &server.uploads()[0];
+    assert_eq!(upload.param("language").as_deref(), Some("html"));
+    assert!(upload.text().starts_with("<!DOCTYPE html>\n"));
+    assert!(upload.text().contains("&lt;hi&gt;"));
+
+    // The theme comes from the configuration file unless --theme is given.
+    let config = temp_file(
+        "as_html",
+        "patisserie/config.toml",
+        "theme = \"Nonesuch\"\n",
+    );
+    let configured = || {
+        let mut command = patisserie(&server.url);
+        command
+            .env(
+                "XDG_CONFIG_HOME",
+                config.parent().unwrap().parent().unwrap(),
+            )
+            .env_remove("PATISSERIE_PROFILE")
+            .arg("--as-html");
+        command
+    };
+
+    let output = run(&mut configured(), "text");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Unknown theme Nonesuch"));
+
+    let output = run(configured().args(["--theme", "InspiredGitHub"]), "text");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(server.uploads().len(), 2);
+}
+
+#[test]
+fn convert_notebooks() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+    let path = temp_file(
+        "notebook",
+        "analysis.ipynb",
+        r#"{"cells": [{"cell_type": "code", "source": ["x = 1"], "outputs": []}], "metadata": {}}"#,
+    );
+
+    let output = run(patisserie(&server.url).arg(&path), "");
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let output = run(
+        patisserie(&server.url).args(["--notebook", "script"]),
+        r#"{"cells": [{"cell_type": "code", "source": "y = 2", "outputs": []}]}"#,
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let uploads = server.uploads();
+    assert_eq!(uploads[0].param("language").as_deref(), Some("markdown"));
+    assert_eq!(uploads[0].text(), "```python\nx = 1\n```\n\n");
+    assert_eq!(uploads[1].param("language").as_deref(), Some("python"));
+    assert_eq!(uploads[1].text(), "# %%\ny = 2\n\n");
+}
+
+#[cfg(feature = "cast")]
+#[test]
+fn upload_terminal_recordings() {
+    let server = MockServer::start(|request| {
+        assert_eq!(request.target, "/api/asciicasts");
+        Response::json(
+            201,
+            r#"{"url": "https://asciinema.org/a/abc123", "message": "View it at the URL"}"#,
+        )
+    });
+    let path = temp_file(
+        "cast",
+        "demo.cast",
+        "{\"version\": 2, \"width\": 80, \"height\": 24, \"title\": \"demo\"}\n\
+         [0.5, \"o\", \"hello\"]\n",
+    );
+    let asciinema = server.url.trim_end_matches("api/paste/");
+
+    let output = run(
+        patisserie(&server.url)
+            .args([
+                "cast",
+                "--server",
+                asciinema,
+                "--install-id",
+                "my-install-id",
+            ])
+            .arg(&path)
+            .env("USER", "alice"),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://asciinema.org/a/abc123\n");
+
+    let upload = &server.uploads()[0];
+    // alice:my-install-id
+    assert_eq!(
+        upload.headers["authorization"],
+        "Basic YWxpY2U6bXktaW5zdGFsbC1pZA=="
+    );
+    assert!(upload.text().contains("filename=\"ascii.cast\""));
+}
+
+#[cfg(feature = "archive")]
+#[test]
+fn upload_archives() {
+    let server = MockServer::start(|_| Response::text(200, "https://0x0.st/repro.tar.gz"));
+    let path = temp_file("archive", "repro/main.rs", "fn main() {}\n");
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["--file-host", &server.url, "--archive"])
+            .arg(path.parent().unwrap()),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://0x0.st/repro.tar.gz\n");
+    assert!(stderr(&output).starts_with("Packed 1 file into repro.tar.gz: "));
+
+    let body = &server.uploads()[0].body;
+    assert!(body.windows(2).any(|bytes| bytes == b"\x1f\x8b"));
+}
+
+#[test]
+fn shorten_urls() {
+    let server = MockServer::start(|request| match request.method.as_str() {
+        "GET" => {
+            assert_eq!(
+                request.param("long").as_deref(),
+                Some("https://www.pastery.net/abcdef/")
+            );
+            Response::text(200, "https://short.example/x\n")
+        }
+        _ => Response::paste("https://www.pastery.net/abcdef/"),
+    });
+    let shortener = format!("{}?long={{url}}", server.url);
+
+    let output = run(
+        patisserie(&server.url).args(["--shorten", "--shortener", &shortener]),
+        "text",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://short.example/x\n");
+}
+
+#[test]
+fn send_from_a_source_address() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+
+    let output = run(
+        patisserie(&server.url).args(["--source-ip", "127.0.0.1"]),
+        "text",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(server.uploads().len(), 1);
+
+    // An address from TEST-NET-1, which this machine does not have.
+    let output = run(
+        patisserie(&server.url).args(["--source-ip", "192.0.2.1"]),
+        "text",
+    );
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Cannot send from 192.0.2.1"));
+    assert_eq!(server.uploads().len(), 1);
+}
+
+#[test]
+fn record_requests_in_a_har_file() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+    let har = temp_file("har", "requests.har", "");
+
+    let output = run(patisserie(&server.url).arg("--har").arg(&har), "hello");
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let text = fs::read_to_string(&har).unwrap();
+    assert!(!text.contains("test-key"));
+
+    let log: serde_json::Value = serde_json::from_str(&text).unwrap();
+    let entries = log["log"]["entries"].as_array().unwrap();
+    let upload = entries
+        .iter()
+        .find(|entry| entry["request"]["method"] == "POST")
+        .unwrap();
+    assert!(upload["request"]["url"]
+        .as_str()
+        .unwrap()
+        .contains("api_key=(redacted)"));
+    assert_eq!(upload["request"]["postData"]["text"], "hello");
+    assert_eq!(upload["response"]["status"], 200);
+    assert!(upload["response"]["content"]["text"]
+        .as_str()
+        .unwrap()
+        .contains("https://www.pastery.net/abcdef/"));
+}
+
+#[test]
+fn keep_the_api_key_out_of_network_errors() {
+    // Nothing is listening once the listener is dropped, so the connection is refused.
+    let url = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}/api/paste/", listener.local_addr().unwrap())
+    };
+
+    let output = run(&mut patisserie(&url), "text");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Could not reach Pastery"));
+    assert!(!stderr(&output).contains("test-key"));
+}
+
+#[test]
+fn upload_inline_batch_pastes() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+    let manifest = temp_file(
+        "batch_inline",
+        "manifest.toml",
+        "[[paste]]\ncontent = \"Built from 4f2a9c1.\"\ntitle = \"Release notes\"\n\
+         max-views = 10\n",
+    );
+
+    let output = run(patisserie(&server.url).arg("batch").arg(&manifest), "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "{}#1 → https://www.pastery.net/abcdef/\n",
+            manifest.display()
+        )
+    );
+
+    let uploads = server.uploads();
+    assert_eq!(uploads[0].text(), "Built from 4f2a9c1.");
+    assert_eq!(uploads[0].param("title").as_deref(), Some("Release notes"));
+    assert_eq!(uploads[0].param("max_views").as_deref(), Some("10"));
+
+    fs::write(
+        &manifest,
+        "[[paste]]\npath = \"notes.txt\"\ncontent = \"Built from 4f2a9c1.\"\n",
+    )
+    .unwrap();
+    let output = run(patisserie(&server.url).arg("batch").arg(&manifest), "");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("paste 1 has both a path and content"));
+    assert_eq!(server.uploads().len(), 1);
+}
+
+#[test]
+fn upload_batch_entries_in_parallel() {
+    // The slow paste is only answered once the fast one has been sent, which it never would be
+    // if the entries were uploaded one at a time.
+    let fast_sent = Arc::new(AtomicBool::new(false));
+    let server = MockServer::start({
+        let fast_sent = fast_sent.clone();
+        move |request| {
+            let title = request.param("title").unwrap();
+            if title == "fast.txt" {
+                fast_sent.store(true, Ordering::SeqCst);
+            }
+            for _ in 0..50 {
+                if fast_sent.load(Ordering::SeqCst) {
+                    return Response::paste(&format!("https://www.pastery.net/{}/", title));
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+            Response::text(500, "")
+        }
+    });
+    let manifest = temp_file(
+        "batch_jobs",
+        "manifest.toml",
+        "[[paste]]\npath = \"slow.txt\"\n\n[[paste]]\npath = \"fast.txt\"\n",
+    );
+    let dir = manifest.parent().unwrap();
+    fs::write(dir.join("slow.txt"), "slow").unwrap();
+    fs::write(dir.join("fast.txt"), "fast").unwrap();
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["--jobs", "2", "--yes", "batch"])
+            .arg(&manifest),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "{} → https://www.pastery.net/slow.txt/\n{} → https://www.pastery.net/fast.txt/\n",
+            dir.join("slow.txt").display(),
+            dir.join("fast.txt").display()
+        )
+    );
+}
+
+#[test]
+fn write_batch_results_as_json() {
+    let server = MockServer::start(|request| {
+        let id = request.param("title").unwrap().replace('.', "-");
+        Response::json(
+            200,
+            &format!(
+                r#"{{"id": "{0}", "url": "https://www.pastery.net/{0}/"}}"#,
+                id
+            ),
+        )
+    });
+    let main = temp_file("results", "main.rs", "fn main() {}");
+    let log = temp_file("results", "build.log", "ok");
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["--results-file", "-", "--yes"])
+            .arg(&main)
+            .arg(&log),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let results: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let paste = &results[main.to_str().unwrap()];
+    assert_eq!(paste["url"], "https://www.pastery.net/main-rs/");
+    assert_eq!(paste["id"], "main-rs");
+    assert!(paste["expires"].is_string());
+    assert_eq!(
+        results[log.to_str().unwrap()]["url"],
+        "https://www.pastery.net/build-log/"
+    );
+
+    // The usual output is kept when the results go to a file.
+    let file = main.with_file_name("results.json");
+    let output = run(
+        patisserie(&server.url)
+            .arg("--results-file")
+            .arg(&file)
+            .arg(&main),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://www.pastery.net/main-rs/\n");
+    let results: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+    assert_eq!(
+        results[main.to_str().unwrap()]["url"],
+        "https://www.pastery.net/main-rs/"
+    );
+}
+
+#[test]
+fn summarize_uploads_as_markdown() {
+    let server = MockServer::start(|request| {
+        Response::paste(&format!(
+            "https://www.pastery.net/{}/",
+            request.param("title").unwrap().replace('.', "-")
+        ))
+    });
+    let main = temp_file("summary", "main.rs", "fn main() {}");
+    let log = temp_file("summary", "build.log", "ok");
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["--summary", "markdown", "--yes"])
+            .arg(&main)
+            .arg(&log),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "- [{}](https://www.pastery.net/main-rs/)\n- [{}](https://www.pastery.net/build-log/)\n",
+            main.display(),
+            log.display()
+        )
+    );
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["--summary", "markdown-table", "--lang", "rust"])
+            .arg(&main),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "| File | Language | Expires |\n| --- | --- | --- |\n\
+             | [{}](https://www.pastery.net/main-rs/) | rust | 1d |\n",
+            main.display()
+        )
+    );
+
+    // The JSON would not parse with the summary printed after it.
+    let output = run(
+        patisserie(&server.url)
+            .args(["--summary", "markdown", "--results-file", "-"])
+            .arg(&main),
+        "",
+    );
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("--summary and --results-file - both print to standard output")
+    );
+    assert_eq!(server.uploads().len(), 3);
+}
+
+#[test]
+fn keep_editor_pastes_cancelled_in_flight() {
+    let started = Arc::new(AtomicBool::new(false));
+    let server = MockServer::start({
+        let started = started.clone();
+        move |_| {
+            started.store(true, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(300));
+            Response::paste("https://www.pastery.net/abcdef/")
+        }
+    });
+
+    let mut child = patisserie(&server.url)
+        .arg("serve-editor")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut response = |id: u64| loop {
+        let message: serde_json::Value =
+            serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        if message["id"] == id {
+            break message;
+        }
+    };
+
+    writeln!(
+        stdin,
+        r#"{{"jsonrpc":"2.0","id":1,"method":"createPaste","params":{{"content":"x"}}}}"#
+    )
+    .unwrap();
+    for _ in 0..50 {
+        if started.load(Ordering::SeqCst) {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    writeln!(
+        stdin,
+        r#"{{"jsonrpc":"2.0","method":"$/cancelRequest","params":{{"id":1}}}}"#
+    )
+    .unwrap();
+
+    // The paste was created before the cancellation could stop it, so the editor is told where.
+    let cancelled = response(1);
+    assert_eq!(cancelled["error"]["code"], -32800);
+    assert_eq!(
+        cancelled["error"]["data"]["url"],
+        "https://www.pastery.net/abcdef/"
+    );
+
+    writeln!(stdin, r#"{{"jsonrpc":"2.0","id":2,"method":"getHistory"}}"#).unwrap();
+    assert_eq!(
+        response(2)["result"][0]["url"],
+        "https://www.pastery.net/abcdef/"
+    );
+
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn print_batch_urls_in_order_when_responses_are_slow() {
+    let server = MockServer::start(|request| {
+        let title = request.param("title").unwrap();
+        if title == "slow.txt" {
+            thread::sleep(Duration::from_millis(500));
+        }
+
+        Response::paste(&format!("https://www.pastery.net/{}/", title))
+    });
+
+    let slow = temp_file("batch", "slow.txt", "slow");
+    let fast = temp_file("batch", "fast.txt", "fast");
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["--jobs", "2"])
+            .arg(&slow)
+            .arg(&fast),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "{} → https://www.pastery.net/slow.txt/\n{} → https://www.pastery.net/fast.txt/\n",
+            slow.display(),
+            fast.display()
+        )
+    );
+
+    // Every paste gets its own key, sharing the prefix for this invocation.
+    let keys = server
+        .uploads()
+        .iter()
+        .map(|upload| upload.headers["idempotency-key"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(keys.len(), 2);
+    assert_ne!(keys[0], keys[1]);
+    assert_eq!(keys[0][..32], keys[1][..32]);
+}
+
+#[test]
+fn send_pastes_again_when_the_response_is_lost() {
+    let path = temp_file("retry", "notes.txt", "notes");
+
+    // The first upload is created but its response is lost; the second is never created.
+    for (created, posts) in [(true, 1), (false, 2)] {
+        let attempts = AtomicUsize::new(0);
+        let server = MockServer::start(move |request| {
+            if request.method == "GET" {
+                let pastes = if created {
+                    format!(
+                        r#"[{{"id": "lost", "title": "notes", "url": "https://www.pastery.net/lost/",
+                            "language": "text", "created": "{}", "size": 5}}]"#,
+                        chrono::Utc::now().to_rfc3339()
+                    )
+                } else {
+                    "[]".into()
+                };
+                return Response::json(200, &format!(r#"{{"pastes": {}}}"#, pastes));
+            }
+
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 => Response::hang_up(),
+                _ => Response::paste("https://www.pastery.net/sent/"),
+            }
+        });
+
+        let output = run(
+            patisserie(&server.url)
+                .args(["--title", "notes"])
+                .arg(&path),
+            "",
+        );
+        assert!(output.status.success(), "{}", stderr(&output));
+
+        let uploads = server.uploads();
+        let sent = uploads
+            .iter()
+            .filter(|upload| upload.method == "POST")
+            .collect::<Vec<_>>();
+        assert_eq!(sent.len(), posts);
+        if created {
+            assert_eq!(stdout(&output), "https://www.pastery.net/lost/\n");
+        } else {
+            assert_eq!(stdout(&output), "https://www.pastery.net/sent/\n");
+            assert!(stderr(&output).contains("sending the paste again"));
+            assert_eq!(
+                sent[0].headers["idempotency-key"],
+                sent[1].headers["idempotency-key"]
+            );
+        }
+    }
+}
+
+#[test]
+fn time_out_waiting_for_stdin() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/late/"));
+
+    let mut child = patisserie(&server.url)
+        .args(["--stdin-timeout", "1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Hold standard input open without writing to it.
+    let _stdin = child.stdin.take();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("Nothing was read from standard input within 1s"));
+    assert!(server.uploads().is_empty());
+
+    let output = run(
+        patisserie(&server.url).args(["--stdin-timeout", "5"]),
+        "on time",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(server.uploads()[0].text(), "on time");
+}
+
+#[test]
+fn refuse_empty_input() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/empty/"));
+
+    let output = run(&mut patisserie(&server.url), "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("--allow-empty"));
+    assert!(server.uploads().is_empty());
+
+    let output = run(patisserie(&server.url).arg("--allow-empty"), "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(server.uploads().len(), 1);
+}
+
+#[test]
+fn download_pastes_into_a_directory() {
+    let server = MockServer::start(|request| match request.target.split('?').next() {
+        Some("/api/paste/abcdef/") => Response::json(
+            200,
+            r#"{"pastes": [{"id": "abcdef", "title": "build.log", "language": "text",
+                "duration": 1440, "body": "ok\n"}]}"#,
+        ),
+        _ => Response::json(
+            200,
+            r#"{"pastes": [
+                {"id": "ghijkl", "title": "build.log", "language": "text", "body": "failed\n"},
+                {"id": "abcdef", "title": "build.log", "language": "text", "body": "ok\n"},
+                {"id": "mnopqr", "title": "", "language": "rust", "body": "fn main() {}"}
+            ]}"#,
+        ),
+    });
+    let dir = temp_file("get", "pastes/.keep", "")
+        .parent()
+        .unwrap()
+        .to_owned();
+
+    let output = run(patisserie(&server.url).args(["get", "abcdef"]), "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "ok\n");
+    assert_eq!(
+        server.uploads()[0].param("api_key").as_deref(),
+        Some("test-key")
+    );
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["get", "https://www.pastery.net/abcdef/", "ghijkl", "mnopqr"])
+            .arg("--output-dir")
+            .arg(&dir),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        server.uploads()[1].target.split('?').next(),
+        Some("/api/paste/abcdef,ghijkl,mnopqr/")
+    );
+    assert_eq!(fs::read_to_string(dir.join("build.log")).unwrap(), "ok\n");
+    assert_eq!(
+        fs::read_to_string(dir.join("build.log-ghijkl")).unwrap(),
+        "failed\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.join("mnopqr")).unwrap(),
+        "fn main() {}"
+    );
+    assert!(stderr(&output).contains(&format!("mnopqr → {}", dir.join("mnopqr").display())));
+
+    // Files that are already there are kept, and the paste goes next to them under its ID.
+    let again = || {
+        run(
+            patisserie(&server.url)
+                .args(["get", "abcdef", "--output-dir"])
+                .arg(&dir),
+            "",
+        )
+    };
+    let output = again();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        fs::read_to_string(dir.join("build.log-abcdef")).unwrap(),
+        "ok\n"
+    );
+    let output = again();
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Could not write"));
+    assert_eq!(fs::read_to_string(dir.join("build.log")).unwrap(), "ok\n");
+
+    let output = run(
+        patisserie(&server.url).args(["get", "abcdef", "ghijkl", "-o", "out.log"]),
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("--output takes a single paste"));
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["get", "abcdef", "-o", "-"])
+            .current_dir(&dir),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "ok\n");
+    assert!(!dir.join("-").exists());
+}
+
+#[test]
+fn delete_pastes() {
+    let server = MockServer::start(|request| match request.target.split('?').next() {
+        Some("/api/paste/abcdef/") | Some("/api/paste/ghijkl/") => {
+            Response::json(200, r#"{"result": "success"}"#)
+        }
+        _ => Response::json(
+            404,
+            r#"{"result": "error", "error_msg": "Paste not found."}"#,
+        ),
+    });
+
+    // Nobody can be asked outside a terminal, so nothing is deleted without --yes.
+    let output = run(patisserie(&server.url).args(["delete", "abcdef"]), "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("give --yes"));
+    assert!(server.uploads().is_empty());
+
+    let output = run(
+        patisserie(&server.url).args([
+            "--yes",
+            "delete",
+            "abcdef",
+            "https://www.pastery.net/ghijkl/",
+        ]),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Deleted abcdef"));
+    assert!(stderr(&output).contains("Deleted ghijkl"));
+    let uploads = server.uploads();
+    assert_eq!(uploads[0].method, "DELETE");
+    assert_eq!(
+        uploads[1].target.split('?').next(),
+        Some("/api/paste/ghijkl/")
+    );
+    assert_eq!(uploads[1].param("api_key").as_deref(), Some("test-key"));
+
+    let output = run(
+        patisserie(&server.url).args(["--yes", "delete", "mnopqr"]),
+        "",
+    );
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Paste not found."));
+
+    // A paste elsewhere is never taken for the one on Pastery with the same ID.
+    let output = run(
+        patisserie(&server.url).args(["--yes", "delete", "https://gist.github.com/abcdef"]),
+        "",
+    );
+    assert!(stderr(&output).contains("is not the URL of a paste on https://www.pastery.net"));
+    let output = run(
+        patisserie(&server.url).args(["--backend", "paste.rs", "--yes", "delete", "abcdef"]),
+        "",
+    );
+    assert!(stderr(&output).contains("delete only works with pastes on Pastery, not"));
+    assert_eq!(server.uploads().len(), 3);
+}
+
+#[test]
+fn list_pastes_across_pages() {
+    let server = MockServer::start(|request| match request.param("page").as_deref() {
+        Some("2") => Response::json(
+            200,
+            r#"{"pastes": [{"id": "ghijkl", "title": "main.rs", "language": "rust",
+                "url": "https://www.pastery.net/ghijkl/", "duration": 60,
+                "created": "2026-10-02T12:00:00Z"}]}"#,
+        ),
+        _ => Response::json(
+            200,
+            r#"{"pastes": [
+                {"id": "abcdef", "title": "build.log", "language": "text",
+                 "url": "https://www.pastery.net/abcdef/", "duration": 1440,
+                 "created": "2026-10-01T12:00:00Z"},
+                {"id": "mnopqr", "title": "old.log", "language": "text",
+                 "url": "https://www.pastery.net/mnopqr/",
+                 "created": "2026-09-01T12:00:00Z"}
+            ], "next": "?page=2"}"#,
+        ),
+    });
+
+    let output = run(
+        patisserie(&server.url).args(["list", "--since", "2026-09-15T00:00:00Z"]),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "ID      TITLE      LANG  EXPIRES\n\
+         ------  ---------  ----  -------\n\
+         ghijkl  main.rs    rust  1h\n\
+         abcdef  build.log  text  1d\n"
+    );
+
+    let requests = server.uploads();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].param("page").as_deref(), Some("2"));
+    assert_eq!(requests[1].param("api_key").as_deref(), Some("test-key"));
+
+    let output = run(
+        patisserie(&server.url).args(["list", "--format", "ids", "--sort", "expiry"]),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "ghijkl\nabcdef\nmnopqr\n");
+}
+
+#[test]
+fn title_files_in_git_repositories() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+    let path = temp_file("repo-title", "shop/src/net/mod.rs", "pub mod tcp;\n");
+    let repo = path.parent().unwrap().parent().unwrap().parent().unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    };
+    git(&["init", "-q"]);
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "Add the network module"]);
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .unwrap();
+    let rev = String::from_utf8(output.stdout).unwrap().trim().to_owned();
+
+    let output = run(patisserie(&server.url).arg(&path), "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        server.uploads()[0].param("title"),
+        Some(format!("shop/src/net/mod.rs@{}", rev))
+    );
+
+    let output = run(
+        patisserie(&server.url).arg("--no-repo-title").arg(&path),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        server.uploads()[1].param("title").as_deref(),
+        Some("mod.rs")
+    );
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["--title", "Network", "--title-meta", "commit"])
+            .arg(&path),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        server.uploads()[2].param("title"),
+        Some(format!("Network (commit {})", rev))
+    );
+
+    fs::remove_dir_all(repo).unwrap();
+}
+
+#[cfg(feature = "backends")]
+#[test]
+fn upload_gists() {
+    let server = MockServer::start(|request| match request.headers.get("authorization") {
+        Some(token) if token == "Bearer gh-token" => Response::json(
+            201,
+            r#"{"id": "aa5a315d", "html_url": "https://gist.github.com/aa5a315d"}"#,
+        ),
+        _ => Response::json(401, r#"{"message": "Bad credentials"}"#),
+    });
+    let main = temp_file("upload_gists", "src/main.rs", "fn main() {}\n");
+    let readme = temp_file("upload_gists", "README.md", "# Hello\n");
+
+    let gist = || {
+        let mut command = patisserie(&server.url);
+        command
+            .args(["--backend", "gist"])
+            .env_remove("GITHUB_TOKEN");
+        command
+    };
+
+    let output = run(
+        gist()
+            .args(["--github-token", "gh-token", "--public", "--yes"])
+            .args(["--title", "Hello"])
+            .arg(&main)
+            .arg(&readme),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://gist.github.com/aa5a315d\n");
+
+    let uploads = server.uploads();
+    assert_eq!(uploads.len(), 1);
+    let body: serde_json::Value = serde_json::from_str(uploads[0].text()).unwrap();
+    assert_eq!(body["description"], "Hello");
+    assert_eq!(body["public"], true);
+    assert_eq!(body["files"]["main.rs"]["content"], "fn main() {}\n");
+    assert_eq!(body["files"]["README.md"]["content"], "# Hello\n");
+
+    let output = run(gist().args(["--github-token", "wrong"]), "text");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).contains("GitHub Gist rejected the paste: Bad credentials"));
+    assert!(stderr(&output).contains("--github-token"));
+
+    let output = run(&mut gist(), "text");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("requires --github-token"));
+    assert_eq!(server.uploads().len(), 2);
+}
+
+#[cfg(feature = "backends")]
+#[test]
+fn upload_gitlab_snippets() {
+    let server = MockServer::start(|request| match request.headers.get("private-token") {
+        Some(token) if token == "gl-token" => Response::json(
+            201,
+            r#"{"id": 42, "web_url": "https://gitlab.example.com/-/snippets/42"}"#,
+        ),
+        _ => Response::json(401, r#"{"message": "401 Unauthorized"}"#),
+    });
+    let path = temp_file("upload_gitlab_snippets", "main.rs", "fn main() {}\n");
+
+    let gitlab = || {
+        let mut command = patisserie(&server.url);
+        command
+            .args(["--backend", "gitlab"])
+            .env_remove("GITLAB_TOKEN");
+        command
+    };
+
+    let output = run(
+        gitlab()
+            .args(["--gitlab-token", "gl-token", "--visibility", "internal"])
+            .arg(&path),
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "https://gitlab.example.com/-/snippets/42\n"
+    );
+
+    let body: serde_json::Value = serde_json::from_str(server.uploads()[0].text()).unwrap();
+    assert_eq!(body["title"], "main.rs");
+    assert_eq!(body["visibility"], "internal");
+    assert_eq!(body["files"][0]["file_path"], "main.rs");
+    assert_eq!(body["files"][0]["content"], "fn main() {}\n");
+
+    let output = run(gitlab().args(["--gitlab-token", "wrong"]), "text");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).contains("GitLab rejected the paste: 401 Unauthorized"));
+    assert!(stderr(&output).contains("--gitlab-token"));
+}
+
+#[cfg(feature = "backends")]
+#[test]
+fn upload_to_hastebin() {
+    let server = MockServer::start(|request| match request.text() {
+        "too long" => Response::json(400, r#"{"message": "Document exceeds maximum length."}"#),
+        _ => Response::json(200, r#"{"key": "moxiyocate"}"#),
+    });
+
+    let hastebin = || {
+        let mut command = patisserie(&server.url);
+        command
+            .args(["--backend", "hastebin"])
+            .env_remove("HASTEBIN_URL");
+        command
+    };
+
+    let output = run(
+        hastebin()
+            .args(["--hastebin-url", "https://haste.example.com"])
+            .args(["--title", "Hello"]),
+        "hello\n",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://haste.example.com/moxiyocate\n");
+    assert!(stderr(&output).contains("title"));
+    assert_eq!(server.uploads()[0].text(), "hello\n");
+
+    let output = run(
+        hastebin().args(["--hastebin-url", "https://haste.example.com"]),
+        "too long",
+    );
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("hastebin rejected the paste: Document exceeds maximum length.")
+    );
+
+    let output = run(&mut hastebin(), "hello\n");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("requires --hastebin-url"));
+    assert_eq!(server.uploads().len(), 2);
+}
+
+#[test]
+fn upload_to_paste_rs_without_an_api_key() {
+    let server = MockServer::start(|request| match request.text() {
+        "too long" => Response::text(206, "https://paste.rs/Cut\n"),
+        _ => Response::text(201, "https://paste.rs/Ab3\n"),
+    });
+
+    let output = run(&mut without_api_key(&server.url), "hello\n");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://paste.rs/Ab3\n");
+    assert!(stderr(&output).contains("uploaded to paste.rs"));
+    assert_eq!(server.uploads()[0].text(), "hello\n");
+
+    let output = run(
+        without_api_key(&server.url).args(["--backend", "paste.rs"]),
+        "too long",
+    );
+    assert_eq!(output.status.code(), Some(4));
+    assert!(stderr(&output).contains("only part of it was uploaded to https://paste.rs/Cut"));
+    assert!(!stderr(&output).contains("no Pastery API key"));
+
+    let output = run(
+        without_api_key(&server.url).args(["--backend", "pastery"]),
+        "hello\n",
+    );
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("requires --api-key"));
+    assert_eq!(server.uploads().len(), 2);
+}
+
+#[cfg(feature = "backends")]
+#[test]
+fn upload_to_termbin() {
+    use std::io::Read;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut received = String::new();
+        stream.read_to_string(&mut received).unwrap();
+        stream.write_all(b"https://termbin.com/x7k2\n\0").unwrap();
+        received
+    });
+
+    let output = run(
+        without_api_key("http://127.0.0.1:9/")
+            .args(["--backend", "termbin", "--termbin-address", &address])
+            .env_remove("TERMBIN_ADDRESS"),
+        "hello\n",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "https://termbin.com/x7k2\n");
+    assert_eq!(server.join().unwrap(), "hello\n");
+}
+
+#[cfg(feature = "privatebin")]
+#[test]
+fn upload_to_privatebin() {
+    let server = MockServer::start(|request| {
+        match request.headers.get("x-requested-with").map(String::as_str) {
+            Some("JSONHttpRequest") => Response::json(
+                200,
+                r#"{"status": 0, "id": "f468483c313401e8", "url": "/?f468483c313401e8"}"#,
+            ),
+            _ => match request.param("long") {
+                Some(long) => {
+                    assert_eq!(long, "https://bin.example.com/?f468483c313401e8");
+                    Response::text(200, "https://short.example/x\n")
+                }
+                None => Response::html(200, "<html>"),
+            },
+        }
+    });
+
+    let privatebin = || {
+        let mut command = without_api_key(&server.url);
+        command
+            .args(["--backend", "privatebin"])
+            .env_remove("PRIVATEBIN_URL");
+        command
+    };
+
+    let output = run(
+        privatebin()
+            .args(["--privatebin-url", "https://bin.example.com/paste"])
+            .args(["--duration", "2d", "--max-views", "burn"]),
+        "secret\n",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    // The key is only ever in the fragment.
+    let url = stdout(&output);
+    let (url, key) = url.trim_end().split_once('#').unwrap();
+    assert_eq!(url, "https://bin.example.com/paste/?f468483c313401e8");
+    assert!(!key.is_empty());
+
+    let upload = &server.uploads()[0];
+    assert!(!upload.text().contains("secret"));
+    assert!(!upload.text().contains(key));
+    let body: serde_json::Value = serde_json::from_str(upload.text()).unwrap();
+    assert_eq!(body["v"], 2);
+    assert_eq!(body["meta"]["expire"], "1week");
+    assert_eq!(body["adata"][3], 1);
+
+    let output = run(
+        privatebin()
+            .args(["--privatebin-url", "https://bin.example.com/"])
+            .args(["--max-views", "3"]),
+        "secret\n",
+    );
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("--max-views burn"));
+
+    let output = run(&mut privatebin(), "secret\n");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("requires --privatebin-url"));
+    assert_eq!(server.uploads().len(), 1);
+
+    // The shortener is sent the URL without its key, which goes on the short link instead.
+    let har = temp_file("privatebin", "shorten.har", "");
+    let shortener = format!("{}?long={{url}}", server.url);
+    let output = run(
+        privatebin()
+            .args(["--privatebin-url", "https://bin.example.com/"])
+            .args(["--shorten", "--shortener", &shortener])
+            .arg("--har")
+            .arg(&har),
+        "secret\n",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let url = stdout(&output);
+    let (url, key) = url.trim_end().split_once('#').unwrap();
+    assert_eq!(url, "https://short.example/x");
+    assert!(!fs::read_to_string(&har).unwrap().contains(key));
+}
+
+#[cfg(feature = "backends")]
+#[test]
+fn upload_forms() {
+    let server = MockServer::start(|request| {
+        if request
+            .text()
+            .contains("name=\"sprunge\"\r\n\r\nhello\n\r\n")
+        {
+            Response::text(200, "http://sprunge.us/AbCd\n")
+        } else {
+            Response::text(400, "Bad Request: no paste given\n")
+        }
+    });
+
+    let form = |field: &str| {
+        let mut command = without_api_key(&server.url);
+        command
+            .args(["--backend", "form", "--form-url", "http://sprunge.us/"])
+            .args(["--form-field", field])
+            .env_remove("PATISSERIE_FORM_URL")
+            .env_remove("PATISSERIE_FORM_FIELD");
+        command
+    };
+
+    let output = run(&mut form("sprunge"), "hello\n");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "http://sprunge.us/AbCd\n");
+    assert!(server.uploads()[0].headers["content-type"].starts_with("multipart/form-data"));
+
+    let output = run(&mut form("f:1"), "hello\n");
+    assert_eq!(output.status.code(), Some(4));
+    assert!(stderr(&output).contains("rejected the paste: Bad Request: no paste given"));
+}
+
+#[test]
+fn reject_invalid_base_urls() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+
+    for url in [
+        "ftp://pastery.example.com/",
+        "mailto:paste@example.com",
+        "pastery",
+    ] {
+        let output = run(patisserie(&server.url).args(["--base-url", url]), "text");
+        assert!(!output.status.success(), "{} was accepted", url);
+        assert!(
+            stderr(&output).contains("--base-url"),
+            "{}",
+            stderr(&output)
+        );
+    }
+    assert!(server.uploads().is_empty());
+}
+
+#[test]
+fn read_defaults_from_the_config_file() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+    let config = temp_file(
+        "read_defaults_from_the_config_file",
+        "patisserie/config.toml",
+        "api-key = \"config-key\"\nlang = \"rust\"\nduration = \"1w\"\nmax-views = 10\n",
+    );
+    let config_home = config.parent().unwrap().parent().unwrap();
+
+    let configured = || {
+        let mut command = without_api_key(&server.url);
+        command.env("XDG_CONFIG_HOME", config_home);
+        command
+    };
+
+    let output = run(&mut configured(), "text");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let upload = &server.uploads()[0];
+    assert_eq!(upload.param("api_key").as_deref(), Some("config-key"));
+    assert_eq!(upload.param("language").as_deref(), Some("rust"));
+    assert_eq!(upload.param("duration").as_deref(), Some("10080"));
+    assert_eq!(upload.param("max_views").as_deref(), Some("10"));
+
+    // The command line and the environment take precedence.
+    let output = run(
+        configured()
+            .args(["--lang", "c", "--max-views", "2"])
+            .env("PASTERY_API_KEY", "env-key"),
+        "text",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let upload = &server.uploads()[1];
+    assert_eq!(upload.param("api_key").as_deref(), Some("env-key"));
+    assert_eq!(upload.param("language").as_deref(), Some("c"));
+    assert_eq!(upload.param("duration").as_deref(), Some("10080"));
+    assert_eq!(upload.param("max_views").as_deref(), Some("2"));
+
+    fs::write(&config, "lang = \"klingon\"\n").unwrap();
+    let output = run(&mut configured(), "text");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Invalid configuration"));
+    assert!(stderr(&output).contains("unknown language klingon"));
+    assert_eq!(server.uploads().len(), 2);
+}
+
+#[test]
+fn choose_config_profiles() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+    let config = temp_file(
+        "choose_config_profiles",
+        "patisserie/config.toml",
+        "api-key = \"personal-key\"\nlang = \"rust\"\n\n\
+         [profile.work]\napi-key = \"work-key\"\nduration = \"1w\"\n\n\
+         [profile.oss]\nbackend = \"paste.rs\"\n",
+    );
+    let config_home = config.parent().unwrap().parent().unwrap();
+
+    let configured = || {
+        let mut command = without_api_key(&server.url);
+        command
+            .env("XDG_CONFIG_HOME", config_home)
+            .env_remove("PATISSERIE_PROFILE");
+        command
+    };
+
+    let output = run(configured().args(["--profile", "work"]), "text");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let upload = &server.uploads()[0];
+    assert_eq!(upload.param("api_key").as_deref(), Some("work-key"));
+    assert_eq!(upload.param("language").as_deref(), Some("rust"));
+    assert_eq!(upload.param("duration").as_deref(), Some("10080"));
+
+    let output = run(configured().env("PATISSERIE_PROFILE", "work"), "text");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        server.uploads()[1].param("api_key").as_deref(),
+        Some("work-key")
+    );
+
+    let output = run(configured().args(["--profile", "home"]), "text");
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Unknown profile home, expected one of oss, work"));
+    assert_eq!(server.uploads().len(), 2);
+}
+
+#[cfg(feature = "backends")]
+#[test]
+fn upload_with_the_credentials_of_profiles() {
+    let server = MockServer::start(|request| match request.headers.get("private-token") {
+        Some(token) if token == "gl-work" => Response::json(
+            201,
+            r#"{"id": 42, "web_url": "https://gitlab.example.com/-/snippets/42"}"#,
+        ),
+        _ => Response::json(401, r#"{"message": "401 Unauthorized"}"#),
+    });
+    let config = temp_file(
+        "upload_with_the_credentials_of_profiles",
+        "patisserie/config.toml",
+        "gitlab-token = \"gl-personal\"\n\n\
+         [profile.work]\nbackend = \"gitlab\"\ngitlab-token = \"gl-work\"\n",
+    );
+    let config_home = config.parent().unwrap().parent().unwrap();
+
+    let output = run(
+        patisserie(&server.url)
+            .args(["--profile", "work"])
+            .env("XDG_CONFIG_HOME", config_home)
+            .env_remove("GITLAB_TOKEN")
+            .env_remove("PATISSERIE_BACKEND"),
+        "fn main() {}\n",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "https://gitlab.example.com/-/snippets/42\n"
+    );
+    assert_eq!(
+        server.uploads()[0]
+            .headers
+            .get("private-token")
+            .map(String::as_str),
+        Some("gl-work")
+    );
+}
+
+#[test]
+fn read_api_keys_from_a_command() {
+    let server = MockServer::start(|_| Response::paste("https://www.pastery.net/abcdef/"));
+
+    let output = run(
+        without_api_key(&server.url).args(["--api-key-cmd", "printf 'cmd-key\\nlogin: me\\n'"]),
+        "text",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        server.uploads()[0].param("api_key").as_deref(),
+        Some("cmd-key")
+    );
+
+    // A key that is given takes precedence, and the command is not run.
+    let output = run(
+        patisserie(&server.url).args(["--api-key-cmd", "exit 1"]),
+        "text",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        server.uploads()[1].param("api_key").as_deref(),
+        Some("test-key")
+    );
+
+    let output = run(
+        without_api_key(&server.url).args(["--api-key-cmd", "exit 3"]),
+        "text",
+    );
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("--api-key-cmd failed"));
+    assert_eq!(server.uploads().len(), 2);
+}